@@ -0,0 +1,295 @@
+use ethers_core::abi::{Abi, Function, LogParam, RawLog, Token};
+use ethers_core::types::Log as EthersLog;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::block::TransactionExecutionLog;
+use crate::error::EvmError;
+use crate::transaction::TransactionReceiptLog;
+use crate::H160;
+
+/// Errors returned by the [`Contract`] helpers.
+#[derive(Debug, Error, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ContractError {
+    #[error("invalid ABI: {0}")]
+    InvalidAbi(String),
+
+    #[error("function {0} not found in the ABI")]
+    UnknownFunction(String),
+
+    #[error("no event of the ABI matches the log topics")]
+    UnknownEvent,
+
+    #[error("the log was emitted by {0:#x}, not by the contract")]
+    UnexpectedAddress(H160),
+
+    #[error("failed to encode the call: {0}")]
+    Encoding(String),
+
+    #[error("failed to decode: {0}")]
+    Decoding(String),
+}
+
+pub type ContractResult<T> = Result<T, ContractError>;
+
+impl From<ContractError> for EvmError {
+    fn from(err: ContractError) -> Self {
+        match err {
+            ContractError::Decoding(_) => Self::Internal(err.to_string()),
+            _ => Self::BadRequest(err.to_string()),
+        }
+    }
+}
+
+/// An event decoded from a log.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DecodedEvent {
+    /// The name of the event.
+    pub name: String,
+    /// The decoded parameters of the event, in declaration order.
+    pub params: Vec<LogParam>,
+}
+
+impl DecodedEvent {
+    /// Returns the value of the parameter with the given name.
+    pub fn param(&self, name: &str) -> Option<&Token> {
+        self.params
+            .iter()
+            .find(|param| param.name == name)
+            .map(|param| &param.value)
+    }
+}
+
+/// A deployed contract described by its ABI.
+///
+/// Encodes the calls to the contract functions, decodes their output and decodes the
+/// logs emitted by the contract into events.
+/// Overloaded functions are resolved to the first overload declared in the ABI,
+/// and anonymous events are not decoded.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Contract {
+    address: H160,
+    abi: Abi,
+}
+
+impl Contract {
+    /// Creates a new contract.
+    ///
+    /// # Arguments
+    /// * `address` - The address of the contract.
+    /// * `abi` - The ABI of the contract.
+    pub fn new(address: H160, abi: Abi) -> Self {
+        Self { address, abi }
+    }
+
+    /// Creates a new contract from its JSON ABI, as produced by the Solidity compiler.
+    pub fn from_json(address: H160, abi: &str) -> ContractResult<Self> {
+        let abi =
+            serde_json::from_str(abi).map_err(|e| ContractError::InvalidAbi(e.to_string()))?;
+        Ok(Self::new(address, abi))
+    }
+
+    /// Returns the address of the contract.
+    pub fn address(&self) -> &H160 {
+        &self.address
+    }
+
+    /// Returns the ABI of the contract.
+    pub fn abi(&self) -> &Abi {
+        &self.abi
+    }
+
+    /// Returns the function with the given name.
+    pub fn function(&self, name: &str) -> ContractResult<&Function> {
+        self.abi
+            .function(name)
+            .map_err(|_| ContractError::UnknownFunction(name.to_string()))
+    }
+
+    /// Encodes the call of `function` with `args`, including the function selector.
+    pub fn encode_call(&self, function: &str, args: &[Token]) -> ContractResult<Vec<u8>> {
+        self.function(function)?
+            .encode_input(args)
+            .map_err(|e| ContractError::Encoding(e.to_string()))
+    }
+
+    /// Decodes the output of a call of `function`.
+    pub fn decode_output(&self, function: &str, output: &[u8]) -> ContractResult<Vec<Token>> {
+        self.function(function)?
+            .decode_output(output)
+            .map_err(|e| ContractError::Decoding(e.to_string()))
+    }
+
+    /// Decodes a log returned by `eth_getLogs` or included in a receipt.
+    ///
+    /// Fails with [`ContractError::UnexpectedAddress`] if the log was not emitted by the contract.
+    pub fn decode_log(&self, log: &EthersLog) -> ContractResult<DecodedEvent> {
+        self.check_address(&log.address.into())?;
+        self.decode_raw_log(RawLog {
+            topics: log.topics.clone(),
+            data: log.data.to_vec(),
+        })
+    }
+
+    /// Decodes a log of a transaction execution result.
+    pub fn decode_execution_log(
+        &self,
+        log: &TransactionExecutionLog,
+    ) -> ContractResult<DecodedEvent> {
+        self.check_address(&log.address)?;
+        self.decode_raw_log(RawLog {
+            topics: log.topics.iter().map(|topic| topic.0).collect(),
+            data: log.data.0.to_vec(),
+        })
+    }
+
+    /// Decodes a log of a transaction receipt returned by the EVM canister.
+    pub fn decode_receipt_log(&self, log: &TransactionReceiptLog) -> ContractResult<DecodedEvent> {
+        self.check_address(&log.address)?;
+        self.decode_raw_log(RawLog {
+            topics: log.topics.iter().map(|topic| topic.0).collect(),
+            data: log.data.0.to_vec(),
+        })
+    }
+
+    fn check_address(&self, address: &H160) -> ContractResult<()> {
+        if address != &self.address {
+            return Err(ContractError::UnexpectedAddress(address.clone()));
+        }
+        Ok(())
+    }
+
+    fn decode_raw_log(&self, log: RawLog) -> ContractResult<DecodedEvent> {
+        let signature = log.topics.first().ok_or(ContractError::UnknownEvent)?;
+        let event = self
+            .abi
+            .events()
+            .find(|event| !event.anonymous && event.signature() == *signature)
+            .ok_or(ContractError::UnknownEvent)?;
+
+        let decoded = event
+            .parse_log(log)
+            .map_err(|e| ContractError::Decoding(e.to_string()))?;
+
+        Ok(DecodedEvent {
+            name: event.name.clone(),
+            params: decoded.params,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ethers_core::abi::ethabi;
+    use ethers_core::types::{H160 as EthersH160, H256 as EthersH256, U256};
+
+    use super::*;
+    use crate::{Bytes, H256};
+
+    const ERC20_ABI: &str = r#"[
+        {
+            "type": "function",
+            "name": "balanceOf",
+            "stateMutability": "view",
+            "inputs": [{ "name": "owner", "type": "address" }],
+            "outputs": [{ "name": "", "type": "uint256" }]
+        },
+        {
+            "type": "event",
+            "name": "Transfer",
+            "anonymous": false,
+            "inputs": [
+                { "name": "from", "type": "address", "indexed": true },
+                { "name": "to", "type": "address", "indexed": true },
+                { "name": "value", "type": "uint256", "indexed": false }
+            ]
+        }
+    ]"#;
+
+    fn erc20() -> Contract {
+        Contract::from_json(H160::from_slice(&[1; 20]), ERC20_ABI).unwrap()
+    }
+
+    #[test]
+    fn should_encode_call_and_decode_output() {
+        let contract = erc20();
+        let owner = EthersH160::repeat_byte(2);
+
+        let call = contract
+            .encode_call("balanceOf", &[Token::Address(owner)])
+            .unwrap();
+        assert_eq!(
+            &call[..4],
+            &ethabi::short_signature("balanceOf", &[ethabi::ParamType::Address])
+        );
+        assert_eq!(&call[16..], owner.as_bytes());
+
+        let output = ethabi::encode(&[Token::Uint(U256::from(42))]);
+        assert_eq!(
+            contract.decode_output("balanceOf", &output).unwrap(),
+            vec![Token::Uint(42.into())]
+        );
+
+        assert_eq!(
+            contract.encode_call("transfer", &[]),
+            Err(ContractError::UnknownFunction("transfer".to_string()))
+        );
+    }
+
+    #[test]
+    fn should_decode_events() {
+        let contract = erc20();
+        let from = EthersH160::repeat_byte(2);
+        let to = EthersH160::repeat_byte(3);
+        let transfer = contract.abi().event("Transfer").unwrap().signature();
+        let log = TransactionExecutionLog {
+            address: contract.address().clone(),
+            topics: vec![
+                transfer.into(),
+                EthersH256::from(from).into(),
+                EthersH256::from(to).into(),
+            ],
+            data: Bytes::from(ethabi::encode(&[Token::Uint(U256::from(7))])),
+        };
+
+        let event = contract.decode_execution_log(&log).unwrap();
+
+        assert_eq!(event.name, "Transfer");
+        assert_eq!(event.param("from"), Some(&Token::Address(from)));
+        assert_eq!(event.param("to"), Some(&Token::Address(to)));
+        assert_eq!(event.param("value"), Some(&Token::Uint(7.into())));
+
+        let ethers_log = EthersLog {
+            address: contract.address().clone().into(),
+            topics: log.topics.iter().map(|topic| topic.0).collect(),
+            data: log.data.0.to_vec().into(),
+            ..Default::default()
+        };
+        assert_eq!(contract.decode_log(&ethers_log).unwrap(), event);
+
+        let unknown = TransactionExecutionLog {
+            topics: vec![H256::from_slice(&[9; 32])],
+            ..log.clone()
+        };
+        assert_eq!(
+            contract.decode_execution_log(&unknown),
+            Err(ContractError::UnknownEvent)
+        );
+
+        let other = H160::from_slice(&[4; 20]);
+        assert_eq!(
+            contract.decode_log(&EthersLog {
+                address: other.clone().into(),
+                ..ethers_log
+            }),
+            Err(ContractError::UnexpectedAddress(other.clone()))
+        );
+        assert_eq!(
+            contract.decode_execution_log(&TransactionExecutionLog {
+                address: other.clone(),
+                ..log
+            }),
+            Err(ContractError::UnexpectedAddress(other))
+        );
+    }
+}
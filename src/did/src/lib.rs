@@ -9,6 +9,7 @@ pub mod bytes;
 pub mod certified;
 pub mod codec;
 pub mod constant;
+pub mod contract;
 pub mod error;
 pub mod evm_reset_state;
 pub mod gas;
@@ -20,8 +21,11 @@ pub mod keccak;
 pub mod logs;
 pub mod mint_order_exemption;
 pub mod notify;
+#[cfg(feature = "pending-transaction")]
+pub mod pending_transaction;
 pub mod permission;
 pub mod state;
+pub mod trace;
 pub mod transaction;
 
 pub mod fees;
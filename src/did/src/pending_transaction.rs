@@ -0,0 +1,435 @@
+//! Waiting for a sent transaction to be confirmed, independently of the client used to
+//! poll its status.
+
+use std::future::{Future, IntoFuture};
+use std::pin::Pin;
+use std::time::Duration;
+
+use thiserror::Error;
+use tokio::time::Instant;
+
+use crate::{H160, H256, U256};
+
+/// Default interval between two polls of the transaction status.
+pub const DEFAULT_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Default time after which the transaction is no longer awaited.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// Default number of consecutive polls without the transaction after which it is
+/// considered dropped.
+pub const DEFAULT_DROPPED_AFTER_POLLS: u32 = 3;
+
+/// Requests used by a [`PendingTransaction`] to follow the status of a transaction.
+pub trait TransactionStatusProvider: Sync {
+    /// Receipt of a transaction.
+    type Receipt: Send;
+
+    /// Error returned by the requests.
+    type Error: Send;
+
+    /// Returns the receipt of the transaction, or `None` if it has not been executed yet.
+    fn transaction_receipt(
+        &self,
+        tx_hash: &H256,
+    ) -> impl Future<Output = Result<Option<Self::Receipt>, Self::Error>> + Send;
+
+    /// Returns the number of the block including the receipt, if any.
+    fn receipt_block_number(receipt: &Self::Receipt) -> Option<u64>;
+
+    /// Returns the sender and the nonce of the transaction, or `None` if the transaction
+    /// is unknown.
+    fn transaction_sender(
+        &self,
+        tx_hash: &H256,
+    ) -> impl Future<Output = Result<Option<(H160, U256)>, Self::Error>> + Send;
+
+    /// Returns the number of transactions sent by the address in the latest block.
+    fn transaction_count(
+        &self,
+        address: &H160,
+    ) -> impl Future<Output = Result<U256, Self::Error>> + Send;
+
+    /// Returns the number of the latest block.
+    fn block_number(&self) -> impl Future<Output = Result<u64, Self::Error>> + Send;
+}
+
+/// Errors returned while waiting for a [`PendingTransaction`].
+#[derive(Debug, Error, Clone, PartialEq)]
+pub enum PendingTransactionError<E> {
+    /// A request to the [`TransactionStatusProvider`] failed.
+    #[error(transparent)]
+    Client(E),
+
+    /// The transaction is no longer known and its nonce has not been used.
+    #[error("transaction {0:#x} has been dropped")]
+    Dropped(H256),
+
+    /// Another transaction with the same sender and nonce has been executed.
+    #[error(
+        "transaction {tx_hash:#x} has been replaced by another transaction with nonce {nonce}"
+    )]
+    Replaced { tx_hash: H256, nonce: U256 },
+
+    /// The transaction did not reach the required confirmations in time.
+    #[error("transaction {tx_hash:#x} not confirmed after {timeout:?}")]
+    Timeout { tx_hash: H256, timeout: Duration },
+}
+
+/// Result of waiting for a [`PendingTransaction`].
+pub type PendingTransactionResult<T, E> = Result<T, PendingTransactionError<E>>;
+
+/// A sent transaction, which can be awaited for its receipt.
+///
+/// The receipt is returned once the block including the transaction has the required
+/// number of confirmations, the including block counting as the first one.
+/// Failed transactions are returned as well: check the receipt status.
+///
+/// While waiting, the transaction is reported as:
+/// - replaced, if the nonce of the transaction has been used by another transaction;
+/// - dropped, if the transaction is no longer known.
+#[derive(Debug)]
+pub struct PendingTransaction<'a, P> {
+    provider: &'a P,
+    tx_hash: H256,
+    sender: Option<(H160, U256)>,
+    confirmations: u64,
+    poll_interval: Duration,
+    timeout: Duration,
+    dropped_after_polls: Option<u32>,
+}
+
+impl<P> Clone for PendingTransaction<'_, P> {
+    fn clone(&self) -> Self {
+        Self {
+            provider: self.provider,
+            tx_hash: self.tx_hash.clone(),
+            sender: self.sender.clone(),
+            confirmations: self.confirmations,
+            poll_interval: self.poll_interval,
+            timeout: self.timeout,
+            dropped_after_polls: self.dropped_after_polls,
+        }
+    }
+}
+
+impl<'a, P: TransactionStatusProvider> PendingTransaction<'a, P> {
+    /// Creates a new pending transaction.
+    ///
+    /// # Arguments
+    /// * `provider` - The client used to poll the transaction status.
+    /// * `tx_hash` - The hash of the sent transaction.
+    pub fn new(provider: &'a P, tx_hash: H256) -> Self {
+        Self {
+            provider,
+            tx_hash,
+            sender: None,
+            confirmations: 1,
+            poll_interval: DEFAULT_POLL_INTERVAL,
+            timeout: DEFAULT_TIMEOUT,
+            dropped_after_polls: Some(DEFAULT_DROPPED_AFTER_POLLS),
+        }
+    }
+
+    /// Sets the sender and the nonce of the transaction, used to detect replacements.
+    ///
+    /// If not set, they are taken from the transaction the first time it is found.
+    pub fn with_sender(mut self, sender: H160, nonce: U256) -> Self {
+        self.sender = Some((sender, nonce));
+        self
+    }
+
+    /// Sets the number of confirmations to wait for.
+    pub fn with_confirmations(mut self, confirmations: u64) -> Self {
+        self.confirmations = confirmations.max(1);
+        self
+    }
+
+    /// Sets the interval between two polls of the transaction status.
+    pub fn with_poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = poll_interval;
+        self
+    }
+
+    /// Sets the time after which the transaction is no longer awaited.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Sets the number of consecutive polls without the transaction after which it is
+    /// considered dropped.
+    ///
+    /// `None` disables the detection, e.g. if the transactions waiting in the pool
+    /// are not returned by hash.
+    pub fn with_dropped_after_polls(mut self, dropped_after_polls: Option<u32>) -> Self {
+        self.dropped_after_polls = dropped_after_polls.map(|polls| polls.max(1));
+        self
+    }
+
+    /// Returns the hash of the transaction.
+    pub fn tx_hash(&self) -> &H256 {
+        &self.tx_hash
+    }
+
+    /// Waits for the receipt of the transaction with the required confirmations.
+    pub async fn wait(mut self) -> PendingTransactionResult<P::Receipt, P::Error> {
+        let deadline = Instant::now() + self.timeout;
+        let mut missing_polls = 0;
+
+        loop {
+            if let Some(receipt) = self.receipt().await? {
+                if let Some(block_number) = P::receipt_block_number(&receipt) {
+                    let latest = self
+                        .provider
+                        .block_number()
+                        .await
+                        .map_err(PendingTransactionError::Client)?;
+                    let confirmations = (latest + 1).saturating_sub(block_number);
+                    if confirmations >= self.confirmations {
+                        return Ok(receipt);
+                    }
+                    log::trace!(
+                        "PendingTransaction - {:#x} has {confirmations} of {} confirmations",
+                        self.tx_hash,
+                        self.confirmations
+                    );
+                }
+            } else if self.is_replaced(&mut missing_polls).await? {
+                // The nonce may have been used by this transaction after the receipt was requested
+                if self.receipt().await?.is_none() {
+                    let (_, nonce) = self.sender.unwrap_or_default();
+                    return Err(PendingTransactionError::Replaced {
+                        tx_hash: self.tx_hash,
+                        nonce,
+                    });
+                }
+                continue;
+            } else if self
+                .dropped_after_polls
+                .is_some_and(|polls| missing_polls >= polls)
+            {
+                return Err(PendingTransactionError::Dropped(self.tx_hash));
+            }
+
+            if Instant::now() + self.poll_interval > deadline {
+                return Err(PendingTransactionError::Timeout {
+                    tx_hash: self.tx_hash,
+                    timeout: self.timeout,
+                });
+            }
+            tokio::time::sleep(self.poll_interval).await;
+        }
+    }
+
+    async fn receipt(&self) -> PendingTransactionResult<Option<P::Receipt>, P::Error> {
+        self.provider
+            .transaction_receipt(&self.tx_hash)
+            .await
+            .map_err(PendingTransactionError::Client)
+    }
+
+    /// Returns whether the nonce of the transaction has been used, counting the polls
+    /// where the transaction is unknown.
+    async fn is_replaced(
+        &mut self,
+        missing_polls: &mut u32,
+    ) -> PendingTransactionResult<bool, P::Error> {
+        match self
+            .provider
+            .transaction_sender(&self.tx_hash)
+            .await
+            .map_err(PendingTransactionError::Client)?
+        {
+            Some(sender) => {
+                *missing_polls = 0;
+                self.sender.get_or_insert(sender);
+            }
+            None => *missing_polls += 1,
+        }
+
+        let Some((sender, nonce)) = &self.sender else {
+            return Ok(false);
+        };
+        let next_nonce = self
+            .provider
+            .transaction_count(sender)
+            .await
+            .map_err(PendingTransactionError::Client)?;
+
+        Ok(&next_nonce > nonce)
+    }
+}
+
+impl<'a, P: TransactionStatusProvider> IntoFuture for PendingTransaction<'a, P> {
+    type Output = PendingTransactionResult<P::Receipt, P::Error>;
+    type IntoFuture = Pin<Box<dyn Future<Output = Self::Output> + Send + 'a>>;
+
+    fn into_future(self) -> Self::IntoFuture {
+        Box::pin(self.wait())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+
+    use super::*;
+
+    const SENDER_NONCE: u64 = 5;
+
+    fn tx_hash() -> H256 {
+        H256::from([1; 32])
+    }
+
+    fn sender() -> H160 {
+        H160::from([2; 20])
+    }
+
+    /// Chain producing a new block at each receipt poll.
+    #[derive(Default)]
+    struct StubChain {
+        /// Block including the transaction, if it is executed.
+        mined_in: Option<u64>,
+        /// Whether the transaction is returned by hash.
+        known: bool,
+        next_nonce: u64,
+        latest: AtomicU64,
+        receipt_polls: AtomicU32,
+        transaction_polls: AtomicU32,
+    }
+
+    impl TransactionStatusProvider for StubChain {
+        type Receipt = u64;
+        type Error = String;
+
+        async fn transaction_receipt(&self, _tx_hash: &H256) -> Result<Option<u64>, String> {
+            self.receipt_polls.fetch_add(1, Ordering::SeqCst);
+            let latest = self.latest.fetch_add(1, Ordering::SeqCst) + 1;
+            Ok(self.mined_in.filter(|block| *block <= latest))
+        }
+
+        fn receipt_block_number(receipt: &u64) -> Option<u64> {
+            Some(*receipt)
+        }
+
+        async fn transaction_sender(
+            &self,
+            _tx_hash: &H256,
+        ) -> Result<Option<(H160, U256)>, String> {
+            self.transaction_polls.fetch_add(1, Ordering::SeqCst);
+            Ok(self.known.then(|| (sender(), SENDER_NONCE.into())))
+        }
+
+        async fn transaction_count(&self, _address: &H160) -> Result<U256, String> {
+            Ok(self.next_nonce.into())
+        }
+
+        async fn block_number(&self) -> Result<u64, String> {
+            Ok(self.latest.load(Ordering::SeqCst))
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn should_wait_for_confirmations() {
+        let chain = StubChain {
+            mined_in: Some(10),
+            known: true,
+            next_nonce: SENDER_NONCE,
+            latest: AtomicU64::new(8),
+            ..Default::default()
+        };
+
+        let receipt = PendingTransaction::new(&chain, tx_hash())
+            .with_confirmations(3)
+            .await
+            .unwrap();
+
+        assert_eq!(receipt, 10);
+        assert_eq!(chain.receipt_polls.load(Ordering::SeqCst), 4);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn should_detect_replaced_transaction() {
+        let chain = StubChain {
+            known: true,
+            next_nonce: SENDER_NONCE + 1,
+            ..Default::default()
+        };
+
+        let err = PendingTransaction::new(&chain, tx_hash())
+            .await
+            .unwrap_err();
+
+        assert_eq!(
+            err,
+            PendingTransactionError::Replaced {
+                tx_hash: tx_hash(),
+                nonce: SENDER_NONCE.into()
+            }
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn should_detect_dropped_transaction() {
+        let chain = StubChain {
+            next_nonce: SENDER_NONCE,
+            ..Default::default()
+        };
+
+        let err = PendingTransaction::new(&chain, tx_hash())
+            .with_sender(sender(), SENDER_NONCE.into())
+            .await
+            .unwrap_err();
+
+        assert_eq!(err, PendingTransactionError::Dropped(tx_hash()));
+        assert_eq!(
+            chain.transaction_polls.load(Ordering::SeqCst),
+            DEFAULT_DROPPED_AFTER_POLLS
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn should_wait_for_unknown_transaction_if_dropped_detection_is_disabled() {
+        let chain = StubChain {
+            next_nonce: SENDER_NONCE,
+            ..Default::default()
+        };
+
+        let err = PendingTransaction::new(&chain, tx_hash())
+            .with_sender(sender(), SENDER_NONCE.into())
+            .with_timeout(Duration::from_secs(10))
+            .with_dropped_after_polls(None)
+            .await
+            .unwrap_err();
+
+        assert!(matches!(err, PendingTransactionError::Timeout { .. }));
+        assert!(chain.transaction_polls.load(Ordering::SeqCst) > DEFAULT_DROPPED_AFTER_POLLS);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn should_time_out() {
+        let chain = StubChain {
+            known: true,
+            next_nonce: SENDER_NONCE,
+            ..Default::default()
+        };
+        let started_at = Instant::now();
+
+        let err = PendingTransaction::new(&chain, tx_hash())
+            .with_poll_interval(Duration::from_secs(2))
+            .with_timeout(Duration::from_secs(10))
+            .await
+            .unwrap_err();
+
+        assert_eq!(
+            err,
+            PendingTransactionError::Timeout {
+                tx_hash: tx_hash(),
+                timeout: Duration::from_secs(10)
+            }
+        );
+        assert!(started_at.elapsed() <= Duration::from_secs(10));
+        assert_eq!(chain.receipt_polls.load(Ordering::SeqCst), 6);
+    }
+}
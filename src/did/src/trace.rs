@@ -0,0 +1,304 @@
+//! Types of the `debug_traceTransaction` and `debug_traceBlockByNumber` methods,
+//! compatible with the geth built-in call tracer and struct logger.
+
+use std::collections::BTreeMap;
+
+use candid::CandidType;
+use serde::{Deserialize, Serialize};
+
+use crate::{Bytes, H160, H256, U256};
+
+/// Built-in tracers which can be requested in [`TraceOptions`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, CandidType)]
+pub enum BuiltinTracer {
+    /// Returns the tree of the calls performed by a transaction as a [`CallFrame`].
+    #[serde(rename = "callTracer")]
+    CallTracer,
+}
+
+/// Configuration of the call tracer.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize, CandidType)]
+#[serde(rename_all = "camelCase")]
+pub struct CallTracerConfig {
+    /// Trace only the top-level call.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub only_top_call: Option<bool>,
+    /// Include the logs emitted by each call.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub with_log: Option<bool>,
+}
+
+/// Options of the tracing methods.
+///
+/// The struct logger is used when no tracer is set.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize, CandidType)]
+#[serde(rename_all = "camelCase")]
+pub struct TraceOptions {
+    /// The tracer to use instead of the struct logger.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tracer: Option<BuiltinTracer>,
+    /// Configuration of the call tracer.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tracer_config: Option<CallTracerConfig>,
+    /// Struct logger: do not collect the storage.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub disable_storage: Option<bool>,
+    /// Struct logger: do not collect the stack.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub disable_stack: Option<bool>,
+    /// Struct logger: collect the memory.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub enable_memory: Option<bool>,
+    /// Struct logger: collect the return data.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub enable_return_data: Option<bool>,
+    /// Maximum duration of the tracing, e.g. `"10s"`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout: Option<String>,
+}
+
+impl TraceOptions {
+    /// Options of the call tracer, including the logs of each call.
+    pub fn call_tracer() -> Self {
+        Self {
+            tracer: Some(BuiltinTracer::CallTracer),
+            tracer_config: Some(CallTracerConfig {
+                only_top_call: None,
+                with_log: Some(true),
+            }),
+            ..Default::default()
+        }
+    }
+
+    /// Options of the struct logger.
+    pub fn struct_logger() -> Self {
+        Self::default()
+    }
+}
+
+/// A call performed during the execution of a transaction, as returned by the call tracer.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize, CandidType)]
+#[serde(rename_all = "camelCase")]
+pub struct CallFrame {
+    /// The kind of call, e.g. `CALL`, `DELEGATECALL` or `CREATE`.
+    #[serde(rename = "type")]
+    pub call_type: String,
+    pub from: H160,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub to: Option<H160>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value: Option<U256>,
+    pub gas: U256,
+    pub gas_used: U256,
+    pub input: Bytes,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output: Option<Bytes>,
+    /// The error which made the call fail.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// The decoded revert reason, if the call reverted with a reason string.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub revert_reason: Option<String>,
+    /// The calls performed by this call.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub calls: Vec<CallFrame>,
+    /// The logs emitted by this call, if requested with [`CallTracerConfig::with_log`].
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub logs: Vec<CallLogFrame>,
+}
+
+impl CallFrame {
+    /// Returns true if the call failed.
+    pub fn is_failed(&self) -> bool {
+        self.error.is_some()
+    }
+
+    /// Returns the innermost failed call, which is where the failure originated.
+    pub fn failure_origin(&self) -> Option<&CallFrame> {
+        if !self.is_failed() {
+            return None;
+        }
+
+        self.calls
+            .iter()
+            .rev()
+            .find_map(CallFrame::failure_origin)
+            .or(Some(self))
+    }
+}
+
+/// A log emitted by a call, as returned by the call tracer.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize, CandidType)]
+pub struct CallLogFrame {
+    pub address: H160,
+    #[serde(default)]
+    pub topics: Vec<H256>,
+    pub data: Bytes,
+}
+
+/// The result of the struct logger.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize, CandidType)]
+#[serde(rename_all = "camelCase")]
+pub struct StructLoggerResult {
+    pub failed: bool,
+    pub gas: u64,
+    /// The hex encoded return data.
+    pub return_value: String,
+    pub struct_logs: Vec<StructLog>,
+}
+
+/// The state of the EVM before the execution of an opcode.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize, CandidType)]
+#[serde(rename_all = "camelCase")]
+pub struct StructLog {
+    pub pc: u64,
+    pub op: String,
+    pub gas: u64,
+    pub gas_cost: u64,
+    pub depth: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stack: Option<Vec<U256>>,
+    /// The memory as hex encoded 32 bytes words.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub memory: Option<Vec<String>>,
+    /// The hex encoded storage slots accessed so far by the current contract.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub storage: Option<BTreeMap<String, String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub refund: Option<u64>,
+}
+
+/// The trace of a transaction of a block.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize, CandidType)]
+#[serde(rename_all = "camelCase")]
+pub struct BlockTraceResult<T> {
+    /// The hash of the traced transaction. Not returned by older nodes.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tx_hash: Option<H256>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub result: Option<T>,
+    /// The error which prevented tracing the transaction.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_serialize_trace_options() {
+        let options = serde_json::to_value(TraceOptions::call_tracer()).unwrap();
+        assert_eq!(
+            options,
+            serde_json::json!({
+                "tracer": "callTracer",
+                "tracerConfig": { "withLog": true }
+            })
+        );
+
+        let options = TraceOptions {
+            enable_memory: Some(true),
+            ..TraceOptions::struct_logger()
+        };
+        assert_eq!(
+            serde_json::to_value(options).unwrap(),
+            serde_json::json!({ "enableMemory": true })
+        );
+    }
+
+    #[test]
+    fn test_deserialize_call_frame() {
+        let json = r#"{
+            "type": "CALL",
+            "from": "0x1000000000000000000000000000000000000001",
+            "to": "0x2000000000000000000000000000000000000002",
+            "value": "0x0",
+            "gas": "0x7148",
+            "gasUsed": "0x5e0d",
+            "input": "0xa9059cbb",
+            "error": "execution reverted",
+            "calls": [
+                {
+                    "type": "STATICCALL",
+                    "from": "0x2000000000000000000000000000000000000002",
+                    "to": "0x3000000000000000000000000000000000000003",
+                    "gas": "0x6000",
+                    "gasUsed": "0x100",
+                    "input": "0x70a08231",
+                    "output": "0x00"
+                },
+                {
+                    "type": "CALL",
+                    "from": "0x2000000000000000000000000000000000000002",
+                    "to": "0x4000000000000000000000000000000000000004",
+                    "gas": "0x5000",
+                    "gasUsed": "0x200",
+                    "input": "0x23b872dd",
+                    "error": "execution reverted",
+                    "revertReason": "insufficient allowance",
+                    "logs": [
+                        {
+                            "address": "0x4000000000000000000000000000000000000004",
+                            "topics": ["0xddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef"],
+                            "data": "0x"
+                        }
+                    ]
+                }
+            ]
+        }"#;
+
+        let frame: CallFrame = serde_json::from_str(json).unwrap();
+
+        assert!(frame.is_failed());
+        assert_eq!(frame.calls.len(), 2);
+        assert!(!frame.calls[0].is_failed());
+        assert_eq!(frame.calls[1].logs.len(), 1);
+
+        let origin = frame.failure_origin().unwrap();
+        assert_eq!(origin.input, Bytes::from(vec![0x23, 0xb8, 0x72, 0xdd]));
+        assert_eq!(
+            origin.revert_reason.as_deref(),
+            Some("insufficient allowance")
+        );
+
+        let roundtrip: CallFrame =
+            serde_json::from_value(serde_json::to_value(&frame).unwrap()).unwrap();
+        assert_eq!(roundtrip, frame);
+    }
+
+    #[test]
+    fn test_deserialize_block_trace_with_struct_logs() {
+        let json = r#"[
+            {
+                "txHash": "0xddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef",
+                "result": {
+                    "failed": false,
+                    "gas": 21000,
+                    "returnValue": "",
+                    "structLogs": [
+                        {
+                            "pc": 0,
+                            "op": "PUSH1",
+                            "gas": 79000,
+                            "gasCost": 3,
+                            "depth": 1,
+                            "stack": ["0x80"]
+                        }
+                    ]
+                }
+            },
+            { "error": "tracing timed out" }
+        ]"#;
+
+        let traces: Vec<BlockTraceResult<StructLoggerResult>> = serde_json::from_str(json).unwrap();
+
+        assert_eq!(traces.len(), 2);
+        let result = traces[0].result.as_ref().unwrap();
+        assert_eq!(result.struct_logs[0].op, "PUSH1");
+        assert_eq!(result.struct_logs[0].stack, Some(vec![U256::from(0x80u64)]));
+        assert_eq!(traces[1].error.as_deref(), Some("tracing timed out"));
+    }
+}
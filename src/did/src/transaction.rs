@@ -99,6 +99,32 @@ impl From<u64> for BlockNumber {
     }
 }
 
+impl From<ethers_core::types::BlockNumber> for BlockNumber {
+    fn from(block: ethers_core::types::BlockNumber) -> Self {
+        match block {
+            ethers_core::types::BlockNumber::Latest => Self::Latest,
+            ethers_core::types::BlockNumber::Earliest => Self::Earliest,
+            ethers_core::types::BlockNumber::Pending => Self::Pending,
+            ethers_core::types::BlockNumber::Safe => Self::Safe,
+            ethers_core::types::BlockNumber::Finalized => Self::Finalized,
+            ethers_core::types::BlockNumber::Number(n) => Self::Number(n.into()),
+        }
+    }
+}
+
+impl From<BlockNumber> for ethers_core::types::BlockNumber {
+    fn from(block: BlockNumber) -> Self {
+        match block {
+            BlockNumber::Latest => Self::Latest,
+            BlockNumber::Earliest => Self::Earliest,
+            BlockNumber::Pending => Self::Pending,
+            BlockNumber::Safe => Self::Safe,
+            BlockNumber::Finalized => Self::Finalized,
+            BlockNumber::Number(n) => Self::Number(n.into()),
+        }
+    }
+}
+
 #[derive(Debug, Display, Clone, PartialEq, Eq, From)]
 pub enum BlockId {
     BlockNumber(BlockNumber),
@@ -0,0 +1,417 @@
+use std::marker::PhantomData;
+
+use ethers_core::types::{
+    Block, BlockNumber, Log, Transaction, TransactionReceipt, TransactionRequest, H160, H256, U256,
+    U64,
+};
+use futures::{StreamExt, TryStreamExt};
+use itertools::Itertools;
+use jsonrpc_core::{Call, Id, MethodCall, Params, Request, Version};
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+use crate::{
+    batch_results, Client, EthGetLogsParams, EthJsonRpcClient, EthJsonRpcError, EthJsonRpcResult,
+    ETH_BLOCK_NUMBER_METHOD, ETH_CALL_METHOD, ETH_CHAIN_ID_METHOD, ETH_ESTIMATE_GAS_METHOD,
+    ETH_GAS_PRICE_METHOD, ETH_GET_BALANCE_METHOD, ETH_GET_BLOCK_BY_HASH_METHOD,
+    ETH_GET_BLOCK_BY_NUMBER_METHOD, ETH_GET_BLOCK_TRANSACTION_COUNT_BY_NUMBER_METHOD,
+    ETH_GET_CODE_METHOD, ETH_GET_LOGS_METHOD, ETH_GET_STORAGE_AT_METHOD,
+    ETH_GET_TRANSACTION_BY_HASH_METHOD, ETH_GET_TRANSACTION_COUNT_METHOD,
+    ETH_GET_TRANSACTION_RECEIPT_METHOD, ETH_MAX_PRIORITY_FEE_PER_GAS_METHOD,
+};
+
+/// A batch of calls with heterogeneous result types, sent as JSON-RPC batch requests.
+///
+/// Each queued call returns a [`BatchHandle`] used to get its result from the
+/// [`BatchResponse`] once the batch has been sent.
+///
+/// ```ignore
+/// let mut batch = client.batch();
+/// let block = batch.get_block_by_number(BlockNumber::Latest)?;
+/// let balance = batch.get_balance(address, BlockNumber::Latest)?;
+/// let response = batch.send(MAX_BATCH_SIZE).await?;
+///
+/// let block = response.get(&block)?;
+/// let balance = response.get(&balance)?;
+/// ```
+pub struct BatchRequest<'a, C: Client> {
+    client: &'a EthJsonRpcClient<C>,
+    calls: Vec<MethodCall>,
+}
+
+/// The handle of a call queued in a [`BatchRequest`].
+pub struct BatchHandle<R> {
+    index: usize,
+    decode: fn(Value) -> EthJsonRpcResult<R>,
+    _result: PhantomData<R>,
+}
+
+/// The results of a [`BatchRequest`].
+#[derive(Debug)]
+pub struct BatchResponse {
+    results: Vec<EthJsonRpcResult<Value>>,
+}
+
+impl<C: Client> EthJsonRpcClient<C> {
+    /// Creates an empty batch of calls.
+    pub fn batch(&self) -> BatchRequest<'_, C> {
+        BatchRequest {
+            client: self,
+            calls: Vec::new(),
+        }
+    }
+}
+
+impl<'a, C: Client> BatchRequest<'a, C> {
+    /// Queues a call, returning the handle of its result.
+    pub fn add<R: DeserializeOwned>(&mut self, method: &str, params: Params) -> BatchHandle<R> {
+        self.add_with_decoder(method, params, decode)
+    }
+
+    /// Returns the number of queued calls.
+    pub fn len(&self) -> usize {
+        self.calls.len()
+    }
+
+    /// Returns true if no call has been queued.
+    pub fn is_empty(&self) -> bool {
+        self.calls.is_empty()
+    }
+
+    /// Queues an `eth_getBlockByNumber` call returning transaction hashes,
+    /// returning `None` for blocks not produced yet.
+    pub fn get_block_by_number(
+        &mut self,
+        block: BlockNumber,
+    ) -> EthJsonRpcResult<BatchHandle<Option<Block<H256>>>> {
+        Ok(self.add(
+            ETH_GET_BLOCK_BY_NUMBER_METHOD,
+            make_params_array!(block, false),
+        ))
+    }
+
+    /// Queues an `eth_getBlockByNumber` call returning full transactions,
+    /// returning `None` for blocks not produced yet.
+    pub fn get_full_block_by_number(
+        &mut self,
+        block: BlockNumber,
+    ) -> EthJsonRpcResult<BatchHandle<Option<Block<Transaction>>>> {
+        Ok(self.add(
+            ETH_GET_BLOCK_BY_NUMBER_METHOD,
+            make_params_array!(block, true),
+        ))
+    }
+
+    /// Queues an `eth_getTransactionReceipt` call, returning `None` for transactions
+    /// not executed yet.
+    pub fn get_receipt_by_hash(
+        &mut self,
+        hash: H256,
+    ) -> EthJsonRpcResult<BatchHandle<Option<TransactionReceipt>>> {
+        Ok(self.add(ETH_GET_TRANSACTION_RECEIPT_METHOD, make_params_array!(hash)))
+    }
+
+    /// Queues an `eth_blockNumber` call.
+    pub fn get_block_number(&mut self) -> EthJsonRpcResult<BatchHandle<u64>> {
+        Ok(self.add_with_decoder(ETH_BLOCK_NUMBER_METHOD, make_params_array!(), decode_u64))
+    }
+
+    /// Queues an `eth_chainId` call.
+    pub fn get_chain_id(&mut self) -> EthJsonRpcResult<BatchHandle<u64>> {
+        Ok(self.add_with_decoder(ETH_CHAIN_ID_METHOD, make_params_array!(), decode_u64))
+    }
+
+    /// Queues an `eth_getBalance` call.
+    pub fn get_balance(
+        &mut self,
+        address: H160,
+        block: BlockNumber,
+    ) -> EthJsonRpcResult<BatchHandle<U256>> {
+        Ok(self.add(ETH_GET_BALANCE_METHOD, make_params_array!(address, block)))
+    }
+
+    /// Queues an `eth_gasPrice` call.
+    pub fn gas_price(&mut self) -> EthJsonRpcResult<BatchHandle<U256>> {
+        Ok(self.add(ETH_GAS_PRICE_METHOD, make_params_array!()))
+    }
+
+    /// Queues an `eth_getCode` call.
+    pub fn get_code(
+        &mut self,
+        address: H160,
+        block: BlockNumber,
+    ) -> EthJsonRpcResult<BatchHandle<String>> {
+        Ok(self.add(ETH_GET_CODE_METHOD, make_params_array!(address, block)))
+    }
+
+    /// Queues an `eth_getTransactionCount` call.
+    pub fn get_transaction_count(
+        &mut self,
+        address: H160,
+        block: BlockNumber,
+    ) -> EthJsonRpcResult<BatchHandle<u64>> {
+        Ok(self.add_with_decoder(
+            ETH_GET_TRANSACTION_COUNT_METHOD,
+            make_params_array!(address, block),
+            decode_u64,
+        ))
+    }
+
+    /// Queues an `eth_call` call.
+    pub fn eth_call(
+        &mut self,
+        params: TransactionRequest,
+        block: BlockNumber,
+    ) -> EthJsonRpcResult<BatchHandle<String>> {
+        Ok(self.add(ETH_CALL_METHOD, make_params_array!(params, block)))
+    }
+
+    /// Queues an `eth_getLogs` call.
+    pub fn get_logs(
+        &mut self,
+        params: EthGetLogsParams,
+    ) -> EthJsonRpcResult<BatchHandle<Vec<Log>>> {
+        Ok(self.add(ETH_GET_LOGS_METHOD, make_params_array!(params)))
+    }
+
+    /// Queues an `eth_getTransactionByHash` call, returning `None` for unknown transactions.
+    pub fn get_transaction_by_hash(
+        &mut self,
+        hash: H256,
+    ) -> EthJsonRpcResult<BatchHandle<Option<Transaction>>> {
+        Ok(self.add(ETH_GET_TRANSACTION_BY_HASH_METHOD, make_params_array!(hash)))
+    }
+
+    /// Queues an `eth_getBlockByHash` call returning transaction hashes,
+    /// returning `None` for unknown blocks.
+    pub fn get_block_by_hash(
+        &mut self,
+        hash: H256,
+    ) -> EthJsonRpcResult<BatchHandle<Option<Block<H256>>>> {
+        Ok(self.add(
+            ETH_GET_BLOCK_BY_HASH_METHOD,
+            make_params_array!(hash, false),
+        ))
+    }
+
+    /// Queues an `eth_getBlockTransactionCountByNumber` call.
+    pub fn get_block_transaction_count_by_number(
+        &mut self,
+        block: BlockNumber,
+    ) -> EthJsonRpcResult<BatchHandle<u64>> {
+        Ok(self.add_with_decoder(
+            ETH_GET_BLOCK_TRANSACTION_COUNT_BY_NUMBER_METHOD,
+            make_params_array!(block),
+            decode_u64,
+        ))
+    }
+
+    /// Queues an `eth_getStorageAt` call.
+    pub fn get_storage_at(
+        &mut self,
+        address: H160,
+        slot: H256,
+        block: BlockNumber,
+    ) -> EthJsonRpcResult<BatchHandle<H256>> {
+        Ok(self.add(
+            ETH_GET_STORAGE_AT_METHOD,
+            make_params_array!(address, slot, block),
+        ))
+    }
+
+    /// Queues an `eth_estimateGas` call.
+    pub fn estimate_gas(
+        &mut self,
+        params: TransactionRequest,
+    ) -> EthJsonRpcResult<BatchHandle<U256>> {
+        Ok(self.add(ETH_ESTIMATE_GAS_METHOD, make_params_array!(params)))
+    }
+
+    /// Queues an `eth_maxPriorityFeePerGas` call.
+    pub fn max_priority_fee_per_gas(&mut self) -> EthJsonRpcResult<BatchHandle<U256>> {
+        Ok(self.add(ETH_MAX_PRIORITY_FEE_PER_GAS_METHOD, make_params_array!()))
+    }
+
+    /// Sends the queued calls, splitting them in batches of at most `max_batch_size` calls.
+    ///
+    /// Up to [`EthJsonRpcClient::max_concurrent_batches`] batches are sent in parallel.
+    ///
+    /// Fails if a batch cannot be sent; the errors of the single calls are
+    /// returned by [`BatchResponse::get`].
+    pub async fn send(self, max_batch_size: usize) -> EthJsonRpcResult<BatchResponse> {
+        // Collect chunks before iteration, otherwise the future won't be `Send`
+        let chunks = self
+            .calls
+            .into_iter()
+            .chunks(max_batch_size.max(1))
+            .into_iter()
+            .map(Iterator::collect::<Vec<_>>)
+            .collect::<Vec<_>>();
+
+        let client = self.client;
+        let results = futures::stream::iter(chunks)
+            .map(|chunk| async move {
+                let ids = chunk.iter().map(|call| call.id.clone()).collect::<Vec<_>>();
+                let request = Request::Batch(chunk.into_iter().map(Call::MethodCall).collect());
+                batch_results(&ids, client.request(request).await?)
+            })
+            .buffered(client.max_concurrent_batches())
+            .try_collect::<Vec<_>>()
+            .await?;
+
+        Ok(BatchResponse {
+            results: results.into_iter().flatten().collect(),
+        })
+    }
+
+    fn add_with_decoder<R>(
+        &mut self,
+        method: &str,
+        params: Params,
+        decode: fn(Value) -> EthJsonRpcResult<R>,
+    ) -> BatchHandle<R> {
+        let index = self.calls.len();
+        self.calls.push(MethodCall {
+            jsonrpc: Some(Version::V2),
+            method: method.to_string(),
+            params,
+            id: Id::Num(index as _),
+        });
+
+        BatchHandle {
+            index,
+            decode,
+            _result: PhantomData,
+        }
+    }
+}
+
+impl BatchResponse {
+    /// Returns the result of the call with the given handle.
+    pub fn get<R>(&self, handle: &BatchHandle<R>) -> EthJsonRpcResult<R> {
+        match self.results.get(handle.index) {
+            Some(Ok(value)) => (handle.decode)(value.clone()),
+            Some(Err(err)) => Err(err.clone()),
+            None => Err(EthJsonRpcError::UnexpectedResponse(format!(
+                "missing response for batch call {}",
+                handle.index
+            ))),
+        }
+    }
+}
+
+fn decode<R: DeserializeOwned>(value: Value) -> EthJsonRpcResult<R> {
+    serde_json::from_value(value).map_err(|e| EthJsonRpcError::Deserialization(e.to_string()))
+}
+
+fn decode_u64(value: Value) -> EthJsonRpcResult<u64> {
+    decode::<U64>(value).map(|value| value.as_u64())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::future::Future;
+    use std::pin::Pin;
+    use std::sync::{Arc, Mutex};
+
+    use jsonrpc_core::{Failure, Output, Response, Success};
+
+    use super::*;
+
+    /// Answers in reverse order, failing the calls of unexpected methods.
+    #[derive(Clone, Default)]
+    struct ReversingClient {
+        batch_sizes: Arc<Mutex<Vec<usize>>>,
+    }
+
+    impl Client for ReversingClient {
+        fn send_rpc_request(
+            &self,
+            request: Request,
+        ) -> Pin<Box<dyn Future<Output = EthJsonRpcResult<Response>> + Send>> {
+            let Request::Batch(calls) = request else {
+                panic!("expected batch request");
+            };
+            self.batch_sizes.lock().unwrap().push(calls.len());
+
+            let outputs = calls
+                .into_iter()
+                .rev()
+                .map(|call| {
+                    let Call::MethodCall(call) = call else {
+                        panic!("expected method call");
+                    };
+                    let result = match call.method.as_str() {
+                        ETH_BLOCK_NUMBER_METHOD => Value::from("0x2a"),
+                        ETH_GET_TRANSACTION_RECEIPT_METHOD => Value::Null,
+                        ETH_GET_BALANCE_METHOD => Value::from("0x64"),
+                        ETH_GET_BLOCK_BY_NUMBER_METHOD => serde_json::to_value(Block::<H256> {
+                            number: Some(7u64.into()),
+                            ..Default::default()
+                        })
+                        .unwrap(),
+                        _ => {
+                            return Output::Failure(Failure {
+                                jsonrpc: Some(Version::V2),
+                                error: jsonrpc_core::Error::invalid_params("not found"),
+                                id: call.id,
+                            })
+                        }
+                    };
+                    Output::Success(Success {
+                        jsonrpc: Some(Version::V2),
+                        result,
+                        id: call.id,
+                    })
+                })
+                .collect();
+
+            Box::pin(async move { Ok(Response::Batch(outputs)) })
+        }
+    }
+
+    #[tokio::test]
+    async fn should_send_heterogeneous_batch() {
+        let client = EthJsonRpcClient::new(ReversingClient::default());
+        let mut batch = client.batch();
+
+        let block = batch.get_block_by_number(BlockNumber::Latest).unwrap();
+        let receipt = batch.get_receipt_by_hash(H256::zero()).unwrap();
+        let block_number = batch.get_block_number().unwrap();
+        let balance = batch
+            .get_balance(H160::zero(), BlockNumber::Latest)
+            .unwrap();
+        let code = batch.get_code(H160::zero(), BlockNumber::Latest).unwrap();
+        assert_eq!(batch.len(), 5);
+
+        let response = batch.send(10).await.unwrap();
+
+        assert_eq!(
+            response.get(&block).unwrap().unwrap().number,
+            Some(7u64.into())
+        );
+        assert_eq!(response.get(&receipt).unwrap(), None);
+        assert!(matches!(
+            response.get(&code),
+            Err(EthJsonRpcError::JsonRpc { .. })
+        ));
+        assert_eq!(response.get(&block_number).unwrap(), 42);
+        assert_eq!(response.get(&balance).unwrap(), 100u64.into());
+    }
+
+    #[tokio::test]
+    async fn should_split_batch_in_chunks() {
+        let client = EthJsonRpcClient::new(ReversingClient::default());
+        let mut batch = client.batch();
+
+        let handles = (0..5)
+            .map(|_| batch.get_block_number().unwrap())
+            .collect::<Vec<_>>();
+        let response = batch.send(2).await.unwrap();
+
+        for handle in &handles {
+            assert_eq!(response.get(handle).unwrap(), 42);
+        }
+        assert_eq!(*client.client.batch_sizes.lock().unwrap(), vec![2, 2, 1]);
+    }
+}
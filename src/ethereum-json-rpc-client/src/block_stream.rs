@@ -0,0 +1,394 @@
+use std::collections::VecDeque;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use ethers_core::types::{Block, BlockNumber, Transaction, H256};
+use futures::stream::BoxStream;
+use futures::{Stream, StreamExt};
+
+use crate::{Client, EthJsonRpcClient, EthJsonRpcError, EthJsonRpcResult};
+
+/// Default interval between two polls of the chain head.
+pub const DEFAULT_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Default number of recent blocks tracked to detect chain reorganizations.
+pub const DEFAULT_MAX_REORG_DEPTH: usize = 64;
+
+/// An event of a [`BlockStream`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum BlockEvent {
+    /// A block added to the canonical chain.
+    New(Block<Transaction>),
+    /// A block previously yielded as new which is no longer in the canonical chain.
+    /// Reverted blocks are yielded from the most recent one.
+    Reverted(Block<Transaction>),
+}
+
+/// A stream of the blocks of the chain, following the head by polling the node.
+///
+/// A block is yielded once it has the required number of confirmations, the block
+/// itself counting as the first one. The parent hash of every block is checked against
+/// the previous block: when the canonical chain changes, the blocks no longer in it are
+/// yielded as [`BlockEvent::Reverted`] before the blocks of the new chain.
+///
+/// Errors are yielded without ending the stream: the failed request is retried after
+/// the poll interval if the stream is polled again. The only exception is a
+/// reorganization deeper than the maximum depth, which ends the stream after the error.
+pub struct BlockStream<C: Client> {
+    client: EthJsonRpcClient<C>,
+    from: u64,
+    confirmations: u64,
+    poll_interval: Duration,
+    max_reorg_depth: usize,
+    stream: Option<BoxStream<'static, EthJsonRpcResult<BlockEvent>>>,
+}
+
+// The inner stream is boxed, so the `BlockStream` is never structurally pinned.
+impl<C: Client> Unpin for BlockStream<C> {}
+
+impl<C: Client + 'static> BlockStream<C> {
+    /// Creates a new stream.
+    ///
+    /// # Arguments
+    /// * `client` - The client used to poll the node.
+    /// * `from` - The number of the first block.
+    /// * `confirmations` - The number of confirmations a block needs to be yielded.
+    pub fn new(client: EthJsonRpcClient<C>, from: u64, confirmations: u64) -> Self {
+        Self {
+            client,
+            from,
+            confirmations: confirmations.max(1),
+            poll_interval: DEFAULT_POLL_INTERVAL,
+            max_reorg_depth: DEFAULT_MAX_REORG_DEPTH,
+            stream: None,
+        }
+    }
+
+    /// Sets the interval between two polls of the chain head.
+    pub fn with_poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = poll_interval;
+        self
+    }
+
+    /// Sets the number of recent blocks tracked to detect chain reorganizations.
+    ///
+    /// Reorganizations reverting up to this number of blocks are handled, while a deeper
+    /// one is yielded as an error and ends the stream.
+    pub fn with_max_reorg_depth(mut self, max_reorg_depth: usize) -> Self {
+        self.max_reorg_depth = max_reorg_depth.max(1);
+        self
+    }
+
+    fn start(&self) -> BoxStream<'static, EthJsonRpcResult<BlockEvent>> {
+        let state = BlockStreamState {
+            client: self.client.clone(),
+            next: self.from,
+            confirmations: self.confirmations,
+            poll_interval: self.poll_interval,
+            max_reorg_depth: self.max_reorg_depth,
+            canonical: VecDeque::new(),
+            base: None,
+            failed: false,
+            ended: false,
+        };
+
+        futures::stream::unfold(state, |mut state| async move {
+            if state.ended {
+                return None;
+            }
+            let event = state.next_event().await;
+            state.failed = event.is_err();
+            Some((event, state))
+        })
+        .boxed()
+    }
+}
+
+impl<C: Client + 'static> Stream for BlockStream<C> {
+    type Item = EthJsonRpcResult<BlockEvent>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        if self.stream.is_none() {
+            self.stream = Some(self.start());
+        }
+        self.stream
+            .as_mut()
+            .expect("stream is initialized")
+            .poll_next_unpin(cx)
+    }
+}
+
+struct BlockStreamState<C: Client> {
+    client: EthJsonRpcClient<C>,
+    /// The number of the next block to fetch.
+    next: u64,
+    confirmations: u64,
+    poll_interval: Duration,
+    max_reorg_depth: usize,
+    /// The most recent blocks yielded as new, in order.
+    canonical: VecDeque<Block<Transaction>>,
+    /// The hash of the last block removed from `canonical` to respect the maximum depth.
+    base: Option<H256>,
+    /// Whether the previous request failed.
+    failed: bool,
+    /// Whether the stream has ended after a reorganization deeper than the maximum depth.
+    ended: bool,
+}
+
+impl<C: Client> BlockStreamState<C> {
+    async fn next_event(&mut self) -> EthJsonRpcResult<BlockEvent> {
+        if self.failed {
+            tokio::time::sleep(self.poll_interval).await;
+        }
+
+        loop {
+            let head = self.client.get_block_number().await?;
+            if head + 1 < self.next + self.confirmations {
+                tokio::time::sleep(self.poll_interval).await;
+                continue;
+            }
+
+            let block = self
+                .client
+                .get_full_block_by_number(BlockNumber::Number(self.next.into()))
+                .await?;
+
+            let parent_hash = match self.canonical.back() {
+                Some(parent) => parent.hash,
+                None => self.base,
+            };
+            if parent_hash.is_none() || parent_hash == Some(block.parent_hash) {
+                return Ok(self.push(block));
+            }
+
+            let Some(reverted) = self.canonical.pop_back() else {
+                self.ended = true;
+                return Err(EthJsonRpcError::UnexpectedResponse(format!(
+                    "chain reorganization deeper than {} blocks",
+                    self.max_reorg_depth
+                )));
+            };
+            log::debug!(
+                "BlockStream - block {} reverted",
+                reverted.number.unwrap_or_default()
+            );
+            self.next -= 1;
+            return Ok(BlockEvent::Reverted(reverted));
+        }
+    }
+
+    fn push(&mut self, block: Block<Transaction>) -> BlockEvent {
+        self.next += 1;
+        self.canonical.push_back(block.clone());
+        if self.canonical.len() > self.max_reorg_depth {
+            self.base = self.canonical.pop_front().and_then(|block| block.hash);
+        }
+
+        BlockEvent::New(block)
+    }
+}
+
+impl<C: Client + 'static> EthJsonRpcClient<C> {
+    /// Returns a [`BlockStream`] of the blocks from `from`, following the chain head.
+    ///
+    /// # Arguments
+    /// * `from` - The number of the first block.
+    /// * `confirmations` - The number of confirmations a block needs to be yielded.
+    pub fn block_stream(&self, from: u64, confirmations: u64) -> BlockStream<C> {
+        BlockStream::new(self.clone(), from, confirmations)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use ethers_core::types::{H256, U64};
+    use jsonrpc_core::Params;
+
+    use super::*;
+    use crate::mock::MockClient;
+
+    /// A chain whose blocks are identified by their number and a fork id.
+    #[derive(Default)]
+    struct Chain {
+        head: u64,
+        /// The fork id of the blocks from the given number.
+        forks: Vec<(u64, u8)>,
+    }
+
+    impl Chain {
+        fn fork_of(&self, number: u64) -> u8 {
+            self.forks
+                .iter()
+                .rev()
+                .find(|(from, _)| number >= *from)
+                .map_or(0, |(_, fork)| *fork)
+        }
+
+        fn hash(&self, number: u64) -> H256 {
+            let mut hash = H256::from_low_u64_be(number);
+            hash.0[0] = self.fork_of(number);
+            hash
+        }
+
+        fn block(&self, number: u64) -> Block<Transaction> {
+            Block {
+                number: Some(number.into()),
+                hash: Some(self.hash(number)),
+                parent_hash: number.checked_sub(1).map_or(H256::zero(), |n| self.hash(n)),
+                ..Default::default()
+            }
+        }
+    }
+
+    fn node(chain: &Arc<Mutex<Chain>>) -> MockClient {
+        let mock = MockClient::new();
+        let head = chain.clone();
+        let blocks = chain.clone();
+        mock.on_call("eth_blockNumber", move |_| {
+            Ok(U64::from(head.lock().unwrap().head))
+        })
+        .on_call("eth_getBlockByNumber", move |params| {
+            let Params::Array(params) = params else {
+                panic!("unexpected params");
+            };
+            let number: U64 = serde_json::from_value(params[0].clone()).unwrap();
+            Ok(blocks.lock().unwrap().block(number.as_u64()))
+        });
+        mock
+    }
+
+    fn event_summary(event: &BlockEvent) -> (&'static str, u64, u8) {
+        let (kind, block) = match event {
+            BlockEvent::New(block) => ("new", block),
+            BlockEvent::Reverted(block) => ("reverted", block),
+        };
+        (
+            kind,
+            block.number.unwrap().as_u64(),
+            block.hash.unwrap().0[0],
+        )
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn should_follow_head_with_confirmations() {
+        let chain = Arc::new(Mutex::new(Chain {
+            head: 4,
+            ..Default::default()
+        }));
+        let client = EthJsonRpcClient::new(node(&chain));
+        let mut stream = client.block_stream(2, 2);
+
+        let mut numbers = Vec::new();
+        for _ in 0..2 {
+            numbers.push(event_summary(&stream.next().await.unwrap().unwrap()).1);
+        }
+        assert_eq!(numbers, vec![2, 3]);
+
+        let waiting = tokio::time::timeout(Duration::from_secs(5), stream.next()).await;
+        assert!(waiting.is_err());
+
+        chain.lock().unwrap().head = 5;
+        let event = stream.next().await.unwrap().unwrap();
+        assert_eq!(event_summary(&event), ("new", 4, 0));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn should_revert_blocks_on_reorg() {
+        let chain = Arc::new(Mutex::new(Chain {
+            head: 3,
+            ..Default::default()
+        }));
+        let client = EthJsonRpcClient::new(node(&chain));
+        let mut stream = client.block_stream(0, 1);
+        for _ in 0..=3 {
+            stream.next().await.unwrap().unwrap();
+        }
+
+        {
+            let mut chain = chain.lock().unwrap();
+            chain.forks.push((2, 1));
+            chain.head = 4;
+        }
+
+        let mut events = Vec::new();
+        for _ in 0..5 {
+            events.push(event_summary(&stream.next().await.unwrap().unwrap()));
+        }
+        assert_eq!(
+            events,
+            vec![
+                ("reverted", 3, 0),
+                ("reverted", 2, 0),
+                ("new", 2, 1),
+                ("new", 3, 1),
+                ("new", 4, 1),
+            ]
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn should_fail_on_reorg_deeper_than_tracked_blocks() {
+        let chain = Arc::new(Mutex::new(Chain {
+            head: 3,
+            ..Default::default()
+        }));
+        let client = EthJsonRpcClient::new(node(&chain));
+        let mut stream = client.block_stream(0, 1).with_max_reorg_depth(2);
+        for _ in 0..=3 {
+            stream.next().await.unwrap().unwrap();
+        }
+
+        {
+            let mut chain = chain.lock().unwrap();
+            chain.forks.push((1, 1));
+            chain.head = 4;
+        }
+
+        for number in [3, 2] {
+            let event = stream.next().await.unwrap().unwrap();
+            assert_eq!(event_summary(&event), ("reverted", number, 0));
+        }
+        assert!(matches!(
+            stream.next().await.unwrap(),
+            Err(EthJsonRpcError::UnexpectedResponse(_))
+        ));
+        assert!(stream.next().await.is_none());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn should_revert_as_many_blocks_as_the_max_reorg_depth() {
+        let chain = Arc::new(Mutex::new(Chain {
+            head: 3,
+            ..Default::default()
+        }));
+        let client = EthJsonRpcClient::new(node(&chain));
+        let mut stream = client.block_stream(0, 1).with_max_reorg_depth(2);
+        for _ in 0..=3 {
+            stream.next().await.unwrap().unwrap();
+        }
+
+        {
+            let mut chain = chain.lock().unwrap();
+            chain.forks.push((2, 1));
+            chain.head = 4;
+        }
+
+        let mut events = Vec::new();
+        for _ in 0..5 {
+            events.push(event_summary(&stream.next().await.unwrap().unwrap()));
+        }
+        assert_eq!(
+            events,
+            vec![
+                ("reverted", 3, 0),
+                ("reverted", 2, 0),
+                ("new", 2, 1),
+                ("new", 3, 1),
+                ("new", 4, 1),
+            ]
+        );
+    }
+}
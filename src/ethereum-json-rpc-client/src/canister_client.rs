@@ -2,20 +2,19 @@ use std::collections::HashMap;
 use std::future::Future;
 use std::pin::Pin;
 
-use anyhow::Context;
 use candid::{CandidType, Deserialize};
 use ic_canister_client::CanisterClient;
 use jsonrpc_core::{Call, Request, Response};
 use serde::Serialize;
 use serde_bytes::ByteBuf;
 
-use crate::{Client, ETH_SEND_RAW_TRANSACTION_METHOD};
+use crate::{Client, EthJsonRpcError, EthJsonRpcResult, ETH_SEND_RAW_TRANSACTION_METHOD};
 
 impl<T: CanisterClient + Sync + 'static> Client for T {
     fn send_rpc_request(
         &self,
         request: Request,
-    ) -> Pin<Box<dyn Future<Output = anyhow::Result<Response>> + Send>> {
+    ) -> Pin<Box<dyn Future<Output = EthJsonRpcResult<Response>> + Send>> {
         let client = self.clone();
 
         Box::pin(async move {
@@ -40,7 +39,7 @@ impl<T: CanisterClient + Sync + 'static> Client for T {
             } else {
                 client.query("http_request", (args,)).await
             }
-            .map_err(anyhow::Error::from);
+            .map_err(|e| EthJsonRpcError::Transport(e.to_string()));
 
             let http_response = match http_response {
                 Ok(response) => response,
@@ -50,8 +49,9 @@ impl<T: CanisterClient + Sync + 'static> Client for T {
                 }
             };
 
-            let response = serde_json::from_slice(&http_response.body)
-                .context("failed to deserialize RPC request")?;
+            let response = serde_json::from_slice(&http_response.body).map_err(|e| {
+                EthJsonRpcError::Deserialization(format!("failed to deserialize RPC response: {e}"))
+            })?;
 
             log::trace!("response: {:?}", response);
 
@@ -74,16 +74,14 @@ struct HttpRequest {
 }
 
 impl HttpRequest {
-    pub fn new<T: ?Sized + Serialize>(data: &T) -> anyhow::Result<Self> {
+    pub fn new<T: ?Sized + Serialize>(data: &T) -> EthJsonRpcResult<Self> {
         let mut headers = HashMap::new();
         headers.insert("content-type", "application/json");
         Ok(Self {
             method: "POST",
             headers,
             url: "",
-            body: ByteBuf::from(
-                serde_json::to_vec(data).context("failed to serialize RPC request")?,
-            ),
+            body: ByteBuf::from(serde_json::to_vec(data)?),
         })
     }
 }
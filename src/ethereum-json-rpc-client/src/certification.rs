@@ -0,0 +1,655 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use candid::Principal;
+use did::certified::CertifiedResult;
+use ethers_core::types::{Block, H256};
+use ic_certification::{Certificate, HashTree, LookupResult};
+use serde_bytes::ByteBuf;
+use thiserror::Error;
+
+use crate::{Client, EthJsonRpcClient, EthJsonRpcError, EthJsonRpcResult};
+
+/// DER encoded public key of the Internet Computer mainnet.
+pub const IC_ROOT_KEY: &[u8; 133] = b"\x30\x81\x82\x30\x1d\x06\x0d\x2b\x06\x01\x04\x01\x82\xdc\x7c\x05\x03\x01\x02\x01\x06\x0c\x2b\x06\x01\x04\x01\x82\xdc\x7c\x05\x03\x02\x01\x03\x61\x00\x81\x4c\x0e\x6e\xc7\x1f\xab\x58\x3b\x08\xbd\x81\x37\x3c\x25\x5c\x3c\x37\x1b\x2e\x84\x86\x3c\x98\xa4\xf1\xe0\x8b\x74\x23\x5d\x14\xfb\x5d\x9c\x0c\xd5\x46\xd9\x68\x5f\x91\x3a\x0c\x0b\x2c\xc5\x34\x15\x83\xbf\x4b\x43\x92\xe4\x67\xdb\x96\xd6\x5b\x9b\xb4\xcb\x71\x71\x12\xf8\x47\x2e\x0d\x5a\x4d\x14\x50\x5f\xfd\x74\x84\xb0\x12\x91\x09\x1c\x5f\x87\xb9\x88\x83\x46\x3f\x98\x09\x1a\x0b\xaa\xae";
+
+/// DER prefix of the BLS12-381 public keys used by the Internet Computer.
+const BLS_KEY_DER_PREFIX: &[u8; 37] = b"\x30\x81\x82\x30\x1d\x06\x0d\x2b\x06\x01\x04\x01\x82\xdc\x7c\x05\x03\x01\x02\x01\x06\x0c\x2b\x06\x01\x04\x01\x82\xdc\x7c\x05\x03\x02\x01\x03\x61\x00";
+
+/// Length of a BLS12-381 public key.
+const BLS_KEY_LENGTH: usize = 96;
+
+/// Domain separator of the messages signed by the Internet Computer state certification.
+const IC_STATE_ROOT_DOMAIN_SEPARATOR: &[u8; 14] = b"\x0Dic-state-root";
+
+/// Default maximum age of an accepted certificate.
+pub const DEFAULT_MAX_CERTIFICATE_AGE: Duration = Duration::from_secs(5 * 60);
+
+/// Errors returned by the [`CertificateVerifier`].
+#[derive(Debug, Error, Clone, PartialEq, Eq)]
+pub enum CertificationError {
+    /// The certificate or the witness could not be decoded.
+    #[error("failed to decode {0}")]
+    Decoding(String),
+
+    /// The public key is not a DER encoded BLS12-381 key.
+    #[error("invalid public key: {0}")]
+    InvalidPublicKey(String),
+
+    /// The signature of the certificate is not valid.
+    #[error("invalid certificate signature")]
+    InvalidSignature,
+
+    /// The delegation of the certificate is not valid.
+    #[error("invalid delegation: {0}")]
+    InvalidDelegation(String),
+
+    /// The certificate does not contain the certified data of the canister.
+    #[error("the certificate does not contain the certified data of canister {0}")]
+    MissingCertifiedData(String),
+
+    /// The witness root hash is not the certified data of the canister.
+    #[error("the witness does not match the certified data")]
+    WitnessMismatch,
+
+    /// The block has no hash.
+    #[error("the block has no hash")]
+    MissingBlockHash,
+
+    /// The witness does not contain the block hash.
+    #[error("the block hash {0} is not certified by the witness")]
+    BlockHashNotCertified(String),
+
+    /// The certificate has no valid time.
+    #[error("the certificate has no valid time")]
+    MissingTime,
+
+    /// The certificate is older than the maximum age.
+    #[error("the certificate is {age:?} old, more than the maximum of {max_age:?}")]
+    CertificateTooOld { age: Duration, max_age: Duration },
+}
+
+/// Verifies the certified responses of the EVM canister, like the ones of `ic_getLastCertifiedBlock`.
+///
+/// The verification:
+/// - checks the BLS signature of the certificate, and of its delegation if any,
+///   against the root key;
+/// - checks that the certificate is not older than the maximum age;
+/// - checks that the root hash of the witness is the certified data of the canister;
+/// - checks that the witness contains the block hash at the configured path.
+#[derive(Debug, Clone)]
+pub struct CertificateVerifier {
+    root_key: Vec<u8>,
+    canister_id: Principal,
+    block_hash_path: Vec<Vec<u8>>,
+    max_certificate_age: Duration,
+}
+
+impl CertificateVerifier {
+    /// Creates a new verifier using the mainnet root key.
+    ///
+    /// # Arguments
+    /// * `canister_id` - The id of the EVM canister.
+    /// * `block_hash_path` - The path of the block hash in the witness of the canister.
+    pub fn new(canister_id: Principal, block_hash_path: Vec<Vec<u8>>) -> Self {
+        Self {
+            root_key: IC_ROOT_KEY.to_vec(),
+            canister_id,
+            block_hash_path,
+            max_certificate_age: DEFAULT_MAX_CERTIFICATE_AGE,
+        }
+    }
+
+    /// Sets the DER encoded root key, e.g. the one of a local replica.
+    pub fn with_root_key(mut self, root_key: Vec<u8>) -> Self {
+        self.root_key = root_key;
+        self
+    }
+
+    /// Sets the maximum age of an accepted certificate.
+    pub fn with_max_certificate_age(mut self, max_certificate_age: Duration) -> Self {
+        self.max_certificate_age = max_certificate_age;
+        self
+    }
+
+    /// Verifies that the block of the certified result is certified by the canister.
+    pub fn verify_block(
+        &self,
+        certified_block: &CertifiedResult<Block<H256>>,
+    ) -> Result<(), CertificationError> {
+        self.verify_block_at(certified_block, now())
+    }
+
+    /// Verifies that the block of the certified result is certified by the canister,
+    /// checking the age of the certificate at `now`.
+    pub fn verify_block_at(
+        &self,
+        certified_block: &CertifiedResult<Block<H256>>,
+        now: SystemTime,
+    ) -> Result<(), CertificationError> {
+        let block_hash = certified_block
+            .data
+            .hash
+            .ok_or(CertificationError::MissingBlockHash)?;
+
+        self.verify_block_hash_at(
+            &certified_block.certificate,
+            &certified_block.witness,
+            block_hash.as_bytes(),
+            now,
+        )
+    }
+
+    /// Verifies that the block hash is certified by the canister.
+    ///
+    /// # Arguments
+    /// * `certificate` - The CBOR encoded certificate.
+    /// * `witness` - The CBOR encoded hash tree of the certified data.
+    /// * `block_hash` - The block hash.
+    pub fn verify_block_hash(
+        &self,
+        certificate: &[u8],
+        witness: &[u8],
+        block_hash: &[u8],
+    ) -> Result<(), CertificationError> {
+        self.verify_block_hash_at(certificate, witness, block_hash, now())
+    }
+
+    /// Verifies that the block hash is certified by the canister, checking the age
+    /// of the certificate at `now`.
+    ///
+    /// # Arguments
+    /// * `certificate` - The CBOR encoded certificate.
+    /// * `witness` - The CBOR encoded hash tree of the certified data.
+    /// * `block_hash` - The block hash.
+    /// * `now` - The current time.
+    pub fn verify_block_hash_at(
+        &self,
+        certificate: &[u8],
+        witness: &[u8],
+        block_hash: &[u8],
+        now: SystemTime,
+    ) -> Result<(), CertificationError> {
+        let certificate: Certificate = decode("certificate", certificate)?;
+        self.verify_certificate(&certificate)?;
+        self.verify_certificate_time(&certificate, now)?;
+
+        let canister_id = self.canister_id.as_slice();
+        let certified_data = match certificate.tree.lookup_path([
+            b"canister".as_slice(),
+            canister_id,
+            b"certified_data".as_slice(),
+        ]) {
+            LookupResult::Found(data) => data,
+            _ => {
+                return Err(CertificationError::MissingCertifiedData(
+                    self.canister_id.to_text(),
+                ))
+            }
+        };
+
+        let witness: HashTree = decode("witness", witness)?;
+        if witness.digest().as_slice() != certified_data {
+            return Err(CertificationError::WitnessMismatch);
+        }
+
+        if witness.lookup_path(&self.block_hash_path) != LookupResult::Found(block_hash) {
+            return Err(CertificationError::BlockHashNotCertified(format!(
+                "0x{}",
+                hex::encode(block_hash)
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// Verifies the signature of the certificate with the root key or with its delegation.
+    fn verify_certificate(&self, certificate: &Certificate) -> Result<(), CertificationError> {
+        let key = match &certificate.delegation {
+            None => self.root_key.clone(),
+            Some(delegation) => {
+                let subnet_certificate: Certificate =
+                    decode("delegation certificate", &delegation.certificate)?;
+                if subnet_certificate.delegation.is_some() {
+                    return Err(CertificationError::InvalidDelegation(
+                        "nested delegations are not allowed".to_string(),
+                    ));
+                }
+                self.verify_signature(&subnet_certificate, &self.root_key)?;
+                self.check_canister_ranges(&subnet_certificate, &delegation.subnet_id)?;
+
+                match subnet_certificate.tree.lookup_path([
+                    b"subnet".as_slice(),
+                    delegation.subnet_id.as_slice(),
+                    b"public_key".as_slice(),
+                ]) {
+                    LookupResult::Found(key) => key.to_vec(),
+                    _ => {
+                        return Err(CertificationError::InvalidDelegation(
+                            "missing subnet public key".to_string(),
+                        ))
+                    }
+                }
+            }
+        };
+
+        self.verify_signature(certificate, &key)
+    }
+
+    /// Checks that the certificate is not older than the maximum age at `now`.
+    fn verify_certificate_time(
+        &self,
+        certificate: &Certificate,
+        now: SystemTime,
+    ) -> Result<(), CertificationError> {
+        let time = match certificate.tree.lookup_path([b"time".as_slice()]) {
+            LookupResult::Found(time) => {
+                decode_leb128(time).ok_or(CertificationError::MissingTime)?
+            }
+            _ => return Err(CertificationError::MissingTime),
+        };
+
+        let time = UNIX_EPOCH + Duration::from_nanos(time);
+        // Certificates from the future are accepted, as the clocks may be skewed
+        let age = now.duration_since(time).unwrap_or_default();
+        if age > self.max_certificate_age {
+            return Err(CertificationError::CertificateTooOld {
+                age,
+                max_age: self.max_certificate_age,
+            });
+        }
+
+        Ok(())
+    }
+
+    fn verify_signature(
+        &self,
+        certificate: &Certificate,
+        der_key: &[u8],
+    ) -> Result<(), CertificationError> {
+        let key = der_key
+            .strip_prefix(BLS_KEY_DER_PREFIX.as_slice())
+            .filter(|key| key.len() == BLS_KEY_LENGTH)
+            .ok_or_else(|| {
+                CertificationError::InvalidPublicKey(format!(
+                    "expected a DER encoded BLS12-381 key, got {} bytes",
+                    der_key.len()
+                ))
+            })?;
+
+        let mut message = IC_STATE_ROOT_DOMAIN_SEPARATOR.to_vec();
+        message.extend_from_slice(&certificate.tree.digest());
+
+        ic_verify_bls_signature::verify_bls_signature(&certificate.signature, &message, key)
+            .map_err(|_| CertificationError::InvalidSignature)
+    }
+
+    /// Checks that the canister is in the ranges of the subnet the certificate is delegated to.
+    fn check_canister_ranges(
+        &self,
+        subnet_certificate: &Certificate,
+        subnet_id: &[u8],
+    ) -> Result<(), CertificationError> {
+        let ranges = match subnet_certificate.tree.lookup_path([
+            b"subnet".as_slice(),
+            subnet_id,
+            b"canister_ranges".as_slice(),
+        ]) {
+            LookupResult::Found(ranges) => ranges,
+            _ => {
+                return Err(CertificationError::InvalidDelegation(
+                    "missing subnet canister ranges".to_string(),
+                ))
+            }
+        };
+
+        let ranges: Vec<(ByteBuf, ByteBuf)> = decode("canister ranges", ranges)?;
+        let canister_id = self.canister_id.as_slice();
+        if ranges
+            .iter()
+            .any(|(low, high)| low.as_slice() <= canister_id && canister_id <= high.as_slice())
+        {
+            Ok(())
+        } else {
+            Err(CertificationError::InvalidDelegation(format!(
+                "canister {} is not in the subnet ranges",
+                self.canister_id.to_text()
+            )))
+        }
+    }
+}
+
+/// Decodes an unsigned LEB128 encoded integer, as the time of the certificates.
+fn decode_leb128(bytes: &[u8]) -> Option<u64> {
+    let mut value = 0u64;
+    for (i, byte) in bytes.iter().enumerate() {
+        let bits = u64::from(byte & 0x7f);
+        let shift = 7 * i as u32;
+        if shift >= u64::BITS || (shift > 0 && bits >> (u64::BITS - shift) != 0) {
+            return None;
+        }
+        value |= bits << shift;
+        if byte & 0x80 == 0 {
+            return (i + 1 == bytes.len()).then_some(value);
+        }
+    }
+    None
+}
+
+/// Returns the current time, which is the time of the IC inside a canister,
+/// as `SystemTime::now` is not available there.
+fn now() -> SystemTime {
+    #[cfg(target_arch = "wasm32")]
+    {
+        UNIX_EPOCH + Duration::from_nanos(ic_exports::ic_cdk::api::time())
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        SystemTime::now()
+    }
+}
+
+fn decode<'a, T: serde::Deserialize<'a>>(
+    name: &str,
+    bytes: &'a [u8],
+) -> Result<T, CertificationError> {
+    serde_cbor::from_slice(bytes)
+        .map_err(|err| CertificationError::Decoding(format!("{name}: {err}")))
+}
+
+impl<C: Client> EthJsonRpcClient<C> {
+    /// Returns the last certified block after verifying its certificate.
+    pub async fn get_verified_last_certified_block(
+        &self,
+        verifier: &CertificateVerifier,
+    ) -> EthJsonRpcResult<CertifiedResult<Block<H256>>> {
+        let certified_block = self.get_last_certified_block().await?;
+        verifier
+            .verify_block(&certified_block)
+            .map_err(|err| EthJsonRpcError::Certification(err.to_string()))?;
+
+        Ok(certified_block)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ic_certification::certificate::Delegation;
+    use ic_certification::hash_tree::{fork, label, leaf, pruned};
+    use ic_verify_bls_signature::PrivateKey;
+    use serde_bytes::Bytes;
+
+    use super::*;
+
+    const BLOCK_HASH: [u8; 32] = [7; 32];
+
+    fn block_hash_path() -> Vec<Vec<u8>> {
+        vec![b"last_block_hash".to_vec()]
+    }
+
+    fn verifier(canister_id: Principal, root_key: &PrivateKey) -> CertificateVerifier {
+        CertificateVerifier::new(canister_id, block_hash_path()).with_root_key(der_key(root_key))
+    }
+
+    fn encode_leb128(mut value: u64) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        loop {
+            let byte = (value & 0x7f) as u8;
+            value >>= 7;
+            if value == 0 {
+                bytes.push(byte);
+                return bytes;
+            }
+            bytes.push(byte | 0x80);
+        }
+    }
+
+    fn nanos_since_epoch(time: SystemTime) -> u64 {
+        time.duration_since(UNIX_EPOCH).unwrap().as_nanos() as u64
+    }
+
+    fn der_key(key: &PrivateKey) -> Vec<u8> {
+        [
+            BLS_KEY_DER_PREFIX.as_slice(),
+            key.public_key().serialize().as_slice(),
+        ]
+        .concat()
+    }
+
+    fn witness(block_hash: &[u8]) -> HashTree {
+        fork(
+            label("last_block_hash", leaf(block_hash.to_vec())),
+            pruned([1; 32]),
+        )
+    }
+
+    fn sign(
+        tree: HashTree,
+        key: &PrivateKey,
+        delegation: Option<Delegation<Vec<u8>>>,
+    ) -> Certificate {
+        let mut message = IC_STATE_ROOT_DOMAIN_SEPARATOR.to_vec();
+        message.extend_from_slice(&tree.digest());
+        Certificate {
+            tree,
+            signature: key.sign(&message).serialize().to_vec(),
+            delegation,
+        }
+    }
+
+    fn state_tree(canister_id: &Principal, certified_data: [u8; 32]) -> HashTree {
+        state_tree_at(canister_id, certified_data, SystemTime::now())
+    }
+
+    fn state_tree_at(
+        canister_id: &Principal,
+        certified_data: [u8; 32],
+        time: SystemTime,
+    ) -> HashTree {
+        fork(
+            label(
+                "canister",
+                label(
+                    canister_id.as_slice(),
+                    label("certified_data", leaf(certified_data.to_vec())),
+                ),
+            ),
+            label("time", leaf(encode_leb128(nanos_since_epoch(time)))),
+        )
+    }
+
+    fn certified_block(
+        certificate: &Certificate,
+        witness: &HashTree,
+    ) -> CertifiedResult<Block<H256>> {
+        CertifiedResult {
+            data: Block {
+                hash: Some(H256::from(BLOCK_HASH)),
+                ..Default::default()
+            },
+            witness: serde_cbor::to_vec(witness).unwrap(),
+            certificate: serde_cbor::to_vec(certificate).unwrap(),
+        }
+    }
+
+    fn keys() -> (PrivateKey, PrivateKey) {
+        let mut rng = rand::thread_rng();
+        (PrivateKey::random(&mut rng), PrivateKey::random(&mut rng))
+    }
+
+    #[test]
+    fn should_verify_certified_block() {
+        let (root_key, _) = keys();
+        let canister_id = Principal::management_canister();
+        let witness = witness(&BLOCK_HASH);
+        let certificate = sign(state_tree(&canister_id, witness.digest()), &root_key, None);
+        let certified = certified_block(&certificate, &witness);
+
+        assert_eq!(
+            verifier(canister_id, &root_key).verify_block(&certified),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn should_reject_wrong_signature() {
+        let (root_key, other_key) = keys();
+        let canister_id = Principal::management_canister();
+        let witness = witness(&BLOCK_HASH);
+        let certificate = sign(state_tree(&canister_id, witness.digest()), &other_key, None);
+
+        let verifier = verifier(canister_id, &root_key);
+
+        assert_eq!(
+            verifier.verify_block(&certified_block(&certificate, &witness)),
+            Err(CertificationError::InvalidSignature)
+        );
+    }
+
+    #[test]
+    fn should_reject_uncertified_block_hash() {
+        let (root_key, _) = keys();
+        let canister_id = Principal::management_canister();
+        let verifier = verifier(canister_id, &root_key);
+
+        // The witness does not match the certified data
+        let witness = witness(&BLOCK_HASH);
+        let certificate = sign(state_tree(&canister_id, [0; 32]), &root_key, None);
+        assert_eq!(
+            verifier.verify_block(&certified_block(&certificate, &witness)),
+            Err(CertificationError::WitnessMismatch)
+        );
+
+        // The witness certifies another block
+        let witness = self::witness(&[8; 32]);
+        let certificate = sign(state_tree(&canister_id, witness.digest()), &root_key, None);
+        assert!(matches!(
+            verifier.verify_block(&certified_block(&certificate, &witness)),
+            Err(CertificationError::BlockHashNotCertified(_))
+        ));
+
+        // The witness contains the block hash under another label
+        let witness = fork(
+            label("last_block_hash", leaf(vec![8; 32])),
+            label("other", leaf(BLOCK_HASH.to_vec())),
+        );
+        let certificate = sign(state_tree(&canister_id, witness.digest()), &root_key, None);
+        assert!(matches!(
+            verifier.verify_block(&certified_block(&certificate, &witness)),
+            Err(CertificationError::BlockHashNotCertified(_))
+        ));
+    }
+
+    #[test]
+    fn should_reject_old_certificate() {
+        let (root_key, _) = keys();
+        let canister_id = Principal::management_canister();
+        let witness = witness(&BLOCK_HASH);
+        let time = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let certificate = sign(
+            state_tree_at(&canister_id, witness.digest(), time),
+            &root_key,
+            None,
+        );
+        let certified = certified_block(&certificate, &witness);
+        let now = time + Duration::from_secs(60);
+
+        let verifier = verifier(canister_id, &root_key);
+        assert_eq!(verifier.verify_block_at(&certified, now), Ok(()));
+
+        let verifier = verifier.with_max_certificate_age(Duration::from_secs(30));
+        assert_eq!(
+            verifier.verify_block_at(&certified, now),
+            Err(CertificationError::CertificateTooOld {
+                age: Duration::from_secs(60),
+                max_age: Duration::from_secs(30)
+            })
+        );
+        assert_eq!(
+            verifier.verify_block_at(&certified, time + Duration::from_secs(10)),
+            Ok(())
+        );
+
+        // The certificate has no time
+        let tree = label(
+            "canister",
+            label(
+                canister_id.as_slice(),
+                label("certified_data", leaf(witness.digest().to_vec())),
+            ),
+        );
+        let certificate = sign(tree, &root_key, None);
+        assert_eq!(
+            verifier.verify_block(&certified_block(&certificate, &witness)),
+            Err(CertificationError::MissingTime)
+        );
+    }
+
+    #[test]
+    fn test_decode_leb128() {
+        for value in [0, 1, 127, 128, 300, u64::MAX] {
+            assert_eq!(decode_leb128(&encode_leb128(value)), Some(value));
+        }
+        assert_eq!(decode_leb128(&[]), None);
+        assert_eq!(decode_leb128(&[0x80]), None);
+        assert_eq!(decode_leb128(&[0x01, 0x02]), None);
+        assert_eq!(decode_leb128(&[0xff; 11]), None);
+    }
+
+    #[test]
+    fn should_verify_delegated_certificate() {
+        let (root_key, subnet_key) = keys();
+        let canister_id = Principal::management_canister();
+        let subnet_id = b"subnet-id".to_vec();
+        let canister_ranges = |low: &[u8], high: &[u8]| {
+            serde_cbor::to_vec(&vec![(Bytes::new(low), Bytes::new(high))]).unwrap()
+        };
+
+        let subnet_certificate = |ranges: Vec<u8>| {
+            let tree = label(
+                "subnet",
+                label(
+                    subnet_id.clone(),
+                    fork(
+                        label("canister_ranges", leaf(ranges)),
+                        label("public_key", leaf(der_key(&subnet_key))),
+                    ),
+                ),
+            );
+            serde_cbor::to_vec(&sign(tree, &root_key, None)).unwrap()
+        };
+
+        let witness = witness(&BLOCK_HASH);
+        let delegated = |ranges: Vec<u8>| {
+            sign(
+                state_tree(&canister_id, witness.digest()),
+                &subnet_key,
+                Some(Delegation {
+                    subnet_id: subnet_id.clone(),
+                    certificate: subnet_certificate(ranges),
+                }),
+            )
+        };
+        let verifier = verifier(canister_id, &root_key);
+
+        let canister_id = canister_id.as_slice();
+        let certificate = delegated(canister_ranges(canister_id, canister_id));
+        assert_eq!(
+            verifier.verify_block(&certified_block(&certificate, &witness)),
+            Ok(())
+        );
+
+        let certificate = delegated(canister_ranges(&[0xff; 30], &[0xff; 30]));
+        assert!(matches!(
+            verifier.verify_block(&certified_block(&certificate, &witness)),
+            Err(CertificationError::InvalidDelegation(_))
+        ));
+    }
+
+    #[test]
+    fn test_mainnet_root_key() {
+        let key = IC_ROOT_KEY
+            .strip_prefix(BLS_KEY_DER_PREFIX.as_slice())
+            .unwrap();
+        assert!(ic_verify_bls_signature::PublicKey::deserialize(key).is_ok());
+    }
+}
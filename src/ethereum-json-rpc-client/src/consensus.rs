@@ -0,0 +1,319 @@
+use std::future::Future;
+use std::pin::Pin;
+
+use jsonrpc_core::{Request, Response};
+
+use crate::http_outcall::HttpOutcallClient;
+use crate::{normalize_response, Client, EthJsonRpcError, EthJsonRpcResult};
+
+/// Strategy used by the [`ConsensusClient`] to decide whether the providers agree.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConsensusStrategy {
+    /// All the providers must return the same response.
+    AllEqual,
+    /// More than half of the providers must return the same response.
+    Majority,
+    /// At least the given number of providers must return the same response.
+    Threshold(usize),
+}
+
+impl ConsensusStrategy {
+    /// Returns the number of agreeing providers required out of `providers`.
+    ///
+    /// Fails if the threshold is zero or greater than the number of providers.
+    pub fn required(&self, providers: usize) -> EthJsonRpcResult<usize> {
+        match *self {
+            Self::AllEqual => Ok(providers),
+            Self::Majority => Ok(providers / 2 + 1),
+            Self::Threshold(0) => Err(EthJsonRpcError::InvalidConfiguration(
+                "the consensus threshold must be at least 1".to_string(),
+            )),
+            Self::Threshold(threshold) if threshold > providers => {
+                Err(EthJsonRpcError::InvalidConfiguration(format!(
+                    "the consensus threshold {threshold} is greater than the {providers} providers"
+                )))
+            }
+            Self::Threshold(threshold) => Ok(threshold),
+        }
+    }
+}
+
+/// A client which sends each request to several providers and returns the response
+/// on which they agree according to a [`ConsensusStrategy`].
+///
+/// It is meant to be used from canisters with [`HttpOutcallClient`] providers, so it
+/// does not rely on timers: it waits for all the providers before comparing their
+/// responses. Batch responses are compared after sorting their items by id.
+/// If the providers do not agree, [`EthJsonRpcError::InconsistentResponses`] is returned
+/// with the response of each provider.
+#[derive(Debug, Clone)]
+pub struct ConsensusClient<C: Client = HttpOutcallClient> {
+    providers: Vec<C>,
+    strategy: ConsensusStrategy,
+}
+
+impl<C: Client> ConsensusClient<C> {
+    /// Creates a new client.
+    ///
+    /// # Arguments
+    /// * `providers` - The clients of the providers.
+    /// * `strategy` - The strategy deciding whether the providers agree.
+    pub fn new(providers: Vec<C>, strategy: ConsensusStrategy) -> Self {
+        Self {
+            providers,
+            strategy,
+        }
+    }
+
+    /// Returns the clients of the providers.
+    pub fn providers(&self) -> &[C] {
+        &self.providers
+    }
+
+    /// Returns the strategy deciding whether the providers agree.
+    pub fn strategy(&self) -> ConsensusStrategy {
+        self.strategy
+    }
+}
+
+impl ConsensusClient<HttpOutcallClient> {
+    /// Creates a new client sending http outcalls to the given provider urls.
+    ///
+    /// # Arguments
+    /// * `urls` - The urls of the providers.
+    /// * `strategy` - The strategy deciding whether the providers agree.
+    pub fn from_urls(urls: impl IntoIterator<Item = String>, strategy: ConsensusStrategy) -> Self {
+        Self::new(
+            urls.into_iter().map(HttpOutcallClient::new).collect(),
+            strategy,
+        )
+    }
+}
+
+impl<C: Client + 'static> Client for ConsensusClient<C> {
+    fn send_rpc_request(
+        &self,
+        request: Request,
+    ) -> Pin<Box<dyn Future<Output = EthJsonRpcResult<Response>> + Send>> {
+        let requests = self
+            .providers
+            .iter()
+            .map(|provider| provider.send_rpc_request(request.clone()))
+            .collect::<Vec<_>>();
+        let required = self.strategy.required(self.providers.len());
+
+        Box::pin(async move {
+            if requests.is_empty() {
+                return Err(EthJsonRpcError::Transport(
+                    "no providers configured".to_string(),
+                ));
+            }
+            let required = required?;
+
+            let responses: Vec<_> = futures::future::join_all(requests)
+                .await
+                .into_iter()
+                .map(|result| result.map(normalize_response))
+                .collect();
+
+            agreed_response(responses, required)
+        })
+    }
+}
+
+/// Returns the response returned by the most providers if at least `required` of them agree.
+fn agreed_response(
+    responses: Vec<EthJsonRpcResult<Response>>,
+    required: usize,
+) -> EthJsonRpcResult<Response> {
+    // Distinct responses with the number of providers which returned them
+    let mut counts: Vec<(&Response, usize)> = Vec::new();
+    for response in responses.iter().flatten() {
+        match counts
+            .iter_mut()
+            .find(|(existing, _)| *existing == response)
+        {
+            Some((_, count)) => *count += 1,
+            None => counts.push((response, 1)),
+        }
+    }
+
+    let best = counts.iter().max_by_key(|(_, count)| *count).copied();
+    match best {
+        Some((response, agreeing)) if agreeing >= required => Ok(response.clone()),
+        best => {
+            let agreeing = best.map(|(_, count)| count).unwrap_or_default();
+            log::warn!(
+                "ConsensusClient - {agreeing} providers agree, {required} required: {responses:?}"
+            );
+            Err(EthJsonRpcError::InconsistentResponses {
+                required,
+                agreeing,
+                responses,
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use jsonrpc_core::{Call, Id, Output, Success, Value, Version};
+
+    use super::*;
+    use crate::EthJsonRpcClient;
+
+    /// A client which always returns the same block number.
+    #[derive(Clone)]
+    struct StaticClient(EthJsonRpcResult<&'static str>);
+
+    impl Client for StaticClient {
+        fn send_rpc_request(
+            &self,
+            request: Request,
+        ) -> Pin<Box<dyn Future<Output = EthJsonRpcResult<Response>> + Send>> {
+            let id = match request {
+                Request::Single(Call::MethodCall(call)) => call.id,
+                _ => Id::Null,
+            };
+            let result = self.0.clone().map(|result| {
+                Response::Single(Output::Success(Success {
+                    jsonrpc: Some(Version::V2),
+                    result: result.into(),
+                    id,
+                }))
+            });
+            Box::pin(async move { result })
+        }
+    }
+
+    fn consensus_client(
+        results: &[EthJsonRpcResult<&'static str>],
+        strategy: ConsensusStrategy,
+    ) -> EthJsonRpcClient<ConsensusClient<StaticClient>> {
+        let providers = results.iter().cloned().map(StaticClient).collect();
+        EthJsonRpcClient::new(ConsensusClient::new(providers, strategy))
+    }
+
+    fn transport_error() -> EthJsonRpcResult<&'static str> {
+        Err(EthJsonRpcError::Transport("connection refused".to_string()))
+    }
+
+    #[test]
+    fn test_required_providers() {
+        assert_eq!(ConsensusStrategy::AllEqual.required(4), Ok(4));
+        assert_eq!(ConsensusStrategy::Majority.required(4), Ok(3));
+        assert_eq!(ConsensusStrategy::Majority.required(3), Ok(2));
+        assert_eq!(ConsensusStrategy::Threshold(2).required(4), Ok(2));
+        assert!(matches!(
+            ConsensusStrategy::Threshold(10).required(4),
+            Err(EthJsonRpcError::InvalidConfiguration(_))
+        ));
+        assert!(matches!(
+            ConsensusStrategy::Threshold(0).required(4),
+            Err(EthJsonRpcError::InvalidConfiguration(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn should_fail_when_threshold_exceeds_providers() {
+        let client = consensus_client(
+            &[Ok("0x10"), Ok("0x10"), Ok("0x10")],
+            ConsensusStrategy::Threshold(4),
+        );
+
+        assert!(matches!(
+            client.get_block_number().await,
+            Err(EthJsonRpcError::InvalidConfiguration(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn should_return_agreed_response() {
+        let client = consensus_client(
+            &[Ok("0x10"), Ok("0x10"), Ok("0x10")],
+            ConsensusStrategy::AllEqual,
+        );
+        assert_eq!(client.get_block_number().await.unwrap(), 16);
+
+        let client = consensus_client(
+            &[Ok("0x10"), Ok("0x11"), Ok("0x10")],
+            ConsensusStrategy::Majority,
+        );
+        assert_eq!(client.get_block_number().await.unwrap(), 16);
+
+        let client = consensus_client(
+            &[Ok("0x10"), transport_error(), Ok("0x10"), Ok("0x11")],
+            ConsensusStrategy::Threshold(2),
+        );
+        assert_eq!(client.get_block_number().await.unwrap(), 16);
+    }
+
+    #[tokio::test]
+    async fn should_fail_on_inconsistent_responses() {
+        let client = consensus_client(
+            &[Ok("0x10"), Ok("0x11"), Ok("0x10")],
+            ConsensusStrategy::AllEqual,
+        );
+
+        let err = client.get_block_number().await.unwrap_err();
+
+        let EthJsonRpcError::InconsistentResponses {
+            required,
+            agreeing,
+            responses,
+        } = err
+        else {
+            panic!("unexpected error: {err:?}");
+        };
+        assert_eq!(required, 3);
+        assert_eq!(agreeing, 2);
+        assert_eq!(responses.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn should_count_failed_providers_as_disagreeing() {
+        let client = consensus_client(
+            &[Ok("0x10"), transport_error(), transport_error()],
+            ConsensusStrategy::Majority,
+        );
+
+        let err = client.get_block_number().await.unwrap_err();
+
+        assert!(
+            matches!(
+                &err,
+                EthJsonRpcError::InconsistentResponses { required: 2, agreeing: 1, responses }
+                    if responses[1].is_err()
+            ),
+            "{err:?}"
+        );
+    }
+
+    #[test]
+    fn should_compare_batches_regardless_of_order() {
+        let output = |id: u64, result: &str| {
+            Output::Success(Success {
+                jsonrpc: Some(Version::V2),
+                result: Value::from(result),
+                id: Id::Num(id),
+            })
+        };
+        let responses = vec![
+            Ok(normalize_response(Response::Batch(vec![
+                output(1, "a"),
+                output(2, "b"),
+            ]))),
+            Ok(normalize_response(Response::Batch(vec![
+                output(2, "b"),
+                output(1, "a"),
+            ]))),
+        ];
+
+        let response = agreed_response(responses, 2).unwrap();
+
+        assert_eq!(
+            response,
+            Response::Batch(vec![output(1, "a"), output(2, "b")])
+        );
+    }
+}
@@ -0,0 +1,193 @@
+pub use did::contract::{Contract, ContractError, ContractResult, DecodedEvent};
+use ethers_core::abi::Token;
+use ethers_core::types::{BlockNumber, TransactionReceipt, TransactionRequest, H160};
+
+use crate::{Client, EthJsonRpcClient, EthJsonRpcError, EthJsonRpcResult};
+
+/// A client to call a [`Contract`] through a JSON-RPC node.
+#[derive(Clone)]
+pub struct ContractClient<C: Client> {
+    client: EthJsonRpcClient<C>,
+    contract: Contract,
+    from: Option<H160>,
+    block: BlockNumber,
+}
+
+impl<C: Client> ContractClient<C> {
+    /// Creates a new client.
+    ///
+    /// # Arguments
+    /// * `client` - The client used to talk to the node.
+    /// * `contract` - The contract to call.
+    pub fn new(client: EthJsonRpcClient<C>, contract: Contract) -> Self {
+        Self {
+            client,
+            contract,
+            from: None,
+            block: BlockNumber::Latest,
+        }
+    }
+
+    /// Sets the sender of the calls and of the transactions.
+    pub fn with_from(mut self, from: H160) -> Self {
+        self.from = Some(from);
+        self
+    }
+
+    /// Sets the block the calls are executed at. Defaults to the latest block.
+    pub fn with_block(mut self, block: BlockNumber) -> Self {
+        self.block = block;
+        self
+    }
+
+    /// Returns the client used to talk to the node.
+    pub fn client(&self) -> &EthJsonRpcClient<C> {
+        &self.client
+    }
+
+    /// Returns the contract.
+    pub fn contract(&self) -> &Contract {
+        &self.contract
+    }
+
+    /// Calls `function` with `args` using `eth_call` and returns the decoded output.
+    pub async fn call(&self, function: &str, args: &[Token]) -> EthJsonRpcResult<Vec<Token>> {
+        let request = self.transaction(function, args)?;
+        let output = self.client.eth_call(request, self.block).await?;
+        let output = hex::decode(output.trim_start_matches("0x"))
+            .map_err(|e| EthJsonRpcError::Deserialization(e.to_string()))?;
+
+        Ok(self.contract.decode_output(function, &output)?)
+    }
+
+    /// Returns the transaction request calling `function` with `args`.
+    ///
+    /// The request can be filled and sent, e.g. with a `SignerClient`.
+    pub fn transaction(
+        &self,
+        function: &str,
+        args: &[Token],
+    ) -> EthJsonRpcResult<TransactionRequest> {
+        let data = self.contract.encode_call(function, args)?;
+        let mut request = TransactionRequest::new()
+            .to(H160::from(self.contract.address().clone()))
+            .data(data);
+        request.from = self.from;
+
+        Ok(request)
+    }
+
+    /// Decodes the events emitted by the contract in the transaction of the receipt.
+    ///
+    /// The logs of other contracts and the events missing from the ABI are skipped.
+    pub fn events(&self, receipt: &TransactionReceipt) -> EthJsonRpcResult<Vec<DecodedEvent>> {
+        let address = H160::from(self.contract.address().clone());
+        let mut events = Vec::new();
+        for log in receipt.logs.iter().filter(|log| log.address == address) {
+            match self.contract.decode_log(log) {
+                Ok(event) => events.push(event),
+                Err(ContractError::UnknownEvent) => {}
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        Ok(events)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ethers_core::abi::ethabi;
+    use ethers_core::types::{Log, H256, U256};
+    use jsonrpc_core::Params;
+
+    use super::*;
+    use crate::mock::MockClient;
+
+    const ABI: &str = r#"[
+        {
+            "type": "function",
+            "name": "balanceOf",
+            "stateMutability": "view",
+            "inputs": [{ "name": "owner", "type": "address" }],
+            "outputs": [{ "name": "", "type": "uint256" }]
+        },
+        {
+            "type": "event",
+            "name": "Approval",
+            "anonymous": false,
+            "inputs": [{ "name": "value", "type": "uint256", "indexed": false }]
+        }
+    ]"#;
+
+    fn contract_client(mock: &MockClient) -> ContractClient<MockClient> {
+        let contract = Contract::from_json(H160::repeat_byte(1).into(), ABI).unwrap();
+        ContractClient::new(EthJsonRpcClient::new(mock.clone()), contract)
+    }
+
+    #[tokio::test]
+    async fn should_call_contract_function() {
+        let mock = MockClient::new();
+        let output = ethabi::encode(&[Token::Uint(U256::from(42))]);
+        mock.on("eth_call", format!("0x{}", hex::encode(output)));
+        let client = contract_client(&mock).with_block(BlockNumber::Number(10.into()));
+        let owner = H160::repeat_byte(2);
+
+        let result = client
+            .call("balanceOf", &[Token::Address(owner)])
+            .await
+            .unwrap();
+
+        assert_eq!(result, vec![Token::Uint(42.into())]);
+        let request = client
+            .transaction("balanceOf", &[Token::Address(owner)])
+            .unwrap();
+        mock.assert_called_with(
+            "eth_call",
+            Params::Array(vec![serde_json::to_value(request).unwrap(), "0xa".into()]),
+        );
+        assert_eq!(
+            client.call("transfer", &[]).await,
+            Err(EthJsonRpcError::Contract(ContractError::UnknownFunction(
+                "transfer".to_string()
+            )))
+        );
+    }
+
+    #[test]
+    fn should_decode_receipt_events() {
+        let client = contract_client(&MockClient::new());
+        let approval = client
+            .contract()
+            .abi()
+            .event("Approval")
+            .unwrap()
+            .signature();
+        let log = Log {
+            address: H160::repeat_byte(1),
+            topics: vec![approval],
+            data: ethabi::encode(&[Token::Uint(U256::from(5))]).into(),
+            ..Default::default()
+        };
+        let receipt = TransactionReceipt {
+            logs: vec![
+                log.clone(),
+                Log {
+                    address: H160::repeat_byte(3),
+                    ..log.clone()
+                },
+                Log {
+                    topics: vec![H256::zero()],
+                    ..log
+                },
+            ],
+            ..Default::default()
+        };
+
+        let events = client.events(&receipt).unwrap();
+
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].name, "Approval");
+        assert_eq!(events[0].param("value"), Some(&Token::Uint(5.into())));
+    }
+}
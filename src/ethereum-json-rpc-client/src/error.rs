@@ -0,0 +1,394 @@
+use did::contract::ContractError;
+use did::error::{EvmError, TransactionPoolError};
+use did::U256;
+use jsonrpc_core::{ErrorCode, Response};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use thiserror::Error;
+
+/// This is the result type for all JSON-RPC client calls.
+pub type EthJsonRpcResult<T> = Result<T, EthJsonRpcError>;
+
+/// JSON-RPC error code used by the EVM canister for insufficient balance errors.
+const TRANSACTION_ERROR_CODE: i64 = -32010;
+/// JSON-RPC error code used by the EVM canister for invalid gas price errors.
+const ACCOUNT_ERROR_CODE: i64 = -32016;
+/// JSON-RPC error code used by the EVM canister for authorization errors.
+const NO_AUTHOR_ERROR_CODE: i64 = -32002;
+/// JSON-RPC error code used by the EVM canister for all the other errors.
+const EXECUTION_ERROR_CODE: i64 = -32015;
+/// JSON-RPC error code used by Ethereum nodes for reverted calls.
+const EXECUTION_REVERTED_ERROR_CODE: i64 = 3;
+
+/// Errors returned by the Ethereum JSON-RPC client.
+#[derive(Debug, Error, Clone, PartialEq, Serialize, Deserialize)]
+pub enum EthJsonRpcError {
+    /// The request could not be delivered or the response could not be received.
+    #[error("transport error: {0}")]
+    Transport(String),
+
+    /// The server answered with a non-success HTTP status.
+    #[error("RPC request failed: {status} - {body}")]
+    HttpStatus { status: u16, body: String },
+
+    /// The server answered with a JSON-RPC error object.
+    #[error("JSON-RPC error {code}: {message}")]
+    JsonRpc {
+        code: i64,
+        message: String,
+        data: Option<Value>,
+    },
+
+    /// The request parameters could not be serialized.
+    #[error("failed to serialize value: {0}")]
+    Serialization(String),
+
+    /// The response could not be deserialized into the expected type.
+    #[error("failed to deserialize value: {0}")]
+    Deserialization(String),
+
+    /// The response does not match the shape of the request, e.g. a batch
+    /// response with a wrong number of items.
+    #[error("unexpected response: {0}")]
+    UnexpectedResponse(String),
+
+    /// Not enough endpoints returned the same response.
+    #[error("no quorum reached: {agreeing} endpoints agree, {required} required, {failed} failed")]
+    NoQuorum {
+        required: usize,
+        agreeing: usize,
+        failed: usize,
+    },
+
+    /// The responses of the providers do not satisfy the consensus strategy.
+    #[error("inconsistent responses: {agreeing} providers agree, {required} required")]
+    InconsistentResponses {
+        required: usize,
+        agreeing: usize,
+        /// The normalized response or the error of each provider, in order.
+        responses: Vec<EthJsonRpcResult<Response>>,
+    },
+
+    /// The requested item does not exist.
+    #[error("{0} not found")]
+    NotFound(String),
+
+    /// No recorded interaction matches the request.
+    #[error("no recorded response for request: {0}")]
+    UnmatchedRequest(String),
+
+    /// The certificate of a certified response could not be verified.
+    #[error("certification error: {0}")]
+    Certification(String),
+
+    /// The response body is larger than the limit of the client.
+    #[error("response body larger than {limit} bytes")]
+    ResponseTooLarge { limit: usize },
+
+    /// The client is not configured correctly.
+    #[error("invalid configuration: {0}")]
+    InvalidConfiguration(String),
+
+    /// The cycles available to the canister do not cover the cost of the http outcall.
+    #[error("too few cycles: {required} required, {available} available")]
+    InsufficientCycles { required: u128, available: u128 },
+
+    /// A contract call could not be encoded or a log could not be decoded,
+    /// e.g. because the function is not part of the ABI.
+    #[error("contract error: {0}")]
+    Contract(ContractError),
+}
+
+impl EthJsonRpcError {
+    /// Returns the JSON-RPC error code if the error was returned by the server.
+    pub fn json_rpc_code(&self) -> Option<i64> {
+        match self {
+            Self::JsonRpc { code, .. } => Some(*code),
+            _ => None,
+        }
+    }
+
+    /// Returns true if the error reports a reverted transaction or call.
+    pub fn is_revert(&self) -> bool {
+        matches!(self.to_evm_error(), Some(EvmError::TransactionReverted(_)))
+    }
+
+    /// Maps a well-known JSON-RPC error back into the [`EvmError`] which produced it.
+    ///
+    /// Returns `None` if the error is not a JSON-RPC error or if it is not recognized.
+    pub fn to_evm_error(&self) -> Option<EvmError> {
+        let Self::JsonRpc {
+            code,
+            message,
+            data,
+        } = self
+        else {
+            return None;
+        };
+
+        match *code {
+            EXECUTION_REVERTED_ERROR_CODE => {
+                let reason = data
+                    .as_ref()
+                    .and_then(Value::as_str)
+                    .unwrap_or(message.as_str());
+                Some(EvmError::TransactionReverted(reason.to_string()))
+            }
+            TRANSACTION_ERROR_CODE
+            | ACCOUNT_ERROR_CODE
+            | NO_AUTHOR_ERROR_CODE
+            | EXECUTION_ERROR_CODE => evm_error_from_message(message, data.as_ref()),
+            _ => transaction_pool_error_from_node_message(message).map(EvmError::TransactionPool),
+        }
+    }
+
+    /// Maps a well-known JSON-RPC error back into the [`TransactionPoolError`] which produced it.
+    pub fn to_transaction_pool_error(&self) -> Option<TransactionPoolError> {
+        match self.to_evm_error()? {
+            EvmError::TransactionPool(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<jsonrpc_core::Error> for EthJsonRpcError {
+    fn from(err: jsonrpc_core::Error) -> Self {
+        Self::JsonRpc {
+            code: err.code.code(),
+            message: err.message,
+            data: err.data,
+        }
+    }
+}
+
+impl From<EthJsonRpcError> for jsonrpc_core::Error {
+    fn from(err: EthJsonRpcError) -> Self {
+        match err {
+            EthJsonRpcError::JsonRpc {
+                code,
+                message,
+                data,
+            } => Self {
+                code: ErrorCode::from(code),
+                message,
+                data,
+            },
+            err => Self {
+                code: ErrorCode::InternalError,
+                message: err.to_string(),
+                data: None,
+            },
+        }
+    }
+}
+
+impl From<serde_json::Error> for EthJsonRpcError {
+    fn from(err: serde_json::Error) -> Self {
+        Self::Serialization(err.to_string())
+    }
+}
+
+impl From<ContractError> for EthJsonRpcError {
+    fn from(err: ContractError) -> Self {
+        match err {
+            ContractError::Decoding(_) => Self::Deserialization(err.to_string()),
+            _ => Self::Contract(err),
+        }
+    }
+}
+
+/// Parses the message produced by the `EvmError` display implementation.
+fn evm_error_from_message(message: &str, data: Option<&Value>) -> Option<EvmError> {
+    if let Some(reason) = message.strip_prefix("The transaction has been reverted: ") {
+        let reason = data.and_then(Value::as_str).unwrap_or(reason);
+        return Some(EvmError::TransactionReverted(reason.to_string()));
+    }
+
+    if let Some(pool_error) = message.strip_prefix("transaction pool error ") {
+        return transaction_pool_error_from_message(pool_error).map(EvmError::TransactionPool);
+    }
+
+    if let Some(balances) = message.strip_prefix("insufficient balance: actual: ") {
+        let (actual, expected) = balances.split_once(", expected ")?;
+        return Some(EvmError::InsufficientBalance {
+            actual: parse_u256(actual)?,
+            expected: parse_u256(expected)?,
+        });
+    }
+
+    if let Some(gas_price) = message.strip_prefix("gas price should be >= ") {
+        return parse_u256(gas_price).map(EvmError::InvalidGasPrice);
+    }
+
+    if let Some(minimum) = message.strip_prefix("gas is too low, minimum required: ") {
+        return parse_u256(minimum).map(|minimum| EvmError::GasTooLow { minimum });
+    }
+
+    if let Some(msg) = message.strip_prefix("internal error: ") {
+        return Some(EvmError::Internal(msg.to_string()));
+    }
+
+    if let Some(msg) = message.strip_prefix("reservation failed: ") {
+        return Some(EvmError::ReservationFailed(msg.to_string()));
+    }
+
+    if let Some(msg) = message.strip_prefix("Stable Storage error: ") {
+        return Some(EvmError::StableStorageError(msg.to_string()));
+    }
+
+    if let Some(msg) = message.strip_prefix("Transaction Signature error: ") {
+        return Some(EvmError::TransactionSignature(msg.to_string()));
+    }
+
+    if let Some(msg) = message.strip_prefix("The request is not valid: ") {
+        return Some(EvmError::BadRequest(msg.to_string()));
+    }
+
+    match message {
+        "the user has no permission to call this method" => Some(EvmError::NotAuthorized),
+        "anonymous caller is not allowed" => Some(EvmError::AnonymousPrincipal),
+        _ => None,
+    }
+}
+
+/// Parses the message produced by the `TransactionPoolError` display implementation.
+fn transaction_pool_error_from_message(message: &str) -> Option<TransactionPoolError> {
+    if let Some(nonces) = message.strip_prefix("invalid transaction nonce, expected ") {
+        let (expected, actual) = nonces.split_once(", actual ")?;
+        return Some(TransactionPoolError::InvalidNonce {
+            expected: parse_u256(expected)?,
+            actual: parse_u256(actual)?,
+        });
+    }
+
+    match message {
+        "transaction already exists in the pool" => {
+            Some(TransactionPoolError::TransactionAlreadyExists)
+        }
+        "the maximum amount of transactions per sender has been reached" => {
+            Some(TransactionPoolError::TooManyTransactions)
+        }
+        "transaction gas price is too low to replace an existing transaction" => {
+            Some(TransactionPoolError::TxReplacementUnderpriced)
+        }
+        _ => None,
+    }
+}
+
+/// Parses the transaction pool messages used by geth-compatible nodes.
+fn transaction_pool_error_from_node_message(message: &str) -> Option<TransactionPoolError> {
+    match message {
+        "already known" => Some(TransactionPoolError::TransactionAlreadyExists),
+        "replacement transaction underpriced" => {
+            Some(TransactionPoolError::TxReplacementUnderpriced)
+        }
+        _ => None,
+    }
+}
+
+fn parse_u256(value: &str) -> Option<U256> {
+    ethers_core::types::U256::from_dec_str(value.trim())
+        .ok()
+        .map(Into::into)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn roundtrip(err: EvmError) -> Option<EvmError> {
+        EthJsonRpcError::from(jsonrpc_core::Error::from(err)).to_evm_error()
+    }
+
+    #[test]
+    fn test_evm_error_roundtrip() {
+        let errors = [
+            EvmError::Internal("boom".to_string()),
+            EvmError::InsufficientBalance {
+                actual: 10u64.into(),
+                expected: 42u64.into(),
+            },
+            EvmError::InvalidGasPrice(1_000_000u64.into()),
+            EvmError::NotAuthorized,
+            EvmError::ReservationFailed("reserved".to_string()),
+            EvmError::TransactionSignature("bad v".to_string()),
+            EvmError::GasTooLow {
+                minimum: 21000u64.into(),
+            },
+            EvmError::AnonymousPrincipal,
+            EvmError::BadRequest("missing field".to_string()),
+            EvmError::TransactionReverted("not enough allowance".to_string()),
+            EvmError::TransactionPool(TransactionPoolError::TransactionAlreadyExists),
+            EvmError::TransactionPool(TransactionPoolError::InvalidNonce {
+                expected: 3u64.into(),
+                actual: 1u64.into(),
+            }),
+            EvmError::TransactionPool(TransactionPoolError::TooManyTransactions),
+            EvmError::TransactionPool(TransactionPoolError::TxReplacementUnderpriced),
+        ];
+
+        for err in errors {
+            assert_eq!(roundtrip(err.clone()), Some(err));
+        }
+    }
+
+    #[test]
+    fn test_transaction_pool_error() {
+        let err = EthJsonRpcError::from(jsonrpc_core::Error::from(EvmError::TransactionPool(
+            TransactionPoolError::InvalidNonce {
+                expected: 3u64.into(),
+                actual: 1u64.into(),
+            },
+        )));
+
+        assert_eq!(
+            err.to_transaction_pool_error(),
+            Some(TransactionPoolError::InvalidNonce {
+                expected: 3u64.into(),
+                actual: 1u64.into(),
+            })
+        );
+        assert!(!err.is_revert());
+    }
+
+    #[test]
+    fn test_node_errors() {
+        let reverted = EthJsonRpcError::JsonRpc {
+            code: 3,
+            message: "execution reverted".to_string(),
+            data: Some("0x08c379a0".into()),
+        };
+        assert!(reverted.is_revert());
+        assert_eq!(
+            reverted.to_evm_error(),
+            Some(EvmError::TransactionReverted("0x08c379a0".to_string()))
+        );
+
+        let already_known = EthJsonRpcError::JsonRpc {
+            code: -32000,
+            message: "already known".to_string(),
+            data: None,
+        };
+        assert_eq!(
+            already_known.to_transaction_pool_error(),
+            Some(TransactionPoolError::TransactionAlreadyExists)
+        );
+
+        let unknown = EthJsonRpcError::JsonRpc {
+            code: -32000,
+            message: "header not found".to_string(),
+            data: None,
+        };
+        assert_eq!(unknown.to_evm_error(), None);
+    }
+
+    #[test]
+    fn test_non_json_rpc_errors_are_not_mapped() {
+        let err = EthJsonRpcError::HttpStatus {
+            status: 429,
+            body: "Too Many Requests".to_string(),
+        };
+        assert_eq!(err.json_rpc_code(), None);
+        assert_eq!(err.to_evm_error(), None);
+    }
+}
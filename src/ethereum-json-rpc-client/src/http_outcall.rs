@@ -1,20 +1,140 @@
+use std::collections::HashMap;
 use std::future::Future;
 use std::pin::Pin;
+use std::sync::{Arc, Mutex};
 
-use anyhow::Context;
-use ic_exports::ic_cdk::api::call;
+use ic_exports::ic_cdk::api::call::{self, RejectionCode};
 use ic_exports::ic_cdk::api::management_canister::http_request::{
-    self, CanisterHttpRequestArgument, HttpHeader, HttpMethod, TransformContext,
+    self, CanisterHttpRequestArgument, HttpHeader, HttpMethod, HttpResponse, TransformArgs,
+    TransformContext,
 };
-use jsonrpc_core::Request;
+use jsonrpc_core::{Call, Request};
+use serde_json::Value;
 
-use crate::Client;
+use crate::{Client, EthJsonRpcError, EthJsonRpcResult};
 
-/// Http outcall client implementation.
+/// Name of the canister query method used by default to transform the responses.
+pub const DEFAULT_TRANSFORM_METHOD: &str = "transform";
+
+/// Number of nodes of the subnet used by default to compute the cost of the requests.
+pub const DEFAULT_SUBNET_SIZE: u32 = 13;
+
+/// Maximum size of the responses of the http outcalls.
+pub const MAX_RESPONSE_BYTES: u64 = 2_000_000;
+
+/// Response size charged for by [`http_request_required_cycles`] when
+/// `max_response_bytes` is not set.
+const DEFAULT_CHARGED_RESPONSE_BYTES: u64 = 2 * 1024 * 1024;
+
+/// Fragments of the messages of the outcalls rejected with [`RejectionCode::SysFatal`]
+/// because the response exceeds `max_response_bytes`.
+const RESPONSE_TOO_LARGE_MESSAGES: [&str; 2] = [
+    "Http body exceeds size limit",
+    "Header size exceeds specified response size limit",
+];
+
+/// Configuration of the adaptive response size of the [`HttpOutcallClient`].
+///
+/// Requests start with a small `max_response_bytes` and are retried with a bigger
+/// limit when the response is too large. The limit which worked is remembered for
+/// the following requests with the same methods.
 #[derive(Debug, Clone)]
+pub struct AdaptiveResponseSize {
+    /// Limit used for the first request with a given set of methods.
+    pub initial_bytes: u64,
+    /// Highest limit used before giving up.
+    pub max_bytes: u64,
+    /// Factor by which the limit grows after a response too large.
+    pub growth_factor: u64,
+}
+
+impl Default for AdaptiveResponseSize {
+    fn default() -> Self {
+        Self {
+            initial_bytes: 4 * 1024,
+            max_bytes: MAX_RESPONSE_BYTES,
+            growth_factor: 4,
+        }
+    }
+}
+
+impl AdaptiveResponseSize {
+    /// Sets the limit used for the first request with a given set of methods.
+    pub fn with_initial_bytes(mut self, initial_bytes: u64) -> Self {
+        self.initial_bytes = initial_bytes;
+        self
+    }
+
+    /// Sets the highest limit used before giving up.
+    pub fn with_max_bytes(mut self, max_bytes: u64) -> Self {
+        self.max_bytes = max_bytes;
+        self
+    }
+
+    /// Sets the factor by which the limit grows after a response too large.
+    pub fn with_growth_factor(mut self, growth_factor: u64) -> Self {
+        self.growth_factor = growth_factor;
+        self
+    }
+
+    /// Returns the limit to retry with after a response too large, if below the cap.
+    fn next_limit(&self, limit: u64) -> Option<u64> {
+        (limit < self.max_bytes).then(|| {
+            limit
+                .saturating_mul(self.growth_factor.max(2))
+                .min(self.max_bytes)
+        })
+    }
+}
+
+/// The cost of an http outcall.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OutcallCost {
+    /// The JSON-RPC methods of the request.
+    pub methods: String,
+    /// The `max_response_bytes` of the outcall.
+    pub max_response_bytes: Option<u64>,
+    /// The cycles attached to the outcall.
+    pub cycles_attached: u128,
+    /// The cycles refunded after the outcall.
+    pub cycles_refunded: u128,
+    /// Whether the outcall succeeded.
+    pub succeeded: bool,
+}
+
+impl OutcallCost {
+    /// Returns the cycles spent by the outcall.
+    pub fn cycles_spent(&self) -> u128 {
+        self.cycles_attached.saturating_sub(self.cycles_refunded)
+    }
+}
+
+type CostObserver = Arc<dyn Fn(&OutcallCost) + Send + Sync>;
+
+/// Http outcall client implementation.
+#[derive(Clone)]
 pub struct HttpOutcallClient {
     url: String,
     max_response_bytes: Option<u64>,
+    headers: Vec<HttpHeader>,
+    transform: Option<TransformContext>,
+    subnet_size: u32,
+    adaptive_response_size: Option<AdaptiveResponseSize>,
+    learned_response_bytes: Arc<Mutex<HashMap<String, u64>>>,
+    cost_observer: Option<CostObserver>,
+}
+
+impl std::fmt::Debug for HttpOutcallClient {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("HttpOutcallClient")
+            .field("url", &self.url)
+            .field("max_response_bytes", &self.max_response_bytes)
+            .field("headers", &self.headers)
+            .field("transform", &self.transform)
+            .field("subnet_size", &self.subnet_size)
+            .field("adaptive_response_size", &self.adaptive_response_size)
+            .finish_non_exhaustive()
+    }
 }
 
 impl HttpOutcallClient {
@@ -27,11 +147,20 @@ impl HttpOutcallClient {
         Self {
             url,
             max_response_bytes: None,
+            headers: vec![],
+            transform: Some(TransformContext::from_name(
+                DEFAULT_TRANSFORM_METHOD.to_string(),
+                vec![],
+            )),
+            subnet_size: DEFAULT_SUBNET_SIZE,
+            adaptive_response_size: None,
+            learned_response_bytes: Default::default(),
+            cost_observer: None,
         }
     }
 
-    /// The maximal size of the response in bytes. If None, 2MiB will be the
-    /// limit.
+    /// The maximal size of the response in bytes. If None, the limit of the
+    /// outcalls, [`MAX_RESPONSE_BYTES`], applies.
     /// This value affects the cost of the http request and it is highly
     /// recommended to set it as low as possible to avoid unnecessary extra
     /// costs.
@@ -41,63 +170,214 @@ impl HttpOutcallClient {
     pub fn set_max_response_bytes(&mut self, max_response_bytes: Option<u64>) {
         self.max_response_bytes = max_response_bytes;
     }
+
+    /// Adds a header to the requests, e.g. to authenticate with an API key.
+    /// A header with the same name as a default one (`Host`, `Content-Type`) replaces it.
+    ///
+    /// # Arguments
+    /// * `name` - The header name.
+    /// * `value` - The header value.
+    pub fn add_header(&mut self, name: impl Into<String>, value: impl Into<String>) {
+        self.headers.push(HttpHeader {
+            name: name.into(),
+            value: value.into(),
+        });
+    }
+
+    /// The transform function applied by the replicas to the responses before
+    /// reaching consensus. By default the `transform` query method of the canister
+    /// is used, which can forward to [`transform_json_rpc_response`].
+    ///
+    /// # Arguments
+    /// * `transform` - The transform context, or None to disable the transform.
+    pub fn set_transform(&mut self, transform: Option<TransformContext>) {
+        self.transform = transform;
+    }
+
+    /// The number of nodes of the subnet of the canister, used to compute
+    /// the cycles attached to the requests. If not set, 13 nodes are assumed.
+    ///
+    /// # Arguments
+    /// * `subnet_size` - The number of nodes of the subnet.
+    pub fn set_subnet_size(&mut self, subnet_size: u32) {
+        self.subnet_size = subnet_size;
+    }
+
+    /// Enables the adaptive response size, which replaces the limit set with
+    /// [`HttpOutcallClient::set_max_response_bytes`].
+    /// The learned limits are shared by the clones of the client.
+    ///
+    /// # Arguments
+    /// * `adaptive_response_size` - The adaptive response size configuration, or None to disable it.
+    pub fn set_adaptive_response_size(
+        &mut self,
+        adaptive_response_size: Option<AdaptiveResponseSize>,
+    ) {
+        self.adaptive_response_size = adaptive_response_size;
+    }
+
+    /// Returns the response limits learned so far, by JSON-RPC methods.
+    pub fn learned_response_bytes(&self) -> HashMap<String, u64> {
+        self.learned_response_bytes
+            .lock()
+            .expect("poisoned mutex")
+            .clone()
+    }
+
+    /// Sets a function called with the cost of each http outcall, retries included.
+    ///
+    /// # Arguments
+    /// * `observer` - The function receiving the costs.
+    pub fn set_cost_observer(&mut self, observer: impl Fn(&OutcallCost) + Send + Sync + 'static) {
+        self.cost_observer = Some(Arc::new(observer));
+    }
+
+    /// Returns the response limit of the first outcall for the methods.
+    fn initial_response_bytes(&self, methods: &str) -> Option<u64> {
+        let Some(adaptive) = &self.adaptive_response_size else {
+            return self.max_response_bytes;
+        };
+
+        let learned = self
+            .learned_response_bytes
+            .lock()
+            .expect("poisoned mutex")
+            .get(methods)
+            .copied();
+        Some(learned.unwrap_or(adaptive.initial_bytes))
+    }
+
+    /// Returns the response limit to retry with after a response too large.
+    fn next_response_bytes(&self, limit: Option<u64>) -> Option<u64> {
+        let adaptive = self.adaptive_response_size.as_ref()?;
+        adaptive.next_limit(limit.unwrap_or(MAX_RESPONSE_BYTES))
+    }
+
+    /// Remembers the response limit which worked for the methods. Limits never shrink.
+    fn learn_response_bytes(&self, methods: &str, limit: Option<u64>) {
+        if let (Some(_), Some(limit)) = (&self.adaptive_response_size, limit) {
+            let mut learned = self.learned_response_bytes.lock().expect("poisoned mutex");
+            let entry = learned.entry(methods.to_string()).or_default();
+            *entry = (*entry).max(limit);
+        }
+    }
+
+    fn report_cost(&self, cost: OutcallCost) {
+        log::debug!(
+            "HttpOutcallClient - outcall for {} with max_response_bytes {:?} spent {} cycles",
+            cost.methods,
+            cost.max_response_bytes,
+            cost.cycles_spent()
+        );
+
+        if let Some(observer) = &self.cost_observer {
+            observer(&cost);
+        }
+    }
+
+    fn headers(&self, host: &str) -> Vec<HttpHeader> {
+        let defaults = [("Host", host), ("Content-Type", "application/json")];
+
+        defaults
+            .into_iter()
+            .filter(|(name, _)| {
+                !self
+                    .headers
+                    .iter()
+                    .any(|header| header.name.eq_ignore_ascii_case(name))
+            })
+            .map(|(name, value)| HttpHeader {
+                name: name.to_string(),
+                value: value.to_string(),
+            })
+            .chain(self.headers.iter().cloned())
+            .collect()
+    }
 }
 
 impl Client for HttpOutcallClient {
     fn send_rpc_request(
         &self,
         request: Request,
-    ) -> Pin<Box<dyn Future<Output = anyhow::Result<jsonrpc_core::Response>> + Send>> {
+    ) -> Pin<Box<dyn Future<Output = EthJsonRpcResult<jsonrpc_core::Response>> + Send>> {
+        let client = self.clone();
         let url = self.url.clone();
-        let max_response_bytes = self.max_response_bytes;
+        let methods = request_methods(&request);
         let body = serde_json::to_vec(&request).expect("failed to serialize body");
 
         Box::pin(async move {
             log::trace!("CanisterClient - sending 'http_outcall'. url: {url}");
 
-            let parsed_url = url::Url::parse(&url)
-                .map_err(|e| anyhow::format_err!("failed to parse url `{url}`: {e}"))?;
+            let parsed_url = url::Url::parse(&url).map_err(|e| {
+                EthJsonRpcError::InvalidConfiguration(format!("failed to parse url `{url}`: {e}"))
+            })?;
 
-            let host = parsed_url
-                .host_str()
-                .ok_or_else(|| anyhow::format_err!("no host in url `{parsed_url}`"))?;
+            let host = parsed_url.host_str().ok_or_else(|| {
+                EthJsonRpcError::InvalidConfiguration(format!("no host in url `{parsed_url}`"))
+            })?;
 
-            let headers = vec![
-                HttpHeader {
-                    name: "Host".to_string(),
-                    value: host.to_string(),
-                },
-                HttpHeader {
-                    name: "Content-Type".to_string(),
-                    value: "application/json".to_string(),
-                },
-            ];
-
-            let request = CanisterHttpRequestArgument {
-                url,
-                max_response_bytes,
-                method: HttpMethod::POST,
-                headers,
-                body: Some(body),
-                transform: Some(TransformContext::from_name("transform".to_string(), vec![])),
-            };
+            let headers = client.headers(host);
+            let mut max_response_bytes = client.initial_response_bytes(&methods);
+
+            let http_response = loop {
+                let request = CanisterHttpRequestArgument {
+                    url: url.clone(),
+                    max_response_bytes,
+                    method: HttpMethod::POST,
+                    headers: headers.clone(),
+                    body: Some(body.clone()),
+                    transform: client.transform.clone(),
+                };
 
-            let cost = http_request_required_cycles(&request);
+                let cost = http_request_required_cycles_for_subnet(&request, client.subnet_size);
 
-            let cycles_available = call::msg_cycles_available128();
-            if cycles_available < cost {
-                anyhow::bail!("Too few cycles, expected: {cost}, received: {cycles_available}");
-            }
+                let cycles_available = call::msg_cycles_available128();
+                if cycles_available < cost {
+                    return Err(EthJsonRpcError::InsufficientCycles {
+                        required: cost,
+                        available: cycles_available,
+                    });
+                }
 
-            let http_response = http_request::http_request(request, cost)
-                .await
-                .map(|(res,)| res)
-                .map_err(|(r, m)| {
-                    anyhow::format_err!(format!("RejectionCode: {r:?}, Error: {m}"))
-                })?;
+                let result = http_request::http_request(request, cost).await;
+                client.report_cost(OutcallCost {
+                    methods: methods.clone(),
+                    max_response_bytes,
+                    cycles_attached: cost,
+                    cycles_refunded: call::msg_cycles_refunded128(),
+                    succeeded: result.is_ok(),
+                });
 
-            let response = serde_json::from_slice(&http_response.body)
-                .context("failed to deserialize RPC request")?;
+                match result {
+                    Ok((response,)) => {
+                        client.learn_response_bytes(&methods, max_response_bytes);
+                        break response;
+                    }
+                    Err((code, message)) if is_response_too_large(code, &message) => {
+                        let Some(next) = client.next_response_bytes(max_response_bytes) else {
+                            log::warn!(
+                                "HttpOutcallClient - response too large for {methods}: {message}"
+                            );
+                            return Err(EthJsonRpcError::ResponseTooLarge {
+                                limit: max_response_bytes.unwrap_or(MAX_RESPONSE_BYTES) as usize,
+                            });
+                        };
+                        log::debug!(
+                            "HttpOutcallClient - response too large for {methods}, retrying with max_response_bytes {next}"
+                        );
+                        max_response_bytes = Some(next);
+                    }
+                    Err((r, m)) => {
+                        return Err(EthJsonRpcError::Transport(format!(
+                            "RejectionCode: {r:?}, Error: {m}"
+                        )))
+                    }
+                }
+            };
+
+            let response = serde_json::from_slice(&http_response.body).map_err(|e| {
+                EthJsonRpcError::Deserialization(format!("failed to deserialize RPC response: {e}"))
+            })?;
 
             log::trace!("CanisterClient - Response from http_outcall'. Response : {response:?}");
 
@@ -106,19 +386,309 @@ impl Client for HttpOutcallClient {
     }
 }
 
+/// Returns the methods of the calls of the request, identifying the requests
+/// whose responses have a similar size.
+fn request_methods(request: &Request) -> String {
+    let method = |call: &Call| match call {
+        Call::MethodCall(call) => call.method.clone(),
+        Call::Notification(notification) => notification.method.clone(),
+        Call::Invalid { .. } => "invalid".to_string(),
+    };
+
+    match request {
+        Request::Single(call) => method(call),
+        Request::Batch(calls) => {
+            let mut methods: Vec<String> = calls.iter().map(method).collect();
+            methods.sort();
+            methods.dedup();
+            format!("batch({}):{}", calls.len(), methods.join(","))
+        }
+    }
+}
+
+/// Returns true if the outcall was rejected because the response exceeds `max_response_bytes`.
+fn is_response_too_large(code: RejectionCode, message: &str) -> bool {
+    code == RejectionCode::SysFatal
+        && RESPONSE_TOO_LARGE_MESSAGES
+            .iter()
+            .any(|fragment| message.contains(fragment))
+}
+
+/// Transform function making the responses of the JSON-RPC endpoints identical on all the
+/// replicas, so that they can reach consensus.
+///
+/// The headers are removed, since they are not used by the client and often contain
+/// dates or request identifiers. JSON bodies are rewritten with sorted object keys and
+/// with the items of batch responses sorted by id. Other bodies are left unchanged.
+///
+/// The canister must expose it as the query method set with [`HttpOutcallClient::set_transform`]:
+///
+/// ```ignore
+/// #[ic_cdk::query]
+/// fn transform(args: TransformArgs) -> HttpResponse {
+///     ethereum_json_rpc_client::http_outcall::transform_json_rpc_response(args)
+/// }
+/// ```
+pub fn transform_json_rpc_response(args: TransformArgs) -> HttpResponse {
+    let HttpResponse { status, body, .. } = args.response;
+
+    let body = match serde_json::from_slice::<Value>(&body) {
+        Ok(value) => serde_json::to_vec(&normalize_json_rpc_body(value)).unwrap_or(body),
+        Err(_) => body,
+    };
+
+    HttpResponse {
+        status,
+        headers: vec![],
+        body,
+    }
+}
+
+/// Sorts the items of batch responses by id and the keys of all the objects.
+fn normalize_json_rpc_body(value: Value) -> Value {
+    match value {
+        Value::Array(mut outputs) => {
+            outputs.sort_by_cached_key(|output| output.get("id").map(Value::to_string));
+            Value::Array(outputs.into_iter().map(sort_keys).collect())
+        }
+        value => sort_keys(value),
+    }
+}
+
+/// Rebuilds the objects with sorted keys, which the JSON map does not guarantee
+/// when the `preserve_order` feature of `serde_json` is enabled.
+fn sort_keys(value: Value) -> Value {
+    match value {
+        Value::Object(map) => {
+            let mut entries: Vec<_> = map.into_iter().collect();
+            entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+            Value::Object(
+                entries
+                    .into_iter()
+                    .map(|(key, value)| (key, sort_keys(value)))
+                    .collect(),
+            )
+        }
+        Value::Array(items) => Value::Array(items.into_iter().map(sort_keys).collect()),
+        value => value,
+    }
+}
+
 // Calculate cycles for http_request
 // NOTE:
 // https://github.com/dfinity/cdk-rs/blob/710a6cdcc3eb03d2392df1dfd5f047dff9deee80/examples/management_canister/src/caller/lib.rs#L7-L19
 pub fn http_request_required_cycles(arg: &CanisterHttpRequestArgument) -> u128 {
-    let max_response_bytes = match arg.max_response_bytes {
-        Some(ref n) => *n as u128,
-        None => 2 * 1024 * 1024u128, // default 2MiB
-    };
-    let arg_raw = candid::utils::encode_args((arg,)).expect("Failed to encode arguments.");
     // The fee is for a 13-node subnet to demonstrate a typical usage.
+    http_request_required_cycles_for_subnet(arg, DEFAULT_SUBNET_SIZE)
+}
+
+/// Returns the cycles required by an http outcall on a subnet with `subnet_size` nodes.
+pub fn http_request_required_cycles_for_subnet(
+    arg: &CanisterHttpRequestArgument,
+    subnet_size: u32,
+) -> u128 {
+    let max_response_bytes = arg
+        .max_response_bytes
+        .unwrap_or(DEFAULT_CHARGED_RESPONSE_BYTES) as u128;
+    let arg_raw = candid::utils::encode_args((arg,)).expect("Failed to encode arguments.");
+    let nodes = subnet_size as u128;
     (3_000_000u128
-        + 60_000u128 * 13
+        + 60_000u128 * nodes
         + (arg_raw.len() as u128 + "http_request".len() as u128) * 400
         + max_response_bytes * 800)
-        * 13
+        * nodes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn transform(body: &str) -> HttpResponse {
+        transform_json_rpc_response(TransformArgs {
+            response: HttpResponse {
+                status: 200u64.into(),
+                headers: vec![HttpHeader {
+                    name: "Date".to_string(),
+                    value: "Tue, 15 Nov 1994 08:12:31 GMT".to_string(),
+                }],
+                body: body.as_bytes().to_vec(),
+            },
+            context: vec![],
+        })
+    }
+
+    #[test]
+    fn should_normalize_json_rpc_responses() {
+        let a = transform(
+            r#"[{"id":2,"jsonrpc":"2.0","result":{"b":1,"a":2}},{"result":"0x1","jsonrpc":"2.0","id":1}]"#,
+        );
+        let b = transform(
+            r#"[{"jsonrpc":"2.0","id":1,"result":"0x1"},{"jsonrpc":"2.0","result":{"a":2,"b":1},"id":2}]"#,
+        );
+
+        assert_eq!(a, b);
+        assert!(a.headers.is_empty());
+        assert_eq!(a.status, 200u64.into());
+        assert_eq!(
+            String::from_utf8(a.body).unwrap(),
+            r#"[{"id":1,"jsonrpc":"2.0","result":"0x1"},{"id":2,"jsonrpc":"2.0","result":{"a":2,"b":1}}]"#
+        );
+    }
+
+    #[test]
+    fn should_keep_non_json_bodies() {
+        let response = transform("Too Many Requests");
+
+        assert_eq!(response.body, b"Too Many Requests");
+        assert!(response.headers.is_empty());
+    }
+
+    #[test]
+    fn should_replace_default_headers() {
+        let mut client = HttpOutcallClient::new("https://example.com".to_string());
+        client.add_header("Authorization", "Bearer token");
+        client.add_header("content-type", "application/json-rpc");
+
+        let headers = client.headers("example.com");
+
+        assert_eq!(
+            headers,
+            vec![
+                HttpHeader {
+                    name: "Host".to_string(),
+                    value: "example.com".to_string(),
+                },
+                HttpHeader {
+                    name: "Authorization".to_string(),
+                    value: "Bearer token".to_string(),
+                },
+                HttpHeader {
+                    name: "content-type".to_string(),
+                    value: "application/json-rpc".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn should_grow_response_limit_up_to_cap() {
+        let adaptive = AdaptiveResponseSize::default()
+            .with_initial_bytes(1000)
+            .with_max_bytes(10_000)
+            .with_growth_factor(4);
+
+        assert_eq!(adaptive.next_limit(1000), Some(4000));
+        assert_eq!(adaptive.next_limit(4000), Some(10_000));
+        assert_eq!(adaptive.next_limit(10_000), None);
+    }
+
+    #[test]
+    fn should_learn_response_limits_by_methods() {
+        let mut client = HttpOutcallClient::new("https://example.com".to_string());
+        client.set_max_response_bytes(Some(500));
+        assert_eq!(client.initial_response_bytes("eth_getLogs"), Some(500));
+        assert_eq!(client.next_response_bytes(Some(500)), None);
+
+        client.set_adaptive_response_size(Some(
+            AdaptiveResponseSize::default().with_initial_bytes(1000),
+        ));
+        let clone = client.clone();
+
+        assert_eq!(client.initial_response_bytes("eth_getLogs"), Some(1000));
+        client.learn_response_bytes("eth_getLogs", Some(16_000));
+        client.learn_response_bytes("eth_getLogs", Some(4000));
+
+        assert_eq!(clone.initial_response_bytes("eth_getLogs"), Some(16_000));
+        assert_eq!(clone.initial_response_bytes("eth_chainId"), Some(1000));
+        assert_eq!(
+            clone.learned_response_bytes(),
+            HashMap::from([("eth_getLogs".to_string(), 16_000)])
+        );
+    }
+
+    #[test]
+    fn should_identify_requests_by_methods() {
+        let call = |method: &str| {
+            Call::MethodCall(jsonrpc_core::MethodCall {
+                jsonrpc: Some(jsonrpc_core::Version::V2),
+                method: method.to_string(),
+                params: jsonrpc_core::Params::Array(vec![]),
+                id: jsonrpc_core::Id::Num(1),
+            })
+        };
+
+        assert_eq!(
+            request_methods(&Request::Single(call("eth_getLogs"))),
+            "eth_getLogs"
+        );
+        assert_eq!(
+            request_methods(&Request::Batch(vec![
+                call("eth_getBlockByNumber"),
+                call("eth_chainId"),
+                call("eth_getBlockByNumber"),
+            ])),
+            "batch(3):eth_chainId,eth_getBlockByNumber"
+        );
+    }
+
+    #[test]
+    fn should_detect_responses_too_large() {
+        for fragment in RESPONSE_TOO_LARGE_MESSAGES {
+            let message = format!("{fragment} 4096");
+            assert!(is_response_too_large(RejectionCode::SysFatal, &message));
+            assert!(!is_response_too_large(
+                RejectionCode::CanisterReject,
+                &message
+            ));
+        }
+        assert!(!is_response_too_large(
+            RejectionCode::SysFatal,
+            "Timeout expired"
+        ));
+        assert!(!is_response_too_large(
+            RejectionCode::SysTransient,
+            "request exceeds the size limit of the endpoint"
+        ));
+    }
+
+    #[test]
+    fn should_compute_cycles_for_subnet_size() {
+        let arg = CanisterHttpRequestArgument {
+            url: "https://example.com".to_string(),
+            max_response_bytes: Some(1000),
+            ..Default::default()
+        };
+        let request_bytes = candid::utils::encode_args((&arg,)).unwrap().len() as u128
+            + "http_request".len() as u128;
+
+        let expected = |nodes: u128| {
+            (3_000_000 + 60_000 * nodes) * nodes + 400 * nodes * request_bytes + 800 * nodes * 1000
+        };
+
+        assert_eq!(
+            http_request_required_cycles_for_subnet(&arg, 13),
+            expected(13)
+        );
+        assert_eq!(
+            http_request_required_cycles_for_subnet(&arg, 34),
+            expected(34)
+        );
+        assert_eq!(http_request_required_cycles(&arg), expected(13));
+    }
+
+    #[test]
+    fn should_charge_for_2_mib_without_max_response_bytes() {
+        let arg = CanisterHttpRequestArgument {
+            url: "https://example.com".to_string(),
+            max_response_bytes: None,
+            ..Default::default()
+        };
+        let request_bytes = candid::utils::encode_args((&arg,)).unwrap().len() as u128
+            + "http_request".len() as u128;
+
+        assert_eq!(
+            http_request_required_cycles(&arg),
+            (3_000_000 + 60_000 * 13) * 13 + 400 * 13 * request_bytes + 800 * 13 * 2 * 1024 * 1024
+        );
+    }
 }
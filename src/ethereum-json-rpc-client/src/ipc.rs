@@ -0,0 +1,117 @@
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use futures::future::BoxFuture;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader, Lines};
+use tokio::net::unix::{OwnedReadHalf, OwnedWriteHalf};
+use tokio::net::UnixStream;
+
+use crate::pubsub::{Connection, ConnectionConfig, Transport, TransportClient};
+use crate::{EthJsonRpcError, EthJsonRpcResult};
+
+/// Connection settings of the [`IpcClient`].
+pub type IpcConfig = ConnectionConfig;
+
+/// A JSON-RPC client over a Unix domain socket.
+///
+/// Messages are delimited by newlines.
+/// See [`TransportClient`] for the reconnection and multiplexing behaviour.
+pub type IpcClient = TransportClient<IpcTransport>;
+
+impl IpcClient {
+    /// Connects to the given socket with the default configuration.
+    ///
+    /// # Arguments
+    /// * `path` - The path of the socket, e.g. `/tmp/evmc.ipc`.
+    pub async fn connect(path: impl AsRef<Path>) -> EthJsonRpcResult<Self> {
+        Self::connect_with_config(path, IpcConfig::default()).await
+    }
+
+    /// Connects to the given socket.
+    ///
+    /// Must be called within a tokio runtime, which runs the connection task.
+    ///
+    /// # Arguments
+    /// * `path` - The path of the socket, e.g. `/tmp/evmc.ipc`.
+    /// * `config` - The connection settings.
+    pub async fn connect_with_config(
+        path: impl AsRef<Path>,
+        config: IpcConfig,
+    ) -> EthJsonRpcResult<Self> {
+        let transport = IpcTransport {
+            path: path.as_ref().to_path_buf(),
+        };
+        Self::connect_with_transport(transport, config).await
+    }
+}
+
+/// Opens the connections of the [`IpcClient`].
+pub struct IpcTransport {
+    path: PathBuf,
+}
+
+impl fmt::Display for IpcTransport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.path.display().fmt(f)
+    }
+}
+
+impl Transport for IpcTransport {
+    type Connection = IpcConnection;
+
+    const NAME: &'static str = "IpcClient";
+
+    fn connect(&self) -> BoxFuture<'_, EthJsonRpcResult<Self::Connection>> {
+        Box::pin(async move {
+            let stream = UnixStream::connect(&self.path).await.map_err(|e| {
+                EthJsonRpcError::Transport(format!(
+                    "failed to connect to {}: {e}",
+                    self.path.display()
+                ))
+            })?;
+            let (reader, writer) = stream.into_split();
+
+            Ok(IpcConnection {
+                lines: BufReader::new(reader).lines(),
+                writer,
+            })
+        })
+    }
+}
+
+/// An open connection to a Unix domain socket, exchanging a message per line.
+pub struct IpcConnection {
+    lines: Lines<BufReader<OwnedReadHalf>>,
+    writer: OwnedWriteHalf,
+}
+
+impl Connection for IpcConnection {
+    fn send(&mut self, mut message: String) -> BoxFuture<'_, EthJsonRpcResult<()>> {
+        Box::pin(async move {
+            message.push('\n');
+            self.writer
+                .write_all(message.as_bytes())
+                .await
+                .map_err(|e| EthJsonRpcError::Transport(e.to_string()))
+        })
+    }
+
+    fn receive(&mut self) -> BoxFuture<'_, Option<EthJsonRpcResult<String>>> {
+        Box::pin(async move {
+            loop {
+                match self.lines.next_line().await {
+                    Ok(Some(line)) if line.trim().is_empty() => {}
+                    Ok(Some(line)) => return Some(Ok(line)),
+                    Ok(None) => return None,
+                    Err(e) => return Some(Err(EthJsonRpcError::Transport(e.to_string()))),
+                }
+            }
+        })
+    }
+
+    fn close(&mut self) -> BoxFuture<'_, ()> {
+        Box::pin(async move {
+            let _ = self.writer.shutdown().await;
+        })
+    }
+}
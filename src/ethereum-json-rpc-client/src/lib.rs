@@ -1,27 +1,84 @@
+use std::collections::{HashMap, VecDeque};
 use std::future::Future;
 use std::pin::Pin;
 
-use anyhow::Context;
 use did::certified::CertifiedResult;
+use did::trace::{BlockTraceResult, CallFrame, TraceOptions};
 use did::transaction::StorableExecutionResult;
 use ethers_core::types::{
-    Block, BlockNumber, Log, Transaction, TransactionReceipt, TransactionRequest, H160, H256, U256,
-    U64,
+    Block, BlockNumber, FeeHistory, Log, Transaction, TransactionReceipt, TransactionRequest, H160,
+    H256, U256, U64,
 };
+use futures::{StreamExt, TryStreamExt};
 use itertools::Itertools;
 use jsonrpc_core::{Call, Id, MethodCall, Output, Params, Request, Response, Version};
 use serde::de::DeserializeOwned;
-use serde::{Deserialize, Serialize};
+
+macro_rules! make_params_array {
+    ($($items:expr),*) => {
+        Params::Array(vec![$(serde_json::to_value($items)?, )*])
+    };
+}
+
+pub mod batch;
+pub mod contract;
+pub mod error;
+pub mod logs;
 
 #[cfg(feature = "reqwest")]
 pub mod reqwest;
 
+#[cfg(feature = "block-stream")]
+pub mod block_stream;
+
+#[cfg(feature = "certification")]
+pub mod certification;
+
 #[cfg(feature = "ic-canister-client")]
 pub mod canister_client;
 
+#[cfg(feature = "http-outcall")]
+pub mod consensus;
+
 #[cfg(feature = "http-outcall")]
 pub mod http_outcall;
 
+#[cfg(feature = "metrics")]
+pub mod metrics;
+
+#[cfg(feature = "multi-endpoint")]
+pub mod multi_endpoint;
+
+#[cfg(any(test, feature = "test-utils"))]
+pub mod mock;
+
+#[cfg(feature = "pending-transaction")]
+pub mod pending_transaction;
+
+#[cfg(feature = "rate-limit")]
+pub mod rate_limit;
+
+#[cfg(feature = "record-replay")]
+pub mod record_replay;
+
+#[cfg(feature = "retry")]
+pub mod retry;
+
+#[cfg(feature = "signer")]
+pub mod signer;
+
+#[cfg(all(unix, feature = "ipc"))]
+pub mod ipc;
+
+#[cfg(any(feature = "ipc", feature = "websocket"))]
+pub mod pubsub;
+
+#[cfg(feature = "websocket")]
+pub mod websocket;
+
+pub use error::{EthJsonRpcError, EthJsonRpcResult};
+pub use logs::{EthGetLogsParams, LogsBlockFilter};
+
 const ETH_CHAIN_ID_METHOD: &str = "eth_chainId";
 const ETH_GET_BALANCE_METHOD: &str = "eth_getBalance";
 const ETH_GAS_PRICE_METHOD: &str = "eth_gasPrice";
@@ -33,20 +90,28 @@ const ETH_GET_TRANSACTION_RECEIPT_METHOD: &str = "eth_getTransactionReceipt";
 const ETH_CALL_METHOD: &str = "eth_call";
 const ETH_SEND_RAW_TRANSACTION_METHOD: &str = "eth_sendRawTransaction";
 const ETH_GET_LOGS_METHOD: &str = "eth_getLogs";
+const ETH_GET_TRANSACTION_BY_HASH_METHOD: &str = "eth_getTransactionByHash";
+const ETH_GET_BLOCK_BY_HASH_METHOD: &str = "eth_getBlockByHash";
+const ETH_GET_BLOCK_TRANSACTION_COUNT_BY_NUMBER_METHOD: &str =
+    "eth_getBlockTransactionCountByNumber";
+const ETH_GET_BLOCK_TRANSACTION_COUNT_BY_HASH_METHOD: &str = "eth_getBlockTransactionCountByHash";
+const ETH_GET_STORAGE_AT_METHOD: &str = "eth_getStorageAt";
+const ETH_ESTIMATE_GAS_METHOD: &str = "eth_estimateGas";
+const ETH_FEE_HISTORY_METHOD: &str = "eth_feeHistory";
+const ETH_MAX_PRIORITY_FEE_PER_GAS_METHOD: &str = "eth_maxPriorityFeePerGas";
+const NET_VERSION_METHOD: &str = "net_version";
+const WEB3_CLIENT_VERSION_METHOD: &str = "web3_clientVersion";
+const DEBUG_TRACE_TRANSACTION_METHOD: &str = "debug_traceTransaction";
+const DEBUG_TRACE_BLOCK_BY_NUMBER_METHOD: &str = "debug_traceBlockByNumber";
 const IC_GET_TX_EXECUTION_RESULT_BY_HASH_METHOD: &str = "ic_getExeResultByHash";
 const IC_GET_GENESIS_BALANCES: &str = "ic_getGenesisBalances";
 const IC_GET_LAST_CERTIFIED_BLOCK: &str = "ic_getLastCertifiedBlock";
 
-macro_rules! make_params_array {
-    ($($items:expr),*) => {
-        Params::Array(vec![$(serde_json::to_value($items)?, )*])
-    };
-}
-
 /// A client for interacting with an Ethereum node over JSON-RPC.
 #[derive(Clone)]
 pub struct EthJsonRpcClient<C: Client> {
     client: C,
+    max_concurrent_batches: usize,
 }
 
 impl<C: Client> EthJsonRpcClient<C> {
@@ -55,11 +120,27 @@ impl<C: Client> EthJsonRpcClient<C> {
     /// # Arguments
     /// * `client` - The canister client.
     pub fn new(client: C) -> Self {
-        Self { client }
+        Self {
+            client,
+            max_concurrent_batches: 1,
+        }
+    }
+
+    /// Sets the maximum number of batches sent in parallel by batch requests.
+    ///
+    /// Batches are sent one at a time by default.
+    pub fn with_max_concurrent_batches(mut self, max_concurrent_batches: usize) -> Self {
+        self.max_concurrent_batches = max_concurrent_batches.max(1);
+        self
+    }
+
+    /// Returns the maximum number of batches sent in parallel by batch requests.
+    pub fn max_concurrent_batches(&self) -> usize {
+        self.max_concurrent_batches
     }
 
     /// Returns block with transaction hashes by number
-    pub async fn get_block_by_number(&self, block: BlockNumber) -> anyhow::Result<Block<H256>> {
+    pub async fn get_block_by_number(&self, block: BlockNumber) -> EthJsonRpcResult<Block<H256>> {
         self.single_request(
             ETH_GET_BLOCK_BY_NUMBER_METHOD.to_string(),
             make_params_array!(block, false),
@@ -73,7 +154,7 @@ impl<C: Client> EthJsonRpcClient<C> {
     pub async fn get_full_block_by_number(
         &self,
         block: BlockNumber,
-    ) -> anyhow::Result<Block<Transaction>> {
+    ) -> EthJsonRpcResult<Block<Transaction>> {
         self.single_request(
             ETH_GET_BLOCK_BY_NUMBER_METHOD.to_string(),
             make_params_array!(block, true),
@@ -88,14 +169,14 @@ impl<C: Client> EthJsonRpcClient<C> {
         &self,
         blocks: impl IntoIterator<Item = BlockNumber>,
         max_batch_size: usize,
-    ) -> anyhow::Result<Vec<Block<Transaction>>> {
+    ) -> EthJsonRpcResult<Vec<Block<Transaction>>> {
         let params = blocks
             .into_iter()
             .enumerate()
-            .map(|(index, block_number)| -> anyhow::Result<(Params, Id)> {
+            .map(|(index, block_number)| -> EthJsonRpcResult<(Params, Id)> {
                 Ok((make_params_array!(block_number, true), Id::Num(index as _)))
             })
-            .collect::<anyhow::Result<Vec<_>>>()?;
+            .collect::<EthJsonRpcResult<Vec<_>>>()?;
         self.batch_request(
             ETH_GET_BLOCK_BY_NUMBER_METHOD.to_string(),
             params,
@@ -104,28 +185,37 @@ impl<C: Client> EthJsonRpcClient<C> {
         .await
     }
 
-    /// Get receipt by number
+    /// Get receipts by hash
+    ///
+    /// Returns a result for every hash, so that a missing receipt does not fail the whole batch.
     pub async fn get_receipts_by_hash(
         &self,
         hashes: impl IntoIterator<Item = H256>,
         max_batch_size: usize,
-    ) -> anyhow::Result<Vec<TransactionReceipt>> {
+    ) -> EthJsonRpcResult<Vec<EthJsonRpcResult<TransactionReceipt>>> {
         let params = hashes
             .into_iter()
-            .map(|hash| -> anyhow::Result<(Params, Id)> {
-                Ok((make_params_array!(hash), Id::Str(hash.to_string())))
+            .map(|hash| -> EthJsonRpcResult<(Params, Id)> {
+                Ok((make_params_array!(hash), Id::Str(format!("{hash:?}"))))
             })
-            .collect::<anyhow::Result<Vec<_>>>()?;
-        self.batch_request(
-            ETH_GET_TRANSACTION_RECEIPT_METHOD.to_string(),
-            params,
-            max_batch_size,
-        )
-        .await
+            .collect::<EthJsonRpcResult<Vec<_>>>()?;
+
+        Ok(self
+            .batch_request_results::<Option<TransactionReceipt>>(
+                ETH_GET_TRANSACTION_RECEIPT_METHOD.to_string(),
+                params,
+                max_batch_size,
+            )
+            .await?
+            .into_iter()
+            .map(|receipt| {
+                receipt?.ok_or_else(|| EthJsonRpcError::NotFound("transaction receipt".to_string()))
+            })
+            .collect())
     }
 
     /// Get receipt by hash
-    pub async fn get_receipt_by_hash(&self, hash: H256) -> anyhow::Result<TransactionReceipt> {
+    pub async fn get_receipt_by_hash(&self, hash: H256) -> EthJsonRpcResult<TransactionReceipt> {
         self.single_request(
             ETH_GET_TRANSACTION_RECEIPT_METHOD.to_string(),
             make_params_array!(hash),
@@ -135,7 +225,7 @@ impl<C: Client> EthJsonRpcClient<C> {
     }
 
     /// Returns chain block number
-    pub async fn get_block_number(&self) -> anyhow::Result<u64> {
+    pub async fn get_block_number(&self) -> EthJsonRpcResult<u64> {
         self.single_request::<U64>(
             ETH_BLOCK_NUMBER_METHOD.to_string(),
             make_params_array!(),
@@ -146,7 +236,7 @@ impl<C: Client> EthJsonRpcClient<C> {
     }
 
     /// Returns chain id
-    pub async fn get_chain_id(&self) -> anyhow::Result<u64> {
+    pub async fn get_chain_id(&self) -> EthJsonRpcResult<u64> {
         self.single_request::<U64>(
             ETH_CHAIN_ID_METHOD.to_string(),
             Params::Array(vec![]),
@@ -157,7 +247,7 @@ impl<C: Client> EthJsonRpcClient<C> {
     }
 
     /// Returns balance of the address.
-    pub async fn get_balance(&self, address: H160, block: BlockNumber) -> anyhow::Result<U256> {
+    pub async fn get_balance(&self, address: H160, block: BlockNumber) -> EthJsonRpcResult<U256> {
         self.single_request(
             ETH_GET_BALANCE_METHOD.to_string(),
             make_params_array!(address, block),
@@ -167,7 +257,7 @@ impl<C: Client> EthJsonRpcClient<C> {
     }
 
     /// Returns the gas price
-    pub async fn gas_price(&self) -> anyhow::Result<U256> {
+    pub async fn gas_price(&self) -> EthJsonRpcResult<U256> {
         self.single_request(
             ETH_GAS_PRICE_METHOD.to_string(),
             make_params_array!(),
@@ -177,7 +267,7 @@ impl<C: Client> EthJsonRpcClient<C> {
     }
 
     /// Returns code of the given contract.
-    pub async fn get_code(&self, address: H160, block: BlockNumber) -> anyhow::Result<String> {
+    pub async fn get_code(&self, address: H160, block: BlockNumber) -> EthJsonRpcResult<String> {
         self.single_request(
             ETH_GET_CODE_METHOD.to_string(),
             make_params_array!(address, block),
@@ -191,7 +281,7 @@ impl<C: Client> EthJsonRpcClient<C> {
         &self,
         address: H160,
         block: BlockNumber,
-    ) -> anyhow::Result<u64> {
+    ) -> EthJsonRpcResult<u64> {
         self.single_request::<U64>(
             ETH_GET_TRANSACTION_COUNT_METHOD.to_string(),
             make_params_array!(address, block),
@@ -206,7 +296,7 @@ impl<C: Client> EthJsonRpcClient<C> {
         &self,
         params: TransactionRequest,
         block: BlockNumber,
-    ) -> anyhow::Result<String> {
+    ) -> EthJsonRpcResult<String> {
         self.single_request(
             ETH_CALL_METHOD.to_string(),
             make_params_array!(params, block),
@@ -216,7 +306,7 @@ impl<C: Client> EthJsonRpcClient<C> {
     }
 
     /// Sends raw transaction and takes the arguments in bytes.
-    pub async fn send_raw_transaction_bytes(&self, transaction: &[u8]) -> anyhow::Result<H256> {
+    pub async fn send_raw_transaction_bytes(&self, transaction: &[u8]) -> EthJsonRpcResult<H256> {
         let transaction = format!("0x{}", hex::encode(transaction));
         self.single_request(
             ETH_SEND_RAW_TRANSACTION_METHOD.to_string(),
@@ -227,7 +317,7 @@ impl<C: Client> EthJsonRpcClient<C> {
     }
 
     /// Sends raw transaction and returns transaction hash
-    pub async fn send_raw_transaction(&self, transaction: Transaction) -> anyhow::Result<H256> {
+    pub async fn send_raw_transaction(&self, transaction: Transaction) -> EthJsonRpcResult<H256> {
         let bytes = transaction.rlp();
         let transaction = format!("0x{}", hex::encode(bytes));
 
@@ -240,7 +330,7 @@ impl<C: Client> EthJsonRpcClient<C> {
     }
 
     /// Get EVM logs according to the given parameters.
-    pub async fn get_logs(&self, params: EthGetLogsParams) -> anyhow::Result<Vec<Log>> {
+    pub async fn get_logs(&self, params: EthGetLogsParams) -> EthJsonRpcResult<Vec<Log>> {
         self.single_request(
             ETH_GET_LOGS_METHOD.to_string(),
             make_params_array!(params),
@@ -249,11 +339,251 @@ impl<C: Client> EthJsonRpcClient<C> {
         .await
     }
 
+    /// Returns the transaction by hash
+    pub async fn get_transaction_by_hash(&self, hash: H256) -> EthJsonRpcResult<Transaction> {
+        self.single_request::<Option<Transaction>>(
+            ETH_GET_TRANSACTION_BY_HASH_METHOD.to_string(),
+            make_params_array!(hash),
+            Id::Str(ETH_GET_TRANSACTION_BY_HASH_METHOD.to_string()),
+        )
+        .await?
+        .ok_or_else(|| EthJsonRpcError::NotFound("transaction".to_string()))
+    }
+
+    /// Returns the transactions by hash in batch
+    ///
+    /// Returns a result for every hash, so that a missing transaction does not fail the whole batch.
+    pub async fn get_transactions_by_hash(
+        &self,
+        hashes: impl IntoIterator<Item = H256>,
+        max_batch_size: usize,
+    ) -> EthJsonRpcResult<Vec<EthJsonRpcResult<Transaction>>> {
+        let params = hashes
+            .into_iter()
+            .map(|hash| -> EthJsonRpcResult<(Params, Id)> {
+                Ok((make_params_array!(hash), Id::Str(format!("{hash:?}"))))
+            })
+            .collect::<EthJsonRpcResult<Vec<_>>>()?;
+
+        Ok(self
+            .batch_request_results::<Option<Transaction>>(
+                ETH_GET_TRANSACTION_BY_HASH_METHOD.to_string(),
+                params,
+                max_batch_size,
+            )
+            .await?
+            .into_iter()
+            .map(|transaction| {
+                transaction?.ok_or_else(|| EthJsonRpcError::NotFound("transaction".to_string()))
+            })
+            .collect())
+    }
+
+    /// Returns block with transaction hashes by hash
+    pub async fn get_block_by_hash(&self, hash: H256) -> EthJsonRpcResult<Block<H256>> {
+        self.single_request::<Option<Block<H256>>>(
+            ETH_GET_BLOCK_BY_HASH_METHOD.to_string(),
+            make_params_array!(hash, false),
+            Id::Str(ETH_GET_BLOCK_BY_HASH_METHOD.to_string()),
+        )
+        .await?
+        .ok_or_else(|| EthJsonRpcError::NotFound("block".to_string()))
+    }
+
+    /// Returns full block by hash
+    pub async fn get_full_block_by_hash(&self, hash: H256) -> EthJsonRpcResult<Block<Transaction>> {
+        self.single_request::<Option<Block<Transaction>>>(
+            ETH_GET_BLOCK_BY_HASH_METHOD.to_string(),
+            make_params_array!(hash, true),
+            Id::Str(ETH_GET_BLOCK_BY_HASH_METHOD.to_string()),
+        )
+        .await?
+        .ok_or_else(|| EthJsonRpcError::NotFound("block".to_string()))
+    }
+
+    /// Returns full blocks by hash
+    pub async fn get_full_blocks_by_hash(
+        &self,
+        hashes: impl IntoIterator<Item = H256>,
+        max_batch_size: usize,
+    ) -> EthJsonRpcResult<Vec<Block<Transaction>>> {
+        let params = hashes
+            .into_iter()
+            .enumerate()
+            .map(|(index, hash)| -> EthJsonRpcResult<(Params, Id)> {
+                Ok((make_params_array!(hash, true), Id::Num(index as _)))
+            })
+            .collect::<EthJsonRpcResult<Vec<_>>>()?;
+
+        self.batch_request::<Option<Block<Transaction>>>(
+            ETH_GET_BLOCK_BY_HASH_METHOD.to_string(),
+            params,
+            max_batch_size,
+        )
+        .await?
+        .into_iter()
+        .map(|block| block.ok_or_else(|| EthJsonRpcError::NotFound("block".to_string())))
+        .collect()
+    }
+
+    /// Returns the number of transactions in the block with the given number.
+    pub async fn get_block_transaction_count_by_number(
+        &self,
+        block: BlockNumber,
+    ) -> EthJsonRpcResult<u64> {
+        self.single_request::<Option<U64>>(
+            ETH_GET_BLOCK_TRANSACTION_COUNT_BY_NUMBER_METHOD.to_string(),
+            make_params_array!(block),
+            Id::Str(ETH_GET_BLOCK_TRANSACTION_COUNT_BY_NUMBER_METHOD.to_string()),
+        )
+        .await?
+        .map(|v| v.as_u64())
+        .ok_or_else(|| EthJsonRpcError::NotFound("block".to_string()))
+    }
+
+    /// Returns the number of transactions in the block with the given hash.
+    pub async fn get_block_transaction_count_by_hash(&self, hash: H256) -> EthJsonRpcResult<u64> {
+        self.single_request::<Option<U64>>(
+            ETH_GET_BLOCK_TRANSACTION_COUNT_BY_HASH_METHOD.to_string(),
+            make_params_array!(hash),
+            Id::Str(ETH_GET_BLOCK_TRANSACTION_COUNT_BY_HASH_METHOD.to_string()),
+        )
+        .await?
+        .map(|v| v.as_u64())
+        .ok_or_else(|| EthJsonRpcError::NotFound("block".to_string()))
+    }
+
+    /// Returns the value of the given storage slot of the address.
+    pub async fn get_storage_at(
+        &self,
+        address: H160,
+        slot: H256,
+        block: BlockNumber,
+    ) -> EthJsonRpcResult<H256> {
+        self.single_request(
+            ETH_GET_STORAGE_AT_METHOD.to_string(),
+            make_params_array!(address, slot, block),
+            Id::Str(ETH_GET_STORAGE_AT_METHOD.to_string()),
+        )
+        .await
+    }
+
+    /// Returns the estimated gas needed to execute the transaction.
+    pub async fn estimate_gas(&self, params: TransactionRequest) -> EthJsonRpcResult<U256> {
+        self.single_request(
+            ETH_ESTIMATE_GAS_METHOD.to_string(),
+            make_params_array!(params),
+            Id::Str(ETH_ESTIMATE_GAS_METHOD.to_string()),
+        )
+        .await
+    }
+
+    /// Returns the gas fees of the `block_count` blocks ending with `newest_block`.
+    ///
+    /// # Arguments
+    /// * `block_count` - The number of blocks in the requested range.
+    /// * `newest_block` - The highest block of the requested range.
+    /// * `reward_percentiles` - The increasing percentiles of the priority fees to return for each block.
+    pub async fn fee_history(
+        &self,
+        block_count: u64,
+        newest_block: BlockNumber,
+        reward_percentiles: &[f64],
+    ) -> EthJsonRpcResult<FeeHistory> {
+        self.single_request(
+            ETH_FEE_HISTORY_METHOD.to_string(),
+            make_params_array!(U64::from(block_count), newest_block, reward_percentiles),
+            Id::Str(ETH_FEE_HISTORY_METHOD.to_string()),
+        )
+        .await
+    }
+
+    /// Returns the suggested priority fee per gas.
+    pub async fn max_priority_fee_per_gas(&self) -> EthJsonRpcResult<U256> {
+        self.single_request(
+            ETH_MAX_PRIORITY_FEE_PER_GAS_METHOD.to_string(),
+            make_params_array!(),
+            Id::Str(ETH_MAX_PRIORITY_FEE_PER_GAS_METHOD.to_string()),
+        )
+        .await
+    }
+
+    /// Returns the network id
+    pub async fn net_version(&self) -> EthJsonRpcResult<String> {
+        self.single_request(
+            NET_VERSION_METHOD.to_string(),
+            make_params_array!(),
+            Id::Str(NET_VERSION_METHOD.to_string()),
+        )
+        .await
+    }
+
+    /// Returns the name and version of the node software
+    pub async fn web3_client_version(&self) -> EthJsonRpcResult<String> {
+        self.single_request(
+            WEB3_CLIENT_VERSION_METHOD.to_string(),
+            make_params_array!(),
+            Id::Str(WEB3_CLIENT_VERSION_METHOD.to_string()),
+        )
+        .await
+    }
+
+    /// Returns the trace of the transaction with the given hash.
+    ///
+    /// The result type depends on the tracer set in the options: [`CallFrame`] for the
+    /// call tracer and [`StructLoggerResult`] for the struct logger.
+    ///
+    /// [`StructLoggerResult`]: did::trace::StructLoggerResult
+    pub async fn debug_trace_transaction<R: DeserializeOwned>(
+        &self,
+        hash: H256,
+        options: TraceOptions,
+    ) -> EthJsonRpcResult<R> {
+        self.single_request(
+            DEBUG_TRACE_TRANSACTION_METHOD.to_string(),
+            make_params_array!(hash, options),
+            Id::Str(DEBUG_TRACE_TRANSACTION_METHOD.to_string()),
+        )
+        .await
+    }
+
+    /// Returns the traces of the transactions of the block with the given number.
+    ///
+    /// The result type depends on the tracer set in the options, as for
+    /// [`Self::debug_trace_transaction`].
+    pub async fn debug_trace_block_by_number<R: DeserializeOwned>(
+        &self,
+        block: BlockNumber,
+        options: TraceOptions,
+    ) -> EthJsonRpcResult<Vec<BlockTraceResult<R>>> {
+        self.single_request(
+            DEBUG_TRACE_BLOCK_BY_NUMBER_METHOD.to_string(),
+            make_params_array!(block, options),
+            Id::Str(DEBUG_TRACE_BLOCK_BY_NUMBER_METHOD.to_string()),
+        )
+        .await
+    }
+
+    /// Returns the tree of the calls performed by the transaction with the given hash.
+    pub async fn trace_transaction_calls(&self, hash: H256) -> EthJsonRpcResult<CallFrame> {
+        self.debug_trace_transaction(hash, TraceOptions::call_tracer())
+            .await
+    }
+
+    /// Returns the trees of the calls performed by the transactions of the block with the given number.
+    pub async fn trace_block_calls(
+        &self,
+        block: BlockNumber,
+    ) -> EthJsonRpcResult<Vec<BlockTraceResult<CallFrame>>> {
+        self.debug_trace_block_by_number(block, TraceOptions::call_tracer())
+            .await
+    }
+
     /// Returns the transaction execution result by hash
     pub async fn get_tx_execution_result_by_hash(
         &self,
         hash: H256,
-    ) -> anyhow::Result<StorableExecutionResult> {
+    ) -> EthJsonRpcResult<StorableExecutionResult> {
         let transaction = self
             .single_request::<Option<StorableExecutionResult>>(
                 IC_GET_TX_EXECUTION_RESULT_BY_HASH_METHOD.to_string(),
@@ -261,7 +591,7 @@ impl<C: Client> EthJsonRpcClient<C> {
                 Id::Str(hash.to_string()),
             )
             .await?
-            .ok_or_else(|| anyhow::anyhow!("Transaction not found"))?;
+            .ok_or_else(|| EthJsonRpcError::NotFound("transaction".to_string()))?;
 
         Ok(transaction)
     }
@@ -271,14 +601,14 @@ impl<C: Client> EthJsonRpcClient<C> {
         &self,
         hashes: impl IntoIterator<Item = H256>,
         max_batch_size: usize,
-    ) -> anyhow::Result<Vec<StorableExecutionResult>> {
+    ) -> EthJsonRpcResult<Vec<StorableExecutionResult>> {
         let params = hashes
             .into_iter()
             .enumerate()
-            .map(|(index, hash)| -> anyhow::Result<(Params, Id)> {
+            .map(|(index, hash)| -> EthJsonRpcResult<(Params, Id)> {
                 Ok((make_params_array!(hash), Id::Num(index as _)))
             })
-            .collect::<anyhow::Result<Vec<_>>>()?;
+            .collect::<EthJsonRpcResult<Vec<_>>>()?;
 
         Ok(self
             .batch_request::<Option<StorableExecutionResult>>(
@@ -293,7 +623,7 @@ impl<C: Client> EthJsonRpcClient<C> {
     }
 
     /// Returns the genesis accounts
-    pub async fn get_genesis_balances(&self) -> anyhow::Result<Vec<(H160, U256)>> {
+    pub async fn get_genesis_balances(&self) -> EthJsonRpcResult<Vec<(H160, U256)>> {
         self.single_request(
             IC_GET_GENESIS_BALANCES.to_string(),
             make_params_array!(),
@@ -303,7 +633,7 @@ impl<C: Client> EthJsonRpcClient<C> {
     }
 
     /// Returns the last certified block
-    pub async fn get_last_certified_block(&self) -> anyhow::Result<CertifiedResult<Block<H256>>> {
+    pub async fn get_last_certified_block(&self) -> EthJsonRpcResult<CertifiedResult<Block<H256>>> {
         self.single_request(
             IC_GET_LAST_CERTIFIED_BLOCK.to_string(),
             make_params_array!(),
@@ -313,7 +643,7 @@ impl<C: Client> EthJsonRpcClient<C> {
     }
 
     /// Performs a request.
-    pub async fn request(&self, request: Request) -> anyhow::Result<Response> {
+    pub async fn request(&self, request: Request) -> EthJsonRpcResult<Response> {
         self.client.send_rpc_request(request).await
     }
 
@@ -323,7 +653,7 @@ impl<C: Client> EthJsonRpcClient<C> {
         method: String,
         params: Params,
         id: Id,
-    ) -> anyhow::Result<R> {
+    ) -> EthJsonRpcResult<R> {
         let request = Request::Single(Call::MethodCall(MethodCall {
             jsonrpc: Some(Version::V2),
             method,
@@ -335,26 +665,44 @@ impl<C: Client> EthJsonRpcClient<C> {
 
         match response {
             Response::Single(response) => match response {
-                Output::Success(result) => {
-                    serde_json::from_value(result.result).context("failed to deserialize value")
-                }
-                Output::Failure(err) => Err(anyhow::format_err!("{err:?}")),
+                Output::Success(result) => serde_json::from_value(result.result)
+                    .map_err(|e| EthJsonRpcError::Deserialization(e.to_string())),
+                Output::Failure(failure) => Err(failure.error.into()),
             },
-            Response::Batch(_) => Err(anyhow::format_err!("unexpected response type: batch")),
+            Response::Batch(_) => Err(EthJsonRpcError::UnexpectedResponse(
+                "unexpected response type: batch".to_string(),
+            )),
         }
     }
 
     /// Performs a batch request.
+    ///
+    /// Fails if any of the calls fails. The results are returned in the order of `params`,
+    /// whatever the order of the responses.
     pub async fn batch_request<R: DeserializeOwned>(
         &self,
         method: String,
         params: impl IntoIterator<Item = (Params, Id)>,
         max_batch_size: usize,
-    ) -> anyhow::Result<Vec<R>> {
-        let mut results = Vec::new();
-
-        let value_from_json = |value| serde_json::from_value::<R>(value);
+    ) -> EthJsonRpcResult<Vec<R>> {
+        self.batch_request_results(method, params, max_batch_size)
+            .await?
+            .into_iter()
+            .collect()
+    }
 
+    /// Performs a batch request, returning the result of every call.
+    ///
+    /// Fails only if a batch cannot be sent. The results are returned in the order
+    /// of `params`, matching the responses by their id.
+    ///
+    /// Up to [`Self::max_concurrent_batches`] batches are sent in parallel.
+    pub async fn batch_request_results<R: DeserializeOwned>(
+        &self,
+        method: String,
+        params: impl IntoIterator<Item = (Params, Id)>,
+        max_batch_size: usize,
+    ) -> EthJsonRpcResult<Vec<EthJsonRpcResult<R>>> {
         // Collect chunks before iteration, otherwise the future won't be `Send`
         let chunks = params
             .into_iter()
@@ -362,83 +710,136 @@ impl<C: Client> EthJsonRpcClient<C> {
             .into_iter()
             .map(Iterator::collect::<Vec<_>>)
             .collect::<Vec<_>>();
-        for chunk in chunks {
-            let method_calls = chunk
-                .into_iter()
-                .map(|(params, id)| {
-                    Call::MethodCall(MethodCall {
-                        jsonrpc: Some(Version::V2),
-                        method: method.clone(),
-                        params,
-                        id,
-                    })
+
+        let results = futures::stream::iter(chunks)
+            .map(|chunk| self.batch_chunk_request(&method, chunk))
+            .buffered(self.max_concurrent_batches)
+            .try_collect::<Vec<_>>()
+            .await?;
+
+        Ok(results.into_iter().flatten().collect())
+    }
+
+    /// Sends a single batch, returning the result of every call in the order of `chunk`.
+    async fn batch_chunk_request<R: DeserializeOwned>(
+        &self,
+        method: &str,
+        chunk: Vec<(Params, Id)>,
+    ) -> EthJsonRpcResult<Vec<EthJsonRpcResult<R>>> {
+        let ids = chunk.iter().map(|(_, id)| id.clone()).collect::<Vec<_>>();
+        let method_calls = chunk
+            .into_iter()
+            .map(|(params, id)| {
+                Call::MethodCall(MethodCall {
+                    jsonrpc: Some(Version::V2),
+                    method: method.to_string(),
+                    params,
+                    id,
                 })
-                .collect::<Vec<_>>();
-            let chunk_size = method_calls.len();
-            let request = Request::Batch(method_calls);
+            })
+            .collect::<Vec<_>>();
+        let request = Request::Batch(method_calls);
 
-            let response = self.client.send_rpc_request(request).await?;
+        let response = self.client.send_rpc_request(request).await?;
 
-            match response {
-                Response::Single(response) => match response {
-                    Output::Success(result) => {
-                        if chunk_size == 1 {
-                            results.push(value_from_json(result.result)?);
-                        } else {
-                            anyhow::bail!(
-                                "unexpected number of results: have: 1, expected {chunk_size}"
-                            );
-                        }
-                    }
-                    Output::Failure(err) => {
-                        anyhow::bail!("{err:?}");
-                    }
-                },
-                Response::Batch(response) => {
-                    if chunk_size == response.len() {
-                        for resp in response.into_iter() {
-                            match resp {
-                                Output::Success(resp) => {
-                                    results.push(value_from_json(resp.result)?)
-                                }
-                                Output::Failure(err) => {
-                                    anyhow::bail!("{err:?}");
-                                }
-                            }
-                        }
-                    } else {
-                        anyhow::bail!(
-                            "unexpected number of results: have: {}, expected {}",
-                            response.len(),
-                            chunk_size
-                        );
-                    }
-                }
-            }
+        Ok(batch_results(&ids, response)?
+            .into_iter()
+            .map(|result| {
+                result.and_then(|value| {
+                    serde_json::from_value(value)
+                        .map_err(|e| EthJsonRpcError::Deserialization(e.to_string()))
+                })
+            })
+            .collect())
+    }
+}
+
+/// Sorts the items of a batch response by id, so that responses of
+/// different endpoints can be compared.
+#[cfg(any(feature = "multi-endpoint", feature = "http-outcall"))]
+pub(crate) fn normalize_response(response: Response) -> Response {
+    match response {
+        Response::Batch(mut outputs) => {
+            outputs.sort_by(|a, b| id_sort_key(a.id()).cmp(&id_sort_key(b.id())));
+            Response::Batch(outputs)
         }
+        single => single,
+    }
+}
 
-        Ok(results)
+/// Returns a key ordering the ids by kind, then numerically or lexicographically.
+#[cfg(any(feature = "multi-endpoint", feature = "http-outcall"))]
+fn id_sort_key(id: &Id) -> (u8, u64, &str) {
+    match id {
+        Id::Null => (0, 0, ""),
+        Id::Num(num) => (1, *num, ""),
+        Id::Str(str) => (2, 0, str),
     }
 }
 
-/// Parameters to `eth_getLogs`.
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct EthGetLogsParams {
-    /// Addresses of contracts to filter logs for.
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub address: Option<Vec<H160>>,
+/// Returns the results of a batch response in the order of the calls with the given ids.
+///
+/// Fails if the whole batch has been rejected or if the number of results does not
+/// match the number of calls.
+fn batch_results(
+    ids: &[Id],
+    response: Response,
+) -> EthJsonRpcResult<Vec<EthJsonRpcResult<serde_json::Value>>> {
+    let outputs = match response {
+        Response::Single(Output::Failure(failure)) if ids.len() > 1 => {
+            return Err(failure.error.into());
+        }
+        Response::Single(output) => vec![output],
+        Response::Batch(outputs) => outputs,
+    };
+    if outputs.len() != ids.len() {
+        return Err(EthJsonRpcError::UnexpectedResponse(format!(
+            "unexpected number of results: have: {}, expected {}",
+            outputs.len(),
+            ids.len()
+        )));
+    }
+
+    Ok(match_outputs_by_id(ids, outputs)
+        .into_iter()
+        .map(|output| match output {
+            Some(Output::Success(success)) => Ok(success.result),
+            Some(Output::Failure(failure)) => Err(failure.error.into()),
+            None => Err(EthJsonRpcError::UnexpectedResponse(
+                "missing response for batch call".to_string(),
+            )),
+        })
+        .collect())
+}
 
-    /// Start search logs from this block number.
-    #[serde(rename = "fromBlock")]
-    pub from_block: BlockNumber,
+/// Orders the outputs of a batch as the calls with the given ids.
+///
+/// Calls sharing the same id are matched with the responses in order.
+/// A single output of a one-call batch is matched whatever its id, as some
+/// servers answer single-item batches with a null id.
+fn match_outputs_by_id(ids: &[Id], outputs: Vec<Output>) -> Vec<Option<Output>> {
+    if let ([_], [_]) = (ids, outputs.as_slice()) {
+        return outputs.into_iter().map(Some).collect();
+    }
 
-    /// Finish search logs on this block number.
-    #[serde(rename = "toBlock")]
-    pub to_block: BlockNumber,
+    let mut positions = HashMap::<&Id, VecDeque<usize>>::new();
+    for (position, id) in ids.iter().enumerate() {
+        positions.entry(id).or_default().push_back(position);
+    }
 
-    /// Filter logs by topics.
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub topics: Option<Vec<Vec<H256>>>,
+    let mut matched = Vec::with_capacity(ids.len());
+    matched.resize_with(ids.len(), || None);
+    for output in outputs {
+        let id = match &output {
+            Output::Success(success) => &success.id,
+            Output::Failure(failure) => &failure.id,
+        };
+        if let Some(position) = positions.get_mut(id).and_then(VecDeque::pop_front) {
+            matched[position] = Some(output);
+        }
+    }
+
+    matched
 }
 
 pub trait Client: Clone + Send + Sync {
@@ -447,37 +848,229 @@ pub trait Client: Clone + Send + Sync {
     fn send_rpc_request(
         &self,
         request: Request,
-    ) -> Pin<Box<dyn Future<Output = anyhow::Result<Response>> + Send>>;
+    ) -> Pin<Box<dyn Future<Output = EthJsonRpcResult<Response>> + Send>>;
 }
 
 #[cfg(test)]
 mod tests {
+    use jsonrpc_core::{Failure, Success};
+
     use super::*;
 
+    /// Answers batches in reverse order, echoing the first parameter.
+    /// Calls with a `null` parameter fail.
+    #[derive(Clone)]
+    struct ReversingClient;
+
+    impl Client for ReversingClient {
+        fn send_rpc_request(
+            &self,
+            request: Request,
+        ) -> Pin<Box<dyn Future<Output = EthJsonRpcResult<Response>> + Send>> {
+            let Request::Batch(calls) = request else {
+                panic!("expected batch request");
+            };
+
+            let outputs = calls
+                .into_iter()
+                .rev()
+                .map(|call| {
+                    let Call::MethodCall(call) = call else {
+                        panic!("expected method call");
+                    };
+                    let Params::Array(mut params) = call.params else {
+                        panic!("expected params array");
+                    };
+                    match params.remove(0) {
+                        serde_json::Value::Null => Output::Failure(Failure {
+                            jsonrpc: Some(Version::V2),
+                            error: jsonrpc_core::Error::internal_error(),
+                            id: call.id,
+                        }),
+                        param => Output::Success(Success {
+                            jsonrpc: Some(Version::V2),
+                            result: param,
+                            id: call.id,
+                        }),
+                    }
+                })
+                .collect();
+
+            Box::pin(async move { Ok(Response::Batch(outputs)) })
+        }
+    }
+
+    fn batch_params(values: &[serde_json::Value]) -> Vec<(Params, Id)> {
+        values
+            .iter()
+            .enumerate()
+            .map(|(index, value)| (Params::Array(vec![value.clone()]), Id::Num(index as _)))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn should_match_batch_responses_by_id() {
+        let client = EthJsonRpcClient::new(ReversingClient);
+        let values = (0..7u64).map(serde_json::Value::from).collect::<Vec<_>>();
+
+        let results = client
+            .batch_request::<u64>("echo".to_string(), batch_params(&values), 3)
+            .await
+            .unwrap();
+
+        assert_eq!(results, (0..7).collect::<Vec<_>>());
+    }
+
+    #[tokio::test]
+    async fn should_return_batch_results_per_item() {
+        let client = EthJsonRpcClient::new(ReversingClient);
+        let values = vec![1u64.into(), serde_json::Value::Null, 3u64.into()];
+
+        let results = client
+            .batch_request_results::<u64>("echo".to_string(), batch_params(&values), 10)
+            .await
+            .unwrap();
+
+        assert_eq!(results[0], Ok(1));
+        assert!(matches!(results[1], Err(EthJsonRpcError::JsonRpc { .. })));
+        assert_eq!(results[2], Ok(3));
+
+        let result = client
+            .batch_request::<u64>("echo".to_string(), batch_params(&values), 10)
+            .await;
+        assert!(matches!(result, Err(EthJsonRpcError::JsonRpc { .. })));
+    }
+
+    #[tokio::test]
+    async fn should_send_batches_concurrently_in_order() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+        use std::time::Duration;
+
+        /// Delays the first batches the most, so that they complete last.
+        #[derive(Clone, Default)]
+        struct SlowClient {
+            in_flight: Arc<AtomicUsize>,
+            max_in_flight: Arc<AtomicUsize>,
+        }
+
+        impl Client for SlowClient {
+            fn send_rpc_request(
+                &self,
+                request: Request,
+            ) -> Pin<Box<dyn Future<Output = EthJsonRpcResult<Response>> + Send>> {
+                let client = self.clone();
+                Box::pin(async move {
+                    let in_flight = client.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                    client.max_in_flight.fetch_max(in_flight, Ordering::SeqCst);
+
+                    let Request::Batch(calls) = &request else {
+                        panic!("expected batch request");
+                    };
+                    let Some(Call::MethodCall(call)) = calls.first() else {
+                        panic!("expected method call");
+                    };
+                    let Id::Num(first) = call.id else {
+                        panic!("expected numeric id");
+                    };
+                    tokio::time::sleep(Duration::from_millis(50 - first * 2)).await;
+
+                    client.in_flight.fetch_sub(1, Ordering::SeqCst);
+                    ReversingClient.send_rpc_request(request).await
+                })
+            }
+        }
+
+        let client = SlowClient::default();
+        let eth_client = EthJsonRpcClient::new(client.clone()).with_max_concurrent_batches(3);
+        let values = (0..20u64).map(serde_json::Value::from).collect::<Vec<_>>();
+
+        let results = eth_client
+            .batch_request::<u64>("echo".to_string(), batch_params(&values), 2)
+            .await
+            .unwrap();
+
+        assert_eq!(results, (0..20).collect::<Vec<_>>());
+        assert_eq!(client.max_in_flight.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn should_not_fail_receipts_batch_on_missing_receipt() {
+        #[derive(Clone)]
+        struct ReceiptsClient;
+
+        impl Client for ReceiptsClient {
+            fn send_rpc_request(
+                &self,
+                request: Request,
+            ) -> Pin<Box<dyn Future<Output = EthJsonRpcResult<Response>> + Send>> {
+                let Request::Batch(calls) = request else {
+                    panic!("expected batch request");
+                };
+                let outputs = calls
+                    .into_iter()
+                    .enumerate()
+                    .map(|(index, call)| {
+                        let Call::MethodCall(call) = call else {
+                            panic!("expected method call");
+                        };
+                        let result = if index == 1 {
+                            serde_json::Value::Null
+                        } else {
+                            serde_json::to_value(TransactionReceipt::default()).unwrap()
+                        };
+                        Output::Success(Success {
+                            jsonrpc: Some(Version::V2),
+                            result,
+                            id: call.id,
+                        })
+                    })
+                    .collect();
+
+                Box::pin(async move { Ok(Response::Batch(outputs)) })
+            }
+        }
+
+        let client = EthJsonRpcClient::new(ReceiptsClient);
+        let hashes = (1..=3).map(H256::from_low_u64_be);
+
+        let receipts = client.get_receipts_by_hash(hashes, 10).await.unwrap();
+
+        assert_eq!(receipts.len(), 3);
+        assert!(receipts[0].is_ok());
+        assert_eq!(
+            receipts[1],
+            Err(EthJsonRpcError::NotFound("transaction receipt".to_string()))
+        );
+        assert!(receipts[2].is_ok());
+    }
+
     #[test]
     fn test_eth_get_logs_params_serialization() {
         let get_logs_params = EthGetLogsParams {
             address: Some(vec!["0xb59f67a8bff5d8cd03f6ac17265c550ed8f33907"
                 .parse()
                 .unwrap()]),
-            from_block: BlockNumber::Number(42u64.into()),
-            to_block: BlockNumber::Latest,
+            block_filter: LogsBlockFilter::Range {
+                from_block: Some(BlockNumber::Number(42u64.into())),
+                to_block: Some(BlockNumber::Latest),
+            },
             topics: Some(vec![
-                vec![
+                Some(vec![
                     "0xddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef"
                         .parse()
                         .unwrap(),
-                ],
-                vec![
+                ]),
+                Some(vec![
                     "0x00000000000000000000000000b46c2526e227482e2ebb8f4c69e4674d262e75"
                         .parse()
                         .unwrap(),
-                ],
-                vec![
+                ]),
+                Some(vec![
                     "0x00000000000000000000000054a2d42a40f51259dedd1978f6c118a0f0eff078"
                         .parse()
                         .unwrap(),
-                ],
+                ]),
             ]),
         };
 
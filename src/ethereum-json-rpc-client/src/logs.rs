@@ -0,0 +1,886 @@
+use did::logs::{BlockFilter, LogAddressFilter, LogFilter, LogTopicFilter};
+use ethers_core::types::{BlockNumber, Log, H160, H256};
+use ethers_core::utils::keccak256;
+use futures::stream::BoxStream;
+use futures::{StreamExt, TryStreamExt};
+use serde::de::Error as _;
+use serde::{Deserialize, Deserializer, Serialize};
+
+use crate::{Client, EthJsonRpcClient, EthJsonRpcError, EthJsonRpcResult};
+
+/// JSON-RPC error code returned by providers when a query exceeds their limits.
+const LIMIT_EXCEEDED_ERROR_CODE: i64 = -32005;
+
+/// HTTP status code returned when the response is too large.
+const PAYLOAD_TOO_LARGE_STATUS: u16 = 413;
+
+/// Fragments of the error messages returned by providers when the block range
+/// or the result set of `eth_getLogs` is too large, for the providers which do not
+/// use [`LIMIT_EXCEEDED_ERROR_CODE`].
+const RANGE_TOO_LARGE_MESSAGES: [&str; 5] = [
+    // Infura
+    "query returned more than",
+    // Alchemy
+    "log response size exceeded",
+    // QuickNode
+    "eth_getlogs is limited to",
+    // Ankr
+    "block range is too wide",
+    // BNB Smart Chain nodes
+    "exceed maximum block range",
+];
+
+/// Maximum number of topics of a log.
+const MAX_TOPICS: usize = 4;
+
+/// Parameters to `eth_getLogs`.
+///
+/// Can be built with the builder methods:
+/// ```
+/// # use ethereum_json_rpc_client::EthGetLogsParams;
+/// # use ethers_core::types::{H160, H256};
+/// let params = EthGetLogsParams::new()
+///     .address(H160::repeat_byte(1))
+///     .from_block(100)
+///     .event("Transfer(address,address,uint256)")
+///     .topic2(H256::repeat_byte(2));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EthGetLogsParams {
+    /// Addresses of contracts to filter logs for.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub address: Option<Vec<H160>>,
+
+    /// The blocks to search logs in.
+    #[serde(flatten)]
+    pub block_filter: LogsBlockFilter,
+
+    /// Filter logs by topics. Every position matches any of its topics,
+    /// and a `None` position matches any topic.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub topics: Option<Vec<Option<Vec<H256>>>>,
+}
+
+/// The blocks of an `eth_getLogs` query.
+///
+/// Deserializing `blockHash` together with `fromBlock` or `toBlock` fails.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(untagged)]
+pub enum LogsBlockFilter {
+    /// The block with the given hash.
+    #[serde(rename_all = "camelCase")]
+    Hash { block_hash: H256 },
+    /// A range of blocks. Missing bounds default to the latest block.
+    #[serde(rename_all = "camelCase")]
+    Range {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        from_block: Option<BlockNumber>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        to_block: Option<BlockNumber>,
+    },
+}
+
+impl<'de> Deserialize<'de> for LogsBlockFilter {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct RawBlockFilter {
+            block_hash: Option<H256>,
+            from_block: Option<BlockNumber>,
+            to_block: Option<BlockNumber>,
+        }
+
+        let raw = RawBlockFilter::deserialize(deserializer)?;
+        match raw.block_hash {
+            // According to the specification `fromBlock` and `toBlock` cannot be used with `blockHash`
+            Some(_) if raw.from_block.is_some() || raw.to_block.is_some() => Err(D::Error::custom(
+                "'blockHash' property cannot be used with 'fromBlock' or 'toBlock'",
+            )),
+            Some(block_hash) => Ok(Self::Hash { block_hash }),
+            None => Ok(Self::Range {
+                from_block: raw.from_block,
+                to_block: raw.to_block,
+            }),
+        }
+    }
+}
+
+impl Default for LogsBlockFilter {
+    fn default() -> Self {
+        Self::Range {
+            from_block: None,
+            to_block: None,
+        }
+    }
+}
+
+impl EthGetLogsParams {
+    /// Creates parameters matching all the logs of the latest block.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a contract address to filter logs for.
+    pub fn address(mut self, address: H160) -> Self {
+        self.address.get_or_insert_with(Vec::new).push(address);
+        self
+    }
+
+    /// Adds contract addresses to filter logs for.
+    pub fn addresses(mut self, addresses: impl IntoIterator<Item = H160>) -> Self {
+        self.address.get_or_insert_with(Vec::new).extend(addresses);
+        self
+    }
+
+    /// Sets the first block of the range, replacing the block hash if any.
+    pub fn from_block(mut self, block: impl Into<BlockNumber>) -> Self {
+        let to_block = self.range_to_block();
+        self.block_filter = LogsBlockFilter::Range {
+            from_block: Some(block.into()),
+            to_block,
+        };
+        self
+    }
+
+    /// Sets the last block of the range, replacing the block hash if any.
+    pub fn to_block(mut self, block: impl Into<BlockNumber>) -> Self {
+        let from_block = self.range_from_block();
+        self.block_filter = LogsBlockFilter::Range {
+            from_block,
+            to_block: Some(block.into()),
+        };
+        self
+    }
+
+    /// Searches the logs of the block with the given hash, replacing the block range.
+    pub fn block_hash(mut self, block_hash: H256) -> Self {
+        self.block_filter = LogsBlockFilter::Hash { block_hash };
+        self
+    }
+
+    /// Filters the logs of the event with the given signature,
+    /// e.g. `Transfer(address,address,uint256)`.
+    pub fn event(self, signature: &str) -> Self {
+        self.topic0(H256::from(keccak256(signature.as_bytes())))
+    }
+
+    /// Filters the logs whose first topic, the event signature hash, is `topic`.
+    pub fn topic0(self, topic: H256) -> Self {
+        self.topic(0, [topic])
+    }
+
+    /// Filters the logs whose second topic is `topic`.
+    pub fn topic1(self, topic: H256) -> Self {
+        self.topic(1, [topic])
+    }
+
+    /// Filters the logs whose third topic is `topic`.
+    pub fn topic2(self, topic: H256) -> Self {
+        self.topic(2, [topic])
+    }
+
+    /// Filters the logs whose fourth topic is `topic`.
+    pub fn topic3(self, topic: H256) -> Self {
+        self.topic(3, [topic])
+    }
+
+    /// Filters the logs whose topic at `position` is any of `topics`.
+    /// The previous positions without a filter match any topic.
+    fn topic(mut self, position: usize, topics: impl IntoIterator<Item = H256>) -> Self {
+        debug_assert!(
+            position < MAX_TOPICS,
+            "a log has at most {MAX_TOPICS} topics"
+        );
+        let filter = self.topics.get_or_insert_with(Vec::new);
+        if filter.len() <= position {
+            filter.resize(position + 1, None);
+        }
+        filter[position] = Some(topics.into_iter().collect());
+        self
+    }
+
+    fn range_from_block(&self) -> Option<BlockNumber> {
+        match self.block_filter {
+            LogsBlockFilter::Range { from_block, .. } => from_block,
+            LogsBlockFilter::Hash { .. } => None,
+        }
+    }
+
+    fn range_to_block(&self) -> Option<BlockNumber> {
+        match self.block_filter {
+            LogsBlockFilter::Range { to_block, .. } => to_block,
+            LogsBlockFilter::Hash { .. } => None,
+        }
+    }
+}
+
+/// Ranges are converted to [`BlockFilter::Bounded`], even without bounds,
+/// so that converting the filter back gives the same parameters.
+impl From<EthGetLogsParams> for LogFilter {
+    fn from(params: EthGetLogsParams) -> Self {
+        let block_filter = match params.block_filter {
+            LogsBlockFilter::Hash { block_hash } => Some(BlockFilter::Exact {
+                block_hash: block_hash.into(),
+            }),
+            LogsBlockFilter::Range {
+                from_block,
+                to_block,
+            } => Some(BlockFilter::Bounded {
+                from_block: from_block.map(Into::into),
+                to_block: to_block.map(Into::into),
+            }),
+        };
+
+        Self {
+            block_filter,
+            address: params
+                .address
+                .map(|address| LogAddressFilter(address.into_iter().map(Into::into).collect())),
+            topics: params.topics.map(|topics| {
+                topics
+                    .into_iter()
+                    .map(|topic| {
+                        topic.map(|topic| {
+                            LogTopicFilter(topic.into_iter().map(Into::into).collect())
+                        })
+                    })
+                    .collect()
+            }),
+        }
+    }
+}
+
+/// A filter without blocks is converted to a range without bounds, which also
+/// matches the logs of the latest block.
+impl From<LogFilter> for EthGetLogsParams {
+    fn from(filter: LogFilter) -> Self {
+        let block_filter = match filter.block_filter {
+            Some(BlockFilter::Exact { block_hash }) => LogsBlockFilter::Hash {
+                block_hash: block_hash.into(),
+            },
+            Some(BlockFilter::Bounded {
+                from_block,
+                to_block,
+            }) => LogsBlockFilter::Range {
+                from_block: from_block.map(Into::into),
+                to_block: to_block.map(Into::into),
+            },
+            None => LogsBlockFilter::default(),
+        };
+
+        Self {
+            address: filter
+                .address
+                .map(|address| address.0.into_iter().map(Into::into).collect()),
+            block_filter,
+            topics: filter.topics.map(|topics| {
+                topics
+                    .into_iter()
+                    .map(|topic| topic.map(|topic| topic.0.into_iter().map(Into::into).collect()))
+                    .collect()
+            }),
+        }
+    }
+}
+
+/// Configuration of the paginated `eth_getLogs` queries.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogsPagination {
+    /// Number of blocks of the first query.
+    pub initial_range: u64,
+    /// Upper bound of the number of blocks of a query.
+    pub max_range: u64,
+    /// The range is doubled after a query returning less than half of these logs.
+    pub target_logs: usize,
+}
+
+impl Default for LogsPagination {
+    fn default() -> Self {
+        Self {
+            initial_range: 1_000,
+            max_range: 10_000,
+            target_logs: 1_000,
+        }
+    }
+}
+
+/// Position of a paginated `eth_getLogs` query, which can be stored to resume it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LogsCursor {
+    /// First block of the next query.
+    pub from_block: u64,
+    /// Last block of the whole query.
+    pub to_block: u64,
+    /// Number of blocks of the next query.
+    pub range: u64,
+}
+
+impl LogsCursor {
+    /// Returns whether all the blocks have been queried.
+    pub fn is_done(&self) -> bool {
+        self.from_block > self.to_block
+    }
+}
+
+/// The logs of a range of blocks.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LogsPage {
+    /// The logs of the blocks, in order.
+    pub logs: Vec<Log>,
+    /// First block of the page.
+    pub from_block: u64,
+    /// Last block of the page.
+    pub to_block: u64,
+    /// The cursor to resume the query after this page.
+    pub cursor: LogsCursor,
+}
+
+/// Returns whether the provider rejected the query because of the size of the block
+/// range or of the result set.
+fn is_range_too_large(err: &EthJsonRpcError) -> bool {
+    match err {
+        EthJsonRpcError::JsonRpc { code, message, .. } => {
+            let message = message.to_lowercase();
+            *code == LIMIT_EXCEEDED_ERROR_CODE
+                || RANGE_TOO_LARGE_MESSAGES
+                    .iter()
+                    .any(|fragment| message.contains(fragment))
+        }
+        EthJsonRpcError::HttpStatus { status, .. } => *status == PAYLOAD_TOO_LARGE_STATUS,
+        EthJsonRpcError::ResponseTooLarge { .. } => true,
+        _ => false,
+    }
+}
+
+impl<C: Client + 'static> EthJsonRpcClient<C> {
+    /// Returns the logs matching the filter, splitting the block range in several
+    /// `eth_getLogs` queries.
+    ///
+    /// See [`EthJsonRpcClient::logs_stream`].
+    pub async fn get_logs_paged(
+        &self,
+        params: EthGetLogsParams,
+        pagination: LogsPagination,
+    ) -> EthJsonRpcResult<Vec<Log>> {
+        self.logs_stream(params, pagination)
+            .try_fold(Vec::new(), |mut logs, page| async move {
+                logs.extend(page.logs);
+                Ok(logs)
+            })
+            .await
+    }
+
+    /// Returns a stream of the pages of logs matching the filter.
+    ///
+    /// The block tags of the filter are resolved to block numbers when the stream is
+    /// first polled. The block range is split in queries whose range is halved when the
+    /// provider rejects the query as too large and doubled, up to
+    /// [`LogsPagination::max_range`], when the query returns few logs.
+    /// The stream ends after the first error; the cursor of the last page can be used
+    /// to resume the query with [`EthJsonRpcClient::logs_stream_from`].
+    pub fn logs_stream(
+        &self,
+        params: EthGetLogsParams,
+        pagination: LogsPagination,
+    ) -> BoxStream<'static, EthJsonRpcResult<LogsPage>> {
+        self.paged_logs(params, None, pagination)
+    }
+
+    /// Returns a stream of the pages of logs matching the filter, starting at the cursor.
+    ///
+    /// The block range of the filter is ignored in favor of the one of the cursor.
+    pub fn logs_stream_from(
+        &self,
+        params: EthGetLogsParams,
+        cursor: LogsCursor,
+        pagination: LogsPagination,
+    ) -> BoxStream<'static, EthJsonRpcResult<LogsPage>> {
+        self.paged_logs(params, Some(cursor), pagination)
+    }
+
+    fn paged_logs(
+        &self,
+        params: EthGetLogsParams,
+        cursor: Option<LogsCursor>,
+        pagination: LogsPagination,
+    ) -> BoxStream<'static, EthJsonRpcResult<LogsPage>> {
+        let client = self.clone();
+        futures::stream::try_unfold(cursor, move |cursor| {
+            let client = client.clone();
+            let params = params.clone();
+            let pagination = pagination.clone();
+            async move {
+                let cursor = match cursor {
+                    Some(cursor) => cursor,
+                    None => {
+                        let (from_block, to_block) = client.resolve_block_range(&params).await?;
+                        LogsCursor {
+                            from_block,
+                            to_block,
+                            range: pagination.initial_range.max(1),
+                        }
+                    }
+                };
+                if cursor.is_done() {
+                    return Ok(None);
+                }
+
+                let page = client.next_logs_page(params, cursor, &pagination).await?;
+                let cursor = page.cursor;
+                Ok(Some((page, Some(cursor))))
+            }
+        })
+        .boxed()
+    }
+
+    /// Queries the logs of the next page, halving the range until the provider accepts it.
+    async fn next_logs_page(
+        &self,
+        params: EthGetLogsParams,
+        cursor: LogsCursor,
+        pagination: &LogsPagination,
+    ) -> EthJsonRpcResult<LogsPage> {
+        let mut range = cursor.range.clamp(1, pagination.max_range.max(1));
+        loop {
+            let to_block = cursor
+                .to_block
+                .min(cursor.from_block.saturating_add(range - 1));
+            let query = match params.block_filter {
+                // The range of a block hash is the single block, which is never split
+                LogsBlockFilter::Hash { .. } => params.clone(),
+                LogsBlockFilter::Range { .. } => EthGetLogsParams {
+                    block_filter: LogsBlockFilter::Range {
+                        from_block: Some(BlockNumber::Number(cursor.from_block.into())),
+                        to_block: Some(BlockNumber::Number(to_block.into())),
+                    },
+                    ..params.clone()
+                },
+            };
+
+            match self.get_logs(query).await {
+                Ok(logs) => {
+                    let next_range = if logs.len() < pagination.target_logs / 2 {
+                        range.saturating_mul(2).min(pagination.max_range.max(1))
+                    } else {
+                        range
+                    };
+                    return Ok(LogsPage {
+                        logs,
+                        from_block: cursor.from_block,
+                        to_block,
+                        cursor: LogsCursor {
+                            from_block: to_block + 1,
+                            to_block: cursor.to_block,
+                            range: next_range,
+                        },
+                    });
+                }
+                Err(err) if range > 1 && is_range_too_large(&err) => {
+                    log::debug!(
+                        "get_logs_paged - range of {range} blocks from {} too large: {err}",
+                        cursor.from_block
+                    );
+                    range /= 2;
+                }
+                Err(err) => return Err(err),
+            }
+        }
+    }
+
+    /// Resolves the block range of the filter to block numbers.
+    async fn resolve_block_range(&self, params: &EthGetLogsParams) -> EthJsonRpcResult<(u64, u64)> {
+        match params.block_filter {
+            LogsBlockFilter::Hash { block_hash } => {
+                let number = self
+                    .get_block_by_hash(block_hash)
+                    .await?
+                    .number
+                    .map(|number| number.as_u64())
+                    .ok_or_else(|| EthJsonRpcError::NotFound(format!("block {block_hash:?}")))?;
+                Ok((number, number))
+            }
+            LogsBlockFilter::Range {
+                from_block,
+                to_block,
+            } => Ok((
+                self.resolve_block_number(from_block.unwrap_or(BlockNumber::Latest))
+                    .await?,
+                self.resolve_block_number(to_block.unwrap_or(BlockNumber::Latest))
+                    .await?,
+            )),
+        }
+    }
+
+    async fn resolve_block_number(&self, block: BlockNumber) -> EthJsonRpcResult<u64> {
+        match block {
+            BlockNumber::Number(number) => Ok(number.as_u64()),
+            BlockNumber::Earliest => Ok(0),
+            BlockNumber::Latest => self.get_block_number().await,
+            tag => self
+                .get_block_by_number(tag)
+                .await?
+                .number
+                .map(|number| number.as_u64())
+                .ok_or_else(|| EthJsonRpcError::NotFound(format!("block {tag}"))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use ethers_core::types::U64;
+    use jsonrpc_core::Params;
+
+    use super::*;
+    use crate::mock::MockClient;
+
+    /// The block ranges of the `eth_getLogs` queries received by the node.
+    type Queries = Arc<Mutex<Vec<(u64, u64)>>>;
+
+    fn params(from_block: BlockNumber, to_block: BlockNumber) -> EthGetLogsParams {
+        EthGetLogsParams::new()
+            .address(H160::repeat_byte(1))
+            .from_block(from_block)
+            .to_block(to_block)
+    }
+
+    /// Returns the block range of an `eth_getLogs` query.
+    fn query_range(params: &Params) -> (u64, u64) {
+        let Params::Array(params) = params else {
+            panic!("unexpected params");
+        };
+        let query: EthGetLogsParams = serde_json::from_value(params[0].clone()).unwrap();
+        let LogsBlockFilter::Range {
+            from_block: Some(from_block),
+            to_block: Some(to_block),
+        } = query.block_filter
+        else {
+            panic!("unexpected block filter");
+        };
+        let number = |block: BlockNumber| block.as_number().unwrap().as_u64();
+        (number(from_block), number(to_block))
+    }
+
+    /// A node returning one log per block, rejecting the queries with more than `max_logs` logs.
+    fn node(max_logs: u64) -> (MockClient, Queries) {
+        let mock = MockClient::new();
+        let queries = Queries::default();
+        let recorded = queries.clone();
+        mock.on("eth_blockNumber", U64::from(99))
+            .on_call("eth_getLogs", move |params| {
+                let (from, to) = query_range(params);
+                recorded.lock().unwrap().push((from, to));
+                if to - from + 1 > max_logs {
+                    return Err(EthJsonRpcError::JsonRpc {
+                        code: LIMIT_EXCEEDED_ERROR_CODE,
+                        message: format!("query returned more than {max_logs} results"),
+                        data: None,
+                    });
+                }
+                Ok((from..=to)
+                    .map(|block| Log {
+                        block_number: Some(block.into()),
+                        ..Default::default()
+                    })
+                    .collect::<Vec<_>>())
+            });
+        (mock, queries)
+    }
+
+    fn block_numbers(logs: &[Log]) -> Vec<u64> {
+        logs.iter()
+            .map(|log| log.block_number.unwrap().as_u64())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn should_split_range_adaptively() {
+        let (mock, queries) = node(10);
+        let client = EthJsonRpcClient::new(mock);
+        let pagination = LogsPagination {
+            initial_range: 40,
+            max_range: 40,
+            target_logs: 40,
+        };
+
+        let logs = client
+            .get_logs_paged(
+                params(BlockNumber::Number(0.into()), BlockNumber::Latest),
+                pagination,
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(block_numbers(&logs), (0..=99).collect::<Vec<_>>());
+        let queries = queries.lock().unwrap().clone();
+        // 40 and 20 blocks are rejected, then 10 blocks return few logs and the range grows
+        assert_eq!(
+            &queries[..5],
+            &[(0, 39), (0, 19), (0, 9), (10, 29), (10, 19)]
+        );
+    }
+
+    #[tokio::test]
+    async fn should_resume_from_cursor() {
+        let (mock, _) = node(100);
+        let client = EthJsonRpcClient::new(mock.clone());
+        let pagination = LogsPagination {
+            initial_range: 10,
+            max_range: 10,
+            target_logs: 0,
+        };
+        let filter = params(BlockNumber::Earliest, BlockNumber::Number(29.into()));
+
+        let first = client
+            .logs_stream(filter.clone(), pagination.clone())
+            .next()
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!((first.from_block, first.to_block), (0, 9));
+
+        let cursor: LogsCursor =
+            serde_json::from_str(&serde_json::to_string(&first.cursor).unwrap()).unwrap();
+        let pages: Vec<_> = client
+            .logs_stream_from(filter, cursor, pagination)
+            .try_collect()
+            .await
+            .unwrap();
+
+        let ranges: Vec<_> = pages
+            .iter()
+            .map(|page| (page.from_block, page.to_block))
+            .collect();
+        assert_eq!(ranges, vec![(10, 19), (20, 29)]);
+        assert!(pages[1].cursor.is_done());
+        mock.assert_not_called("eth_blockNumber");
+    }
+
+    #[tokio::test]
+    async fn should_fail_when_single_block_is_too_large() {
+        let (mock, _) = node(0);
+        let client = EthJsonRpcClient::new(mock);
+
+        let result = client
+            .get_logs_paged(
+                params(BlockNumber::Number(5.into()), BlockNumber::Number(8.into())),
+                LogsPagination::default(),
+            )
+            .await;
+
+        assert!(matches!(
+            result,
+            Err(EthJsonRpcError::JsonRpc {
+                code: LIMIT_EXCEEDED_ERROR_CODE,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn should_detect_range_too_large_errors() {
+        let json_rpc_error = |code: i64, message: &str| EthJsonRpcError::JsonRpc {
+            code,
+            message: message.to_string(),
+            data: None,
+        };
+
+        assert!(is_range_too_large(&json_rpc_error(
+            LIMIT_EXCEEDED_ERROR_CODE,
+            "request limit reached"
+        )));
+        for message in [
+            "Log response size exceeded. You can make eth_getLogs requests with up to a 2K block range",
+            "eth_getLogs is limited to a 10,000 range",
+            "exceed maximum block range: 5000",
+        ] {
+            assert!(is_range_too_large(&json_rpc_error(-32602, message)), "{message}");
+        }
+        for message in [
+            "execution reverted: transfer amount exceeds balance",
+            "gas limit exceeded",
+            "invalid block range params",
+            "too many arguments, want at most 1",
+        ] {
+            assert!(
+                !is_range_too_large(&json_rpc_error(-32602, message)),
+                "{message}"
+            );
+        }
+    }
+
+    #[test]
+    fn should_serialize_null_topic_wildcards() {
+        let params = EthGetLogsParams::new()
+            .from_block(1)
+            .topic2(H256::repeat_byte(2));
+
+        assert_eq!(
+            serde_json::to_value(&params).unwrap(),
+            serde_json::json!({
+                "fromBlock": "0x1",
+                "topics": [null, null, [H256::repeat_byte(2)]],
+            })
+        );
+    }
+
+    #[test]
+    fn should_serialize_block_hash() {
+        let params = EthGetLogsParams::new()
+            .from_block(1)
+            .block_hash(H256::repeat_byte(3))
+            .address(H160::repeat_byte(1));
+
+        let json = serde_json::to_value(&params).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "address": [H160::repeat_byte(1)],
+                "blockHash": H256::repeat_byte(3),
+            })
+        );
+        assert_eq!(
+            serde_json::from_value::<EthGetLogsParams>(json).unwrap(),
+            params
+        );
+    }
+
+    #[test]
+    fn should_reject_block_hash_with_block_range() {
+        for json in [
+            serde_json::json!({"blockHash": H256::repeat_byte(3), "fromBlock": "0x1"}),
+            serde_json::json!({"blockHash": H256::repeat_byte(3), "toBlock": "latest"}),
+        ] {
+            assert!(LogFilter::try_from(json.clone()).is_err());
+            assert!(serde_json::from_value::<EthGetLogsParams>(json).is_err());
+        }
+
+        let params: EthGetLogsParams =
+            serde_json::from_value(serde_json::json!({"fromBlock": "0x1"})).unwrap();
+        assert_eq!(params, EthGetLogsParams::new().from_block(1));
+    }
+
+    #[test]
+    fn should_build_event_filter() {
+        let params = EthGetLogsParams::new()
+            .address(H160::repeat_byte(1))
+            .address(H160::repeat_byte(2))
+            .event("Transfer(address,address,uint256)")
+            .topic(2, [H256::repeat_byte(4), H256::repeat_byte(5)]);
+
+        assert_eq!(
+            params.address,
+            Some(vec![H160::repeat_byte(1), H160::repeat_byte(2)])
+        );
+        assert_eq!(
+            params.topics,
+            Some(vec![
+                Some(vec![
+                    "0xddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef"
+                        .parse()
+                        .unwrap()
+                ]),
+                None,
+                Some(vec![H256::repeat_byte(4), H256::repeat_byte(5)]),
+            ])
+        );
+        assert_eq!(params.block_filter, LogsBlockFilter::default());
+    }
+
+    #[test]
+    fn should_build_params_with_range_and_topics() {
+        let from: H256 = "0x00000000000000000000000000b46c2526e227482e2ebb8f4c69e4674d262e75"
+            .parse()
+            .unwrap();
+        let to: H256 = "0x00000000000000000000000054a2d42a40f51259dedd1978f6c118a0f0eff078"
+            .parse()
+            .unwrap();
+
+        let params = EthGetLogsParams::new()
+            .address(H160::repeat_byte(1))
+            .from_block(0x429d3b)
+            .to_block(BlockNumber::Latest)
+            .event("Transfer(address,address,uint256)")
+            .topic1(from)
+            .topic2(to);
+
+        assert_eq!(
+            params,
+            EthGetLogsParams {
+                address: Some(vec![H160::repeat_byte(1)]),
+                block_filter: LogsBlockFilter::Range {
+                    from_block: Some("0x429d3b".parse().unwrap()),
+                    to_block: Some(BlockNumber::Latest),
+                },
+                topics: Some(vec![
+                    Some(vec![
+                        "0xddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef"
+                            .parse()
+                            .unwrap()
+                    ]),
+                    Some(vec![from]),
+                    Some(vec![to]),
+                ]),
+            }
+        );
+    }
+
+    #[test]
+    fn should_convert_to_and_from_log_filter() {
+        let filter = LogFilter::try_from(serde_json::json!({
+            "fromBlock": "earliest",
+            "toBlock": "0x10",
+            "address": "0x0101010101010101010101010101010101010101",
+            "topics": [null, ["0x0202020202020202020202020202020202020202020202020202020202020202"]],
+        }))
+        .unwrap();
+
+        let params = EthGetLogsParams::from(filter.clone());
+        assert_eq!(
+            params,
+            EthGetLogsParams::new()
+                .address(H160::repeat_byte(1))
+                .from_block(BlockNumber::Earliest)
+                .to_block(16)
+                .topic1(H256::repeat_byte(2))
+        );
+        assert_eq!(LogFilter::from(params), filter);
+
+        let filter = LogFilter::try_from(serde_json::json!({
+            "blockHash": "0x0303030303030303030303030303030303030303030303030303030303030303",
+        }))
+        .unwrap();
+        let params = EthGetLogsParams::from(filter.clone());
+        assert_eq!(
+            params.block_filter,
+            LogsBlockFilter::Hash {
+                block_hash: H256::repeat_byte(3)
+            }
+        );
+        assert_eq!(LogFilter::from(params), filter);
+    }
+
+    #[test]
+    fn should_convert_unbounded_range_to_and_from_log_filter() {
+        let filter = LogFilter {
+            block_filter: Some(BlockFilter::Bounded {
+                from_block: None,
+                to_block: None,
+            }),
+            ..Default::default()
+        };
+
+        let params = EthGetLogsParams::from(filter.clone());
+        assert_eq!(params, EthGetLogsParams::new());
+        assert_eq!(LogFilter::from(params), filter);
+
+        let params = EthGetLogsParams::from(LogFilter::default());
+        assert_eq!(params, EthGetLogsParams::new());
+        assert_eq!(
+            EthGetLogsParams::from(LogFilter::from(params.clone())),
+            params
+        );
+    }
+}
@@ -0,0 +1,630 @@
+use std::collections::{BTreeMap, HashSet};
+use std::fmt::Write;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use jsonrpc_core::{Call, Id, Output, Request, Response};
+
+use crate::{Client, EthJsonRpcError, EthJsonRpcResult};
+
+/// Class of the error of a call, used to aggregate the errors in the metrics.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ErrorClass {
+    /// The request could not be delivered or the response could not be received.
+    Transport,
+    /// The server answered with a non-success HTTP status.
+    HttpStatus,
+    /// The server answered with a JSON-RPC error object.
+    JsonRpc,
+    /// The request could not be serialized.
+    Serialization,
+    /// The response could not be deserialized.
+    Deserialization,
+    /// The response does not match the request.
+    UnexpectedResponse,
+    /// The endpoints or providers did not agree on the response.
+    Consensus,
+    /// The requested item does not exist.
+    NotFound,
+    /// No recorded response matches the request.
+    Replay,
+    /// The certificate of the response could not be verified.
+    Certification,
+    /// The canister call was rejected or failed.
+    Canister,
+    /// The canister call arguments or response could not be encoded or decoded.
+    Candid,
+    /// The client is not configured correctly.
+    Configuration,
+    /// The canister has not enough cycles for the http outcall.
+    Cycles,
+    /// The contract call could not be encoded.
+    Contract,
+}
+
+impl ErrorClass {
+    /// Returns the label of the class.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Transport => "transport",
+            Self::HttpStatus => "http_status",
+            Self::JsonRpc => "json_rpc",
+            Self::Serialization => "serialization",
+            Self::Deserialization => "deserialization",
+            Self::UnexpectedResponse => "unexpected_response",
+            Self::Consensus => "consensus",
+            Self::NotFound => "not_found",
+            Self::Replay => "replay",
+            Self::Certification => "certification",
+            Self::Canister => "canister",
+            Self::Candid => "candid",
+            Self::Configuration => "configuration",
+            Self::Cycles => "cycles",
+            Self::Contract => "contract",
+        }
+    }
+}
+
+impl From<&EthJsonRpcError> for ErrorClass {
+    fn from(err: &EthJsonRpcError) -> Self {
+        match err {
+            EthJsonRpcError::Transport(_) | EthJsonRpcError::ResponseTooLarge { .. } => {
+                Self::Transport
+            }
+            EthJsonRpcError::HttpStatus { .. } => Self::HttpStatus,
+            EthJsonRpcError::JsonRpc { .. } => Self::JsonRpc,
+            EthJsonRpcError::Serialization(_) => Self::Serialization,
+            EthJsonRpcError::Deserialization(_) => Self::Deserialization,
+            EthJsonRpcError::UnexpectedResponse(_) => Self::UnexpectedResponse,
+            EthJsonRpcError::NoQuorum { .. } | EthJsonRpcError::InconsistentResponses { .. } => {
+                Self::Consensus
+            }
+            EthJsonRpcError::NotFound(_) => Self::NotFound,
+            EthJsonRpcError::UnmatchedRequest(_) => Self::Replay,
+            EthJsonRpcError::Certification(_) => Self::Certification,
+            EthJsonRpcError::InvalidConfiguration(_) => Self::Configuration,
+            EthJsonRpcError::InsufficientCycles { .. } => Self::Cycles,
+            EthJsonRpcError::Contract(_) => Self::Contract,
+        }
+    }
+}
+
+/// Receives the measurements of the instrumented clients.
+///
+/// Implementations must be cheap, as they are called for every call.
+pub trait MetricsObserver: Send + Sync {
+    /// Observes a call to `method`, which took `latency` and failed with `error` if any.
+    ///
+    /// Each call of a batch is observed separately, with the latency of the whole batch.
+    fn observe_call(&self, method: &str, latency: Duration, error: Option<ErrorClass>);
+
+    /// Observes a batch request with `size` calls.
+    fn observe_batch(&self, size: usize);
+}
+
+/// A client which reports the calls sent through the inner client to a [`MetricsObserver`].
+///
+/// Calls answered with a JSON-RPC error are reported with [`ErrorClass::JsonRpc`],
+/// while requests failing as a whole report the error for all their calls.
+#[derive(Clone)]
+pub struct MetricsClient<C: Client> {
+    inner: C,
+    observer: Arc<dyn MetricsObserver>,
+}
+
+impl<C: Client> MetricsClient<C> {
+    /// Creates a new client.
+    ///
+    /// # Arguments
+    /// * `inner` - The client used to send the requests.
+    /// * `observer` - The observer of the calls.
+    pub fn new(inner: C, observer: Arc<dyn MetricsObserver>) -> Self {
+        Self { inner, observer }
+    }
+
+    /// Returns the inner client.
+    pub fn inner(&self) -> &C {
+        &self.inner
+    }
+
+    /// Returns the observer of the calls.
+    pub fn observer(&self) -> &Arc<dyn MetricsObserver> {
+        &self.observer
+    }
+}
+
+impl<C: Client + 'static> Client for MetricsClient<C> {
+    fn send_rpc_request(
+        &self,
+        request: Request,
+    ) -> Pin<Box<dyn Future<Output = EthJsonRpcResult<Response>> + Send>> {
+        let client = self.clone();
+
+        Box::pin(async move {
+            let calls: Vec<(String, Option<Id>)> = match &request {
+                Request::Single(call) => call_method(call).into_iter().collect(),
+                Request::Batch(calls) => {
+                    client.observer.observe_batch(calls.len());
+                    calls.iter().filter_map(call_method).collect()
+                }
+            };
+
+            let started_at = Instant::now();
+            let result = client.inner.send_rpc_request(request).await;
+            let latency = started_at.elapsed();
+
+            let failed_ids: HashSet<&Id> = match &result {
+                Ok(Response::Single(output)) => failed_id(output).into_iter().collect(),
+                Ok(Response::Batch(outputs)) => outputs.iter().filter_map(failed_id).collect(),
+                Err(_) => HashSet::new(),
+            };
+            for (method, id) in &calls {
+                let error = match &result {
+                    Err(err) => Some(err.into()),
+                    Ok(_) if id.as_ref().is_some_and(|id| failed_ids.contains(id)) => {
+                        Some(ErrorClass::JsonRpc)
+                    }
+                    Ok(_) => None,
+                };
+                client.observer.observe_call(method, latency, error);
+            }
+
+            result
+        })
+    }
+}
+
+/// Returns the method and, for method calls, the id of the call.
+fn call_method(call: &Call) -> Option<(String, Option<Id>)> {
+    match call {
+        Call::MethodCall(call) => Some((call.method.clone(), Some(call.id.clone()))),
+        Call::Notification(notification) => Some((notification.method.clone(), None)),
+        Call::Invalid { .. } => None,
+    }
+}
+
+fn failed_id(output: &Output) -> Option<&Id> {
+    match output {
+        Output::Failure(failure) => Some(&failure.id),
+        Output::Success(_) => None,
+    }
+}
+
+#[cfg(feature = "ic-canister-client")]
+pub use canister::MetricsCanisterClient;
+
+#[cfg(feature = "ic-canister-client")]
+mod canister {
+    use candid::utils::ArgumentEncoder;
+    use candid::CandidType;
+    use ic_canister_client::{CanisterClient, CanisterClientError, CanisterClientResult};
+    use serde::de::DeserializeOwned;
+
+    use super::*;
+
+    /// A canister client which reports the calls sent through the inner client
+    /// to a [`MetricsObserver`].
+    #[derive(Clone)]
+    pub struct MetricsCanisterClient<C: CanisterClient> {
+        inner: C,
+        observer: Arc<dyn MetricsObserver>,
+    }
+
+    impl<C: CanisterClient> MetricsCanisterClient<C> {
+        /// Creates a new client.
+        ///
+        /// # Arguments
+        /// * `inner` - The client used to call the canister.
+        /// * `observer` - The observer of the calls.
+        pub fn new(inner: C, observer: Arc<dyn MetricsObserver>) -> Self {
+            Self { inner, observer }
+        }
+
+        /// Returns the inner client.
+        pub fn inner(&self) -> &C {
+            &self.inner
+        }
+
+        /// Returns the observer of the calls.
+        pub fn observer(&self) -> &Arc<dyn MetricsObserver> {
+            &self.observer
+        }
+
+        fn observe<R>(&self, method: &str, started_at: Instant, result: &CanisterClientResult<R>) {
+            let error = result.as_ref().err().map(|err| match err {
+                CanisterClientError::CandidError(_) => ErrorClass::Candid,
+                _ => ErrorClass::Canister,
+            });
+            self.observer
+                .observe_call(method, started_at.elapsed(), error);
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl<C: CanisterClient> CanisterClient for MetricsCanisterClient<C> {
+        async fn update<T, R>(&self, method: &str, args: T) -> CanisterClientResult<R>
+        where
+            T: ArgumentEncoder + Send + Sync,
+            R: DeserializeOwned + CandidType,
+        {
+            let started_at = Instant::now();
+            let result = self.inner.update(method, args).await;
+            self.observe(method, started_at, &result);
+            result
+        }
+
+        async fn query<T, R>(&self, method: &str, args: T) -> CanisterClientResult<R>
+        where
+            T: ArgumentEncoder + Send + Sync,
+            R: DeserializeOwned + CandidType,
+        {
+            let started_at = Instant::now();
+            let result = self.inner.query(method, args).await;
+            self.observe(method, started_at, &result);
+            result
+        }
+    }
+}
+
+/// Upper bounds of the call duration histogram buckets, in seconds.
+const DURATION_BUCKETS: &[f64] = &[
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0,
+];
+
+/// Upper bounds of the batch size histogram buckets.
+const BATCH_SIZE_BUCKETS: &[f64] = &[1.0, 2.0, 5.0, 10.0, 20.0, 50.0, 100.0, 200.0, 500.0];
+
+#[derive(Debug, Clone)]
+struct Histogram {
+    bounds: &'static [f64],
+    counts: Vec<u64>,
+    sum: f64,
+    count: u64,
+}
+
+impl Histogram {
+    fn new(bounds: &'static [f64]) -> Self {
+        Self {
+            bounds,
+            counts: vec![0; bounds.len()],
+            sum: 0.0,
+            count: 0,
+        }
+    }
+
+    fn observe(&mut self, value: f64) {
+        if let Some(bucket) = self.bounds.iter().position(|bound| value <= *bound) {
+            self.counts[bucket] += 1;
+        }
+        self.sum += value;
+        self.count += 1;
+    }
+
+    fn render(&self, out: &mut String, name: &str, labels: &str) {
+        let bucket_labels = |bound: &str| match labels {
+            "" => format!("le=\"{bound}\""),
+            labels => format!("{labels},le=\"{bound}\""),
+        };
+        let labels = match labels {
+            "" => String::new(),
+            labels => format!("{{{labels}}}"),
+        };
+
+        let mut cumulative = 0;
+        for (bound, count) in self.bounds.iter().zip(&self.counts) {
+            cumulative += count;
+            let _ = writeln!(
+                out,
+                "{name}_bucket{{{}}} {cumulative}",
+                bucket_labels(&bound.to_string())
+            );
+        }
+        let _ = writeln!(
+            out,
+            "{name}_bucket{{{}}} {}",
+            bucket_labels("+Inf"),
+            self.count
+        );
+        let _ = writeln!(out, "{name}_sum{labels} {}", self.sum);
+        let _ = writeln!(out, "{name}_count{labels} {}", self.count);
+    }
+}
+
+#[derive(Debug)]
+struct PrometheusState {
+    durations: BTreeMap<String, Histogram>,
+    errors: BTreeMap<(String, ErrorClass), u64>,
+    batch_sizes: Histogram,
+}
+
+/// A [`MetricsObserver`] aggregating the measurements in memory and rendering them
+/// in the Prometheus text exposition format.
+///
+/// The following metrics are exported, prefixed with the namespace:
+/// - `calls_total{method}`: the number of calls;
+/// - `call_errors_total{method, class}`: the number of failed calls by error class;
+/// - `call_duration_seconds{method}`: a histogram of the call latencies;
+/// - `batch_size`: a histogram of the batch request sizes.
+#[derive(Debug)]
+pub struct PrometheusMetrics {
+    namespace: String,
+    state: Mutex<PrometheusState>,
+}
+
+impl PrometheusMetrics {
+    /// Creates a new exporter.
+    ///
+    /// # Arguments
+    /// * `namespace` - The prefix of the metric names, e.g. `eth_json_rpc`.
+    pub fn new(namespace: impl Into<String>) -> Self {
+        Self {
+            namespace: namespace.into(),
+            state: Mutex::new(PrometheusState {
+                durations: BTreeMap::new(),
+                errors: BTreeMap::new(),
+                batch_sizes: Histogram::new(BATCH_SIZE_BUCKETS),
+            }),
+        }
+    }
+
+    /// Renders the metrics in the Prometheus text exposition format.
+    pub fn render(&self) -> String {
+        let state = self.state.lock().expect("poisoned mutex");
+        let namespace = &self.namespace;
+        let mut out = String::new();
+
+        let name = format!("{namespace}_calls_total");
+        let _ = writeln!(out, "# HELP {name} Number of calls by method.");
+        let _ = writeln!(out, "# TYPE {name} counter");
+        for (method, histogram) in &state.durations {
+            let _ = writeln!(
+                out,
+                "{name}{{method=\"{}\"}} {}",
+                escape_label(method),
+                histogram.count
+            );
+        }
+
+        let name = format!("{namespace}_call_errors_total");
+        let _ = writeln!(
+            out,
+            "# HELP {name} Number of failed calls by method and error class."
+        );
+        let _ = writeln!(out, "# TYPE {name} counter");
+        for ((method, class), count) in &state.errors {
+            let _ = writeln!(
+                out,
+                "{name}{{method=\"{}\",class=\"{}\"}} {count}",
+                escape_label(method),
+                class.as_str()
+            );
+        }
+
+        let name = format!("{namespace}_call_duration_seconds");
+        let _ = writeln!(out, "# HELP {name} Duration of the calls by method.");
+        let _ = writeln!(out, "# TYPE {name} histogram");
+        for (method, histogram) in &state.durations {
+            let labels = format!("method=\"{}\"", escape_label(method));
+            histogram.render(&mut out, &name, &labels);
+        }
+
+        let name = format!("{namespace}_batch_size");
+        let _ = writeln!(out, "# HELP {name} Number of calls of the batch requests.");
+        let _ = writeln!(out, "# TYPE {name} histogram");
+        state.batch_sizes.render(&mut out, &name, "");
+
+        out
+    }
+}
+
+impl MetricsObserver for PrometheusMetrics {
+    fn observe_call(&self, method: &str, latency: Duration, error: Option<ErrorClass>) {
+        let mut state = self.state.lock().expect("poisoned mutex");
+        state
+            .durations
+            .entry(method.to_string())
+            .or_insert_with(|| Histogram::new(DURATION_BUCKETS))
+            .observe(latency.as_secs_f64());
+        if let Some(class) = error {
+            *state.errors.entry((method.to_string(), class)).or_default() += 1;
+        }
+    }
+
+    fn observe_batch(&self, size: usize) {
+        self.state
+            .lock()
+            .expect("poisoned mutex")
+            .batch_sizes
+            .observe(size as f64);
+    }
+}
+
+/// Escapes a label value of the Prometheus text format.
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use ethers_core::types::{BlockNumber, U256, U64};
+
+    use super::*;
+    use crate::mock::MockClient;
+    use crate::EthJsonRpcClient;
+
+    type Observation = (String, Option<ErrorClass>);
+
+    #[derive(Default)]
+    struct RecordingObserver {
+        calls: Mutex<Vec<Observation>>,
+        batches: Mutex<Vec<usize>>,
+    }
+
+    impl MetricsObserver for RecordingObserver {
+        fn observe_call(&self, method: &str, _latency: Duration, error: Option<ErrorClass>) {
+            self.calls.lock().unwrap().push((method.to_string(), error));
+        }
+
+        fn observe_batch(&self, size: usize) {
+            self.batches.lock().unwrap().push(size);
+        }
+    }
+
+    #[tokio::test]
+    async fn should_observe_calls_and_batches() {
+        let mock = MockClient::new();
+        mock.on("eth_blockNumber", U64::from(3))
+            .on_error(
+                "eth_getBalance",
+                EthJsonRpcError::JsonRpc {
+                    code: -32000,
+                    message: "header not found".to_string(),
+                    data: None,
+                },
+            )
+            .on_error(
+                "eth_gasPrice",
+                EthJsonRpcError::Transport("connection reset".to_string()),
+            );
+        let observer = Arc::new(RecordingObserver::default());
+        let client = EthJsonRpcClient::new(MetricsClient::new(mock, observer.clone()));
+
+        client.get_block_number().await.unwrap();
+        client.gas_price().await.unwrap_err();
+        client
+            .batch_request_results::<U256>(
+                "eth_getBalance".to_string(),
+                (0..2).map(|id| {
+                    (
+                        jsonrpc_core::Params::Array(vec![
+                            serde_json::to_value(ethers_core::types::H160::zero()).unwrap(),
+                            serde_json::to_value(BlockNumber::Latest).unwrap(),
+                        ]),
+                        Id::Num(id),
+                    )
+                }),
+                10,
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(
+            *observer.calls.lock().unwrap(),
+            vec![
+                ("eth_blockNumber".to_string(), None),
+                ("eth_gasPrice".to_string(), Some(ErrorClass::Transport)),
+                ("eth_getBalance".to_string(), Some(ErrorClass::JsonRpc)),
+                ("eth_getBalance".to_string(), Some(ErrorClass::JsonRpc)),
+            ]
+        );
+        assert_eq!(*observer.batches.lock().unwrap(), vec![2]);
+    }
+
+    #[cfg(feature = "ic-canister-client")]
+    mod canister {
+        use candid::utils::ArgumentEncoder;
+        use candid::CandidType;
+        use ic_canister_client::{CanisterClient, CanisterClientError, CanisterClientResult};
+        use ic_exports::ic_cdk::api::call::RejectionCode;
+        use serde::de::DeserializeOwned;
+
+        use super::*;
+
+        /// Fails the `candid` and `reject` methods and answers 1 to the other ones.
+        #[derive(Clone)]
+        struct StubCanisterClient;
+
+        impl StubCanisterClient {
+            fn call<R: DeserializeOwned>(&self, method: &str) -> CanisterClientResult<R> {
+                match method {
+                    "candid" => Err(CanisterClientError::CandidError(candid::Error::msg(
+                        "type mismatch",
+                    ))),
+                    "reject" => Err(CanisterClientError::CanisterError((
+                        RejectionCode::CanisterReject,
+                        "rejected".to_string(),
+                    ))),
+                    _ => Ok(serde_json::from_value(1.into()).unwrap()),
+                }
+            }
+        }
+
+        #[async_trait::async_trait]
+        impl CanisterClient for StubCanisterClient {
+            async fn update<T, R>(&self, method: &str, _args: T) -> CanisterClientResult<R>
+            where
+                T: ArgumentEncoder + Send + Sync,
+                R: DeserializeOwned + CandidType,
+            {
+                self.call(method)
+            }
+
+            async fn query<T, R>(&self, method: &str, _args: T) -> CanisterClientResult<R>
+            where
+                T: ArgumentEncoder + Send + Sync,
+                R: DeserializeOwned + CandidType,
+            {
+                self.call(method)
+            }
+        }
+
+        #[tokio::test]
+        async fn should_observe_canister_calls() {
+            let observer = Arc::new(RecordingObserver::default());
+            let client = MetricsCanisterClient::new(StubCanisterClient, observer.clone());
+
+            let balance: u64 = client.query("get_balance", ()).await.unwrap();
+            assert_eq!(balance, 1);
+            client.update::<_, u64>("candid", ()).await.unwrap_err();
+            client.query::<_, u64>("reject", ()).await.unwrap_err();
+
+            assert_eq!(
+                *observer.calls.lock().unwrap(),
+                vec![
+                    ("get_balance".to_string(), None),
+                    ("candid".to_string(), Some(ErrorClass::Candid)),
+                    ("reject".to_string(), Some(ErrorClass::Canister)),
+                ]
+            );
+        }
+    }
+
+    #[test]
+    fn should_render_prometheus_metrics() {
+        let metrics = PrometheusMetrics::new("eth_json_rpc");
+        metrics.observe_call("eth_blockNumber", Duration::from_millis(20), None);
+        metrics.observe_call(
+            "eth_blockNumber",
+            Duration::from_millis(700),
+            Some(ErrorClass::Transport),
+        );
+        metrics.observe_batch(15);
+
+        let rendered = metrics.render();
+
+        let expected_lines = [
+            "# TYPE eth_json_rpc_calls_total counter",
+            "eth_json_rpc_calls_total{method=\"eth_blockNumber\"} 2",
+            "eth_json_rpc_call_errors_total{method=\"eth_blockNumber\",class=\"transport\"} 1",
+            "# TYPE eth_json_rpc_call_duration_seconds histogram",
+            "eth_json_rpc_call_duration_seconds_bucket{method=\"eth_blockNumber\",le=\"0.025\"} 1",
+            "eth_json_rpc_call_duration_seconds_bucket{method=\"eth_blockNumber\",le=\"1\"} 2",
+            "eth_json_rpc_call_duration_seconds_bucket{method=\"eth_blockNumber\",le=\"+Inf\"} 2",
+            "eth_json_rpc_call_duration_seconds_count{method=\"eth_blockNumber\"} 2",
+            "eth_json_rpc_batch_size_bucket{le=\"10\"} 0",
+            "eth_json_rpc_batch_size_bucket{le=\"20\"} 1",
+            "eth_json_rpc_batch_size_sum 15",
+        ];
+        for line in expected_lines {
+            assert!(
+                rendered.lines().any(|rendered| rendered == line),
+                "missing {line} in:\n{rendered}"
+            );
+        }
+    }
+}
@@ -0,0 +1,350 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use jsonrpc_core::{
+    Call, Failure, MethodCall, Output, Params, Request, Response, Success, Version,
+};
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::{Client, EthJsonRpcError, EthJsonRpcResult};
+
+type Handler = Arc<dyn Fn(&Params) -> EthJsonRpcResult<Value> + Send + Sync>;
+
+/// An in-memory [`Client`] answering the requests with the handlers registered
+/// for each JSON-RPC method and recording the calls it receives.
+///
+/// Handlers returning a [`EthJsonRpcError::JsonRpc`] error produce a JSON-RPC error
+/// for the call, while any other error makes the whole request fail, as a transport
+/// error would. Calls to methods without a handler are answered with a
+/// "method not found" error.
+///
+/// Clones share the handlers and the recorded calls, so that the client can be
+/// inspected after moving a clone into the code under test.
+#[derive(Clone, Default)]
+pub struct MockClient {
+    handlers: Arc<Mutex<HashMap<String, Handler>>>,
+    latencies: Arc<Mutex<HashMap<String, Duration>>>,
+    calls: Arc<Mutex<Vec<MethodCall>>>,
+    requests: Arc<AtomicUsize>,
+}
+
+impl MockClient {
+    /// Creates a new client without handlers.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Answers the calls to `method` with `value`.
+    pub fn on<R: Serialize>(&self, method: &str, value: R) -> &Self {
+        let value = serde_json::to_value(value).expect("the mock value should be serializable");
+        self.on_call(method, move |_| Ok(value.clone()))
+    }
+
+    /// Answers the calls to `method` with the result of `handler`, which receives the call params.
+    pub fn on_call<R, F>(&self, method: &str, handler: F) -> &Self
+    where
+        R: Serialize,
+        F: Fn(&Params) -> EthJsonRpcResult<R> + Send + Sync + 'static,
+    {
+        let handler: Handler = Arc::new(move |params| {
+            handler(params).and_then(|result| Ok(serde_json::to_value(result)?))
+        });
+        self.handlers
+            .lock()
+            .expect("poisoned mutex")
+            .insert(method.to_string(), handler);
+        self
+    }
+
+    /// Answers the calls to `method` with `error`.
+    pub fn on_error(&self, method: &str, error: EthJsonRpcError) -> &Self {
+        self.on_call::<Value, _>(method, move |_| Err(error.clone()))
+    }
+
+    /// Delays the requests containing calls to `method`.
+    ///
+    /// Requests containing calls to several delayed methods wait for the longest delay.
+    pub fn with_latency(&self, method: &str, latency: Duration) -> &Self {
+        self.latencies
+            .lock()
+            .expect("poisoned mutex")
+            .insert(method.to_string(), latency);
+        self
+    }
+
+    /// Returns all the calls received so far, in order.
+    pub fn calls(&self) -> Vec<MethodCall> {
+        self.calls.lock().expect("poisoned mutex").clone()
+    }
+
+    /// Returns the params of the calls to `method` received so far, in order.
+    pub fn calls_to(&self, method: &str) -> Vec<Params> {
+        self.calls
+            .lock()
+            .expect("poisoned mutex")
+            .iter()
+            .filter(|call| call.method == method)
+            .map(|call| call.params.clone())
+            .collect()
+    }
+
+    /// Returns the number of calls to `method` received so far.
+    pub fn call_count(&self, method: &str) -> usize {
+        self.calls
+            .lock()
+            .expect("poisoned mutex")
+            .iter()
+            .filter(|call| call.method == method)
+            .count()
+    }
+
+    /// Returns the number of requests received so far, a batch counting as one request.
+    pub fn request_count(&self) -> usize {
+        self.requests.load(Ordering::SeqCst)
+    }
+
+    /// Forgets the calls received so far.
+    pub fn clear_calls(&self) {
+        self.calls.lock().expect("poisoned mutex").clear();
+        self.requests.store(0, Ordering::SeqCst);
+    }
+
+    /// Panics if `method` has not been called exactly `times` times.
+    #[track_caller]
+    pub fn assert_called_times(&self, method: &str, times: usize) {
+        let count = self.call_count(method);
+        assert_eq!(
+            count, times,
+            "expected {times} calls to {method}, received {count}"
+        );
+    }
+
+    /// Panics if `method` has been called.
+    #[track_caller]
+    pub fn assert_not_called(&self, method: &str) {
+        self.assert_called_times(method, 0);
+    }
+
+    /// Panics if `method` has never been called with `params`.
+    #[track_caller]
+    pub fn assert_called_with(&self, method: &str, params: Params) {
+        let calls = self.calls_to(method);
+        assert!(
+            calls.contains(&params),
+            "expected a call to {method} with {params:?}, received {calls:?}"
+        );
+    }
+
+    fn answer(&self, call: MethodCall) -> EthJsonRpcResult<Output> {
+        let handler = self
+            .handlers
+            .lock()
+            .expect("poisoned mutex")
+            .get(&call.method)
+            .cloned();
+
+        let result = match handler {
+            Some(handler) => handler(&call.params),
+            None => Err(jsonrpc_core::Error::method_not_found().into()),
+        };
+
+        match result {
+            Ok(result) => Ok(Output::Success(Success {
+                jsonrpc: Some(Version::V2),
+                result,
+                id: call.id,
+            })),
+            Err(err @ EthJsonRpcError::JsonRpc { .. }) => Ok(Output::Failure(Failure {
+                jsonrpc: Some(Version::V2),
+                error: err.into(),
+                id: call.id,
+            })),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Records the method calls of the request and returns the longest latency among them.
+    fn record(&self, request: &Request) -> Duration {
+        let calls: Vec<&MethodCall> = match request {
+            Request::Single(Call::MethodCall(call)) => vec![call],
+            Request::Single(_) => vec![],
+            Request::Batch(calls) => calls
+                .iter()
+                .filter_map(|call| match call {
+                    Call::MethodCall(call) => Some(call),
+                    _ => None,
+                })
+                .collect(),
+        };
+
+        self.requests.fetch_add(1, Ordering::SeqCst);
+        self.calls
+            .lock()
+            .expect("poisoned mutex")
+            .extend(calls.iter().map(|call| (*call).clone()));
+
+        let latencies = self.latencies.lock().expect("poisoned mutex");
+        calls
+            .iter()
+            .filter_map(|call| latencies.get(&call.method))
+            .max()
+            .copied()
+            .unwrap_or_default()
+    }
+}
+
+impl Client for MockClient {
+    fn send_rpc_request(
+        &self,
+        request: Request,
+    ) -> Pin<Box<dyn Future<Output = EthJsonRpcResult<Response>> + Send>> {
+        let client = self.clone();
+
+        Box::pin(async move {
+            let latency = client.record(&request);
+            if !latency.is_zero() {
+                tokio::time::sleep(latency).await;
+            }
+
+            match request {
+                Request::Single(Call::MethodCall(call)) => {
+                    Ok(Response::Single(client.answer(call)?))
+                }
+                Request::Single(_) => Err(EthJsonRpcError::UnexpectedResponse(
+                    "MockClient - notifications do not have a response".to_string(),
+                )),
+                Request::Batch(calls) => calls
+                    .into_iter()
+                    .filter_map(|call| match call {
+                        Call::MethodCall(call) => Some(client.answer(call)),
+                        _ => None,
+                    })
+                    .collect::<EthJsonRpcResult<Vec<_>>>()
+                    .map(Response::Batch),
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ethers_core::types::{Block, BlockNumber, Transaction, U256, U64};
+    use tokio::time::Instant;
+
+    use super::*;
+    use crate::EthJsonRpcClient;
+
+    #[tokio::test]
+    async fn should_answer_with_handlers() {
+        let mock = MockClient::new();
+        mock.on("eth_chainId", U64::from(355113))
+            .on_call("eth_getBalance", |params| {
+                let Params::Array(params) = params else {
+                    panic!("unexpected params");
+                };
+                Ok(U256::from(params.len()))
+            });
+        let client = EthJsonRpcClient::new(mock.clone());
+
+        assert_eq!(client.get_chain_id().await.unwrap(), 355113);
+        assert_eq!(
+            client
+                .get_balance(Default::default(), BlockNumber::Latest)
+                .await
+                .unwrap(),
+            2.into()
+        );
+
+        mock.assert_called_times("eth_chainId", 1);
+        mock.assert_called_with(
+            "eth_getBalance",
+            Params::Array(vec![
+                serde_json::to_value(ethers_core::types::H160::zero()).unwrap(),
+                "latest".into(),
+            ]),
+        );
+        mock.assert_not_called("eth_gasPrice");
+        assert_eq!(mock.request_count(), 2);
+    }
+
+    #[tokio::test]
+    async fn should_inject_errors() {
+        let mock = MockClient::new();
+        mock.on_error(
+            "eth_gasPrice",
+            EthJsonRpcError::JsonRpc {
+                code: -32000,
+                message: "header not found".to_string(),
+                data: None,
+            },
+        )
+        .on_error(
+            "eth_blockNumber",
+            EthJsonRpcError::Transport("connection reset".to_string()),
+        );
+        let client = EthJsonRpcClient::new(mock.clone());
+
+        assert_eq!(
+            client.gas_price().await.unwrap_err().json_rpc_code(),
+            Some(-32000)
+        );
+        assert_eq!(
+            client.get_block_number().await.unwrap_err(),
+            EthJsonRpcError::Transport("connection reset".to_string())
+        );
+        assert_eq!(
+            client.get_chain_id().await.unwrap_err().json_rpc_code(),
+            Some(jsonrpc_core::ErrorCode::MethodNotFound.code())
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn should_inject_latency() {
+        let mock = MockClient::new();
+        mock.on("eth_blockNumber", U64::from(10))
+            .with_latency("eth_blockNumber", Duration::from_secs(3));
+        let client = EthJsonRpcClient::new(mock.clone());
+        let started_at = Instant::now();
+
+        assert_eq!(client.get_block_number().await.unwrap(), 10);
+
+        assert!(started_at.elapsed() >= Duration::from_secs(3));
+    }
+
+    #[tokio::test]
+    async fn should_answer_batches() {
+        let mock = MockClient::new();
+        mock.on_call("eth_getBlockByNumber", |params| {
+            let Params::Array(params) = params else {
+                panic!("unexpected params");
+            };
+            Ok(Block::<Transaction> {
+                number: Some(serde_json::from_value(params[0].clone())?),
+                ..Default::default()
+            })
+        });
+        let client = EthJsonRpcClient::new(mock.clone());
+
+        let blocks = client
+            .get_full_blocks_by_number(
+                (0..5u64).map(|number| BlockNumber::Number(number.into())),
+                2,
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(blocks.len(), 5);
+        assert_eq!(blocks[4].number, Some(4.into()));
+        mock.assert_called_times("eth_getBlockByNumber", 5);
+        assert_eq!(mock.request_count(), 3);
+
+        mock.clear_calls();
+        mock.assert_not_called("eth_getBlockByNumber");
+        assert_eq!(mock.request_count(), 0);
+    }
+}
@@ -0,0 +1,537 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use futures::stream::FuturesUnordered;
+use futures::StreamExt;
+use jsonrpc_core::{Request, Response};
+
+use crate::{normalize_response, Client, EthJsonRpcError, EthJsonRpcResult};
+
+/// Strategy used by the [`MultiEndpointClient`] to dispatch the requests.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MultiEndpointStrategy {
+    /// Sends the request to the healthiest endpoint and moves to the next
+    /// one if it fails.
+    Failover,
+    /// Sends the request to `endpoints` endpoints concurrently and returns
+    /// the response as soon as `threshold` of them agree.
+    /// Batch responses are compared regardless of the order of their items.
+    Quorum { endpoints: usize, threshold: usize },
+}
+
+/// Configuration of the endpoints health tracking.
+#[derive(Debug, Clone)]
+pub struct HealthPolicy {
+    /// Number of consecutive failures after which an endpoint is put in cool-down.
+    pub max_consecutive_failures: u32,
+    /// Time during which an endpoint in cool-down is used only if
+    /// all the other endpoints are unavailable.
+    pub cooldown: Duration,
+}
+
+impl Default for HealthPolicy {
+    fn default() -> Self {
+        Self {
+            max_consecutive_failures: 3,
+            cooldown: Duration::from_secs(30),
+        }
+    }
+}
+
+/// Health of a single endpoint.
+#[derive(Debug, Default, Clone)]
+struct EndpointHealth {
+    consecutive_failures: u32,
+    cooldown_until: Option<Instant>,
+}
+
+impl EndpointHealth {
+    fn is_cooling_down(&self, now: Instant) -> bool {
+        self.cooldown_until.is_some_and(|until| until > now)
+    }
+}
+
+/// A client which dispatches the requests to several endpoints.
+#[derive(Clone)]
+pub struct MultiEndpointClient<C: Client> {
+    endpoints: Arc<Vec<C>>,
+    health: Arc<Mutex<Vec<EndpointHealth>>>,
+    strategy: MultiEndpointStrategy,
+    health_policy: HealthPolicy,
+}
+
+impl<C: Client> MultiEndpointClient<C> {
+    /// Creates a new client which uses the endpoints in failover mode.
+    ///
+    /// # Arguments
+    /// * `endpoints` - The endpoint clients, in order of preference.
+    pub fn failover(endpoints: Vec<C>) -> Self {
+        Self::with_strategy(endpoints, MultiEndpointStrategy::Failover)
+    }
+
+    /// Creates a new client which uses the endpoints in quorum mode.
+    ///
+    /// # Arguments
+    /// * `endpoints` - The endpoint clients, in order of preference.
+    /// * `threshold` - The number of endpoints which must return the same response.
+    ///
+    /// Fails if the threshold is zero or greater than the number of endpoints.
+    pub fn quorum(endpoints: Vec<C>, threshold: usize) -> EthJsonRpcResult<Self> {
+        let count = endpoints.len();
+        Self::new(
+            endpoints,
+            MultiEndpointStrategy::Quorum {
+                endpoints: count,
+                threshold,
+            },
+        )
+    }
+
+    /// Creates a new client with the given strategy.
+    ///
+    /// # Arguments
+    /// * `endpoints` - The endpoint clients, in order of preference.
+    /// * `strategy` - The dispatching strategy.
+    ///
+    /// Fails if the quorum threshold is zero or greater than the number of queried
+    /// endpoints, or if more endpoints are queried than configured.
+    pub fn new(endpoints: Vec<C>, strategy: MultiEndpointStrategy) -> EthJsonRpcResult<Self> {
+        if let MultiEndpointStrategy::Quorum {
+            endpoints: queried,
+            threshold,
+        } = strategy
+        {
+            if threshold == 0 {
+                return Err(EthJsonRpcError::InvalidConfiguration(
+                    "the quorum threshold must be at least 1".to_string(),
+                ));
+            }
+            if threshold > queried {
+                return Err(EthJsonRpcError::InvalidConfiguration(format!(
+                    "the quorum threshold {threshold} is greater than the {queried} queried endpoints"
+                )));
+            }
+            if queried > endpoints.len() {
+                return Err(EthJsonRpcError::InvalidConfiguration(format!(
+                    "the quorum queries {queried} endpoints, but only {} are configured",
+                    endpoints.len()
+                )));
+            }
+        }
+
+        Ok(Self::with_strategy(endpoints, strategy))
+    }
+
+    fn with_strategy(endpoints: Vec<C>, strategy: MultiEndpointStrategy) -> Self {
+        let health = vec![EndpointHealth::default(); endpoints.len()];
+        Self {
+            endpoints: Arc::new(endpoints),
+            health: Arc::new(Mutex::new(health)),
+            strategy,
+            health_policy: HealthPolicy::default(),
+        }
+    }
+
+    /// Sets the health tracking configuration.
+    pub fn with_health_policy(mut self, health_policy: HealthPolicy) -> Self {
+        self.health_policy = health_policy;
+        self
+    }
+
+    /// Returns the dispatching strategy.
+    pub fn strategy(&self) -> MultiEndpointStrategy {
+        self.strategy
+    }
+
+    /// Returns the number of endpoints which are not in cool-down.
+    pub fn healthy_endpoints(&self) -> usize {
+        let now = Instant::now();
+        self.health
+            .lock()
+            .expect("endpoint health lock poisoned")
+            .iter()
+            .filter(|health| !health.is_cooling_down(now))
+            .count()
+    }
+
+    /// Returns the endpoint indexes sorted from the healthiest to the least healthy.
+    /// Endpoints with the same health keep the order of preference.
+    fn endpoints_by_health(&self) -> Vec<usize> {
+        let now = Instant::now();
+        let health = self.health.lock().expect("endpoint health lock poisoned");
+        let mut indexes = (0..health.len()).collect::<Vec<_>>();
+        indexes.sort_by_key(|&index| {
+            (
+                health[index].is_cooling_down(now),
+                health[index].consecutive_failures,
+            )
+        });
+        indexes
+    }
+
+    fn record_success(&self, index: usize) {
+        let mut health = self.health.lock().expect("endpoint health lock poisoned");
+        health[index] = EndpointHealth::default();
+    }
+
+    fn record_failure(&self, index: usize) {
+        let mut health = self.health.lock().expect("endpoint health lock poisoned");
+        let endpoint = &mut health[index];
+        endpoint.consecutive_failures += 1;
+        if endpoint.consecutive_failures >= self.health_policy.max_consecutive_failures {
+            log::warn!("MultiEndpointClient - endpoint {index} is put in cool-down");
+            endpoint.cooldown_until = Some(Instant::now() + self.health_policy.cooldown);
+        }
+    }
+}
+
+impl<C: Client + 'static> MultiEndpointClient<C> {
+    async fn send_failover(self, request: Request) -> EthJsonRpcResult<Response> {
+        let mut last_error = None;
+
+        for index in self.endpoints_by_health() {
+            match self.endpoints[index]
+                .send_rpc_request(request.clone())
+                .await
+            {
+                Ok(response) => {
+                    self.record_success(index);
+                    return Ok(response);
+                }
+                Err(err) => {
+                    log::warn!("MultiEndpointClient - endpoint {index} failed: {err}");
+                    self.record_failure(index);
+                    last_error = Some(err);
+                }
+            }
+        }
+
+        Err(last_error.unwrap_or_else(|| {
+            EthJsonRpcError::InvalidConfiguration("no endpoints configured".to_string())
+        }))
+    }
+
+    async fn send_quorum(
+        self,
+        request: Request,
+        endpoints: usize,
+        threshold: usize,
+    ) -> EthJsonRpcResult<Response> {
+        let mut pending = self
+            .endpoints_by_health()
+            .into_iter()
+            .take(endpoints)
+            .map(|index| {
+                let client = self.endpoints[index].clone();
+                let request = request.clone();
+                async move { (index, client.send_rpc_request(request).await) }
+            })
+            .collect::<FuturesUnordered<_>>();
+
+        // Distinct normalized responses with the first original response
+        // and the number of endpoints which returned them
+        let mut responses: Vec<(Response, Response, usize)> = Vec::new();
+        let mut failed = 0;
+
+        while let Some((index, result)) = pending.next().await {
+            match result {
+                Ok(response) => {
+                    self.record_success(index);
+                    let normalized = normalize_response(response.clone());
+                    let position = match responses
+                        .iter()
+                        .position(|(existing, _, _)| *existing == normalized)
+                    {
+                        Some(position) => {
+                            responses[position].2 += 1;
+                            position
+                        }
+                        None => {
+                            responses.push((normalized, response, 1));
+                            responses.len() - 1
+                        }
+                    };
+
+                    if responses[position].2 >= threshold {
+                        let (_, response, _) = responses.swap_remove(position);
+                        return Ok(response);
+                    }
+                }
+                Err(err) => {
+                    log::warn!("MultiEndpointClient - endpoint {index} failed: {err}");
+                    self.record_failure(index);
+                    failed += 1;
+                }
+            }
+
+            let best = responses
+                .iter()
+                .map(|(_, _, count)| *count)
+                .max()
+                .unwrap_or(0);
+            if best + pending.len() < threshold {
+                break;
+            }
+        }
+
+        Err(EthJsonRpcError::NoQuorum {
+            required: threshold,
+            agreeing: responses
+                .iter()
+                .map(|(_, _, count)| *count)
+                .max()
+                .unwrap_or(0),
+            failed,
+        })
+    }
+}
+
+impl<C: Client + 'static> Client for MultiEndpointClient<C> {
+    fn send_rpc_request(
+        &self,
+        request: Request,
+    ) -> Pin<Box<dyn Future<Output = EthJsonRpcResult<Response>> + Send>> {
+        let client = self.clone();
+
+        match self.strategy {
+            MultiEndpointStrategy::Failover => Box::pin(client.send_failover(request)),
+            MultiEndpointStrategy::Quorum {
+                endpoints,
+                threshold,
+            } => Box::pin(client.send_quorum(request, endpoints, threshold)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    use jsonrpc_core::{Call, Id, MethodCall, Output, Params, Success, Value, Version};
+
+    use super::*;
+    use crate::ETH_BLOCK_NUMBER_METHOD;
+
+    /// A client which always returns the same result.
+    #[derive(Clone)]
+    struct StaticClient {
+        result: Result<Value, EthJsonRpcError>,
+        calls: Arc<AtomicU32>,
+    }
+
+    impl StaticClient {
+        fn ok(value: &str) -> Self {
+            Self {
+                result: Ok(value.into()),
+                calls: Arc::default(),
+            }
+        }
+
+        fn err() -> Self {
+            Self {
+                result: Err(EthJsonRpcError::Transport("connection refused".to_string())),
+                calls: Arc::default(),
+            }
+        }
+
+        fn calls(&self) -> u32 {
+            self.calls.load(Ordering::SeqCst)
+        }
+    }
+
+    impl Client for StaticClient {
+        fn send_rpc_request(
+            &self,
+            _request: Request,
+        ) -> Pin<Box<dyn Future<Output = EthJsonRpcResult<Response>> + Send>> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            let result = self.result.clone().map(|result| {
+                Response::Single(Output::Success(Success {
+                    jsonrpc: Some(Version::V2),
+                    result,
+                    id: Id::Num(0),
+                }))
+            });
+
+            Box::pin(async move { result })
+        }
+    }
+
+    /// A client which always returns the same response.
+    #[derive(Clone)]
+    struct ResponseClient(Response);
+
+    impl Client for ResponseClient {
+        fn send_rpc_request(
+            &self,
+            _request: Request,
+        ) -> Pin<Box<dyn Future<Output = EthJsonRpcResult<Response>> + Send>> {
+            let response = self.0.clone();
+            Box::pin(async move { Ok(response) })
+        }
+    }
+
+    fn batch_response(ids: &[u64]) -> Response {
+        Response::Batch(
+            ids.iter()
+                .map(|&id| {
+                    Output::Success(Success {
+                        jsonrpc: Some(Version::V2),
+                        result: Value::from(id),
+                        id: Id::Num(id),
+                    })
+                })
+                .collect(),
+        )
+    }
+
+    fn request() -> Request {
+        Request::Single(Call::MethodCall(MethodCall {
+            jsonrpc: Some(Version::V2),
+            method: ETH_BLOCK_NUMBER_METHOD.to_string(),
+            params: Params::Array(vec![]),
+            id: Id::Num(0),
+        }))
+    }
+
+    fn result(response: Response) -> Value {
+        match response {
+            Response::Single(Output::Success(success)) => success.result,
+            _ => panic!("unexpected response {response:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn should_fail_over_to_next_endpoint() {
+        let failing = StaticClient::err();
+        let healthy = StaticClient::ok("0x2");
+        let client = MultiEndpointClient::failover(vec![failing.clone(), healthy.clone()]);
+
+        let response = client.send_rpc_request(request()).await.unwrap();
+
+        assert_eq!(result(response), Value::from("0x2"));
+        assert_eq!(failing.calls(), 1);
+        assert_eq!(healthy.calls(), 1);
+    }
+
+    #[tokio::test]
+    async fn should_prefer_healthy_endpoints() {
+        let failing = StaticClient::err();
+        let healthy = StaticClient::ok("0x2");
+        let client = MultiEndpointClient::failover(vec![failing.clone(), healthy.clone()])
+            .with_health_policy(HealthPolicy {
+                max_consecutive_failures: 1,
+                cooldown: Duration::from_secs(60),
+            });
+
+        for _ in 0..3 {
+            client.send_rpc_request(request()).await.unwrap();
+        }
+
+        assert_eq!(failing.calls(), 1);
+        assert_eq!(healthy.calls(), 3);
+        assert_eq!(client.healthy_endpoints(), 1);
+    }
+
+    #[tokio::test]
+    async fn should_return_last_error_if_all_endpoints_fail() {
+        let client = MultiEndpointClient::failover(vec![StaticClient::err(), StaticClient::err()]);
+
+        let err = client.send_rpc_request(request()).await.unwrap_err();
+
+        assert_eq!(
+            err,
+            EthJsonRpcError::Transport("connection refused".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn should_return_response_when_quorum_is_reached() {
+        let client = MultiEndpointClient::quorum(
+            vec![
+                StaticClient::ok("0x1"),
+                StaticClient::ok("0x2"),
+                StaticClient::err(),
+                StaticClient::ok("0x2"),
+            ],
+            2,
+        )
+        .unwrap();
+
+        let response = client.send_rpc_request(request()).await.unwrap();
+
+        assert_eq!(result(response), Value::from("0x2"));
+    }
+
+    #[tokio::test]
+    async fn should_fail_when_quorum_is_not_reached() {
+        let client = MultiEndpointClient::quorum(
+            vec![
+                StaticClient::ok("0x1"),
+                StaticClient::ok("0x2"),
+                StaticClient::err(),
+            ],
+            2,
+        )
+        .unwrap();
+
+        let err = client.send_rpc_request(request()).await.unwrap_err();
+
+        assert_eq!(
+            err,
+            EthJsonRpcError::NoQuorum {
+                required: 2,
+                agreeing: 1,
+                failed: 1,
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn should_return_original_batch_response_when_quorum_is_reached() {
+        let client = MultiEndpointClient::quorum(
+            vec![
+                ResponseClient(batch_response(&[10, 2])),
+                ResponseClient(batch_response(&[10, 2])),
+            ],
+            2,
+        )
+        .unwrap();
+
+        let response = client.send_rpc_request(request()).await.unwrap();
+
+        // The batch is compared with its items sorted by id, but returned as is
+        assert_eq!(
+            normalize_response(response.clone()),
+            batch_response(&[2, 10])
+        );
+        assert_eq!(response, batch_response(&[10, 2]));
+    }
+
+    #[test]
+    fn should_reject_invalid_quorum() {
+        let endpoints = || vec![StaticClient::ok("0x1"), StaticClient::ok("0x1")];
+
+        assert!(matches!(
+            MultiEndpointClient::quorum(endpoints(), 0),
+            Err(EthJsonRpcError::InvalidConfiguration(_))
+        ));
+        assert!(matches!(
+            MultiEndpointClient::quorum(endpoints(), 3),
+            Err(EthJsonRpcError::InvalidConfiguration(_))
+        ));
+        assert!(matches!(
+            MultiEndpointClient::new(
+                endpoints(),
+                MultiEndpointStrategy::Quorum {
+                    endpoints: 3,
+                    threshold: 2,
+                },
+            ),
+            Err(EthJsonRpcError::InvalidConfiguration(_))
+        ));
+        assert!(MultiEndpointClient::quorum(endpoints(), 2).is_ok());
+    }
+}
@@ -0,0 +1,182 @@
+//! Waiting for the transactions sent with an [`EthJsonRpcClient`].
+//!
+//! The transaction status is polled over JSON-RPC by the [`PendingTransaction`] of the
+//! `did` crate, shared with the EVM canister client.
+
+use did::pending_transaction::TransactionStatusProvider;
+pub use did::pending_transaction::{
+    DEFAULT_DROPPED_AFTER_POLLS, DEFAULT_POLL_INTERVAL, DEFAULT_TIMEOUT,
+};
+use ethers_core::types::{BlockNumber, Transaction, TransactionReceipt, H256};
+use jsonrpc_core::{Id, Params};
+
+use crate::{
+    Client, EthJsonRpcClient, EthJsonRpcError, EthJsonRpcResult, ETH_GET_TRANSACTION_RECEIPT_METHOD,
+};
+
+/// A transaction sent with an [`EthJsonRpcClient`], which can be awaited for its receipt.
+pub type PendingTransaction<'a, C> =
+    did::pending_transaction::PendingTransaction<'a, EthJsonRpcClient<C>>;
+
+/// Errors returned while waiting for a [`PendingTransaction`].
+pub type PendingTransactionError =
+    did::pending_transaction::PendingTransactionError<EthJsonRpcError>;
+
+/// Result of waiting for a [`PendingTransaction`].
+pub type PendingTransactionResult<T> = Result<T, PendingTransactionError>;
+
+impl<C: Client> TransactionStatusProvider for EthJsonRpcClient<C> {
+    type Receipt = TransactionReceipt;
+    type Error = EthJsonRpcError;
+
+    async fn transaction_receipt(
+        &self,
+        tx_hash: &did::H256,
+    ) -> EthJsonRpcResult<Option<TransactionReceipt>> {
+        let tx_hash = H256::from(tx_hash.clone());
+        self.single_request(
+            ETH_GET_TRANSACTION_RECEIPT_METHOD.to_string(),
+            make_params_array!(tx_hash),
+            Id::Str(tx_hash.to_string()),
+        )
+        .await
+    }
+
+    fn receipt_block_number(receipt: &TransactionReceipt) -> Option<u64> {
+        receipt
+            .block_number
+            .map(|block_number| block_number.as_u64())
+    }
+
+    async fn transaction_sender(
+        &self,
+        tx_hash: &did::H256,
+    ) -> EthJsonRpcResult<Option<(did::H160, did::U256)>> {
+        match self.get_transaction_by_hash(tx_hash.clone().into()).await {
+            Ok(transaction) => Ok(Some((transaction.from.into(), transaction.nonce.into()))),
+            Err(EthJsonRpcError::NotFound(_)) => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+
+    async fn transaction_count(&self, address: &did::H160) -> EthJsonRpcResult<did::U256> {
+        self.get_transaction_count(address.clone().into(), BlockNumber::Latest)
+            .await
+            .map(did::U256::from)
+    }
+
+    async fn block_number(&self) -> EthJsonRpcResult<u64> {
+        self.get_block_number().await
+    }
+}
+
+impl<C: Client> EthJsonRpcClient<C> {
+    /// Sends raw transaction and returns a [`PendingTransaction`] to wait for its receipt.
+    ///
+    /// This is a separate method rather than the return value of `send_raw_transaction`,
+    /// which only needs the hash, because the pending transaction borrows the client to
+    /// poll it and requires the `pending-transaction` feature.
+    pub async fn send_pending_transaction(
+        &self,
+        transaction: Transaction,
+    ) -> EthJsonRpcResult<PendingTransaction<'_, C>> {
+        let sender = (transaction.from.into(), transaction.nonce.into());
+        let tx_hash = self.send_raw_transaction(transaction).await?;
+
+        Ok(PendingTransaction::new(self, tx_hash.into()).with_sender(sender.0, sender.1))
+    }
+
+    /// Sends raw transaction bytes and returns a [`PendingTransaction`] to wait for its receipt.
+    pub async fn send_pending_transaction_bytes(
+        &self,
+        transaction: &[u8],
+    ) -> EthJsonRpcResult<PendingTransaction<'_, C>> {
+        let tx_hash = self.send_raw_transaction_bytes(transaction).await?;
+
+        Ok(PendingTransaction::new(self, tx_hash.into()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::sync::Arc;
+
+    use ethers_core::types::{H160, U64};
+
+    use super::*;
+    use crate::mock::MockClient;
+
+    const TX_HASH: H256 = H256::repeat_byte(1);
+
+    fn transaction() -> Transaction {
+        Transaction {
+            hash: TX_HASH,
+            from: H160::repeat_byte(2),
+            nonce: 5.into(),
+            ..Default::default()
+        }
+    }
+
+    fn receipt(block_number: u64) -> TransactionReceipt {
+        TransactionReceipt {
+            transaction_hash: TX_HASH,
+            block_number: Some(block_number.into()),
+            status: Some(1.into()),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn should_send_and_confirm_transaction() {
+        // A new block is produced at each poll, the transaction is mined in block 10
+        let block_number = Arc::new(AtomicU64::new(8));
+        let mock = MockClient::new();
+        mock.on("eth_sendRawTransaction", TX_HASH)
+            .on("eth_getTransactionByHash", transaction())
+            .on("eth_getTransactionCount", U64::from(5))
+            .on_call("eth_getTransactionReceipt", {
+                let block_number = block_number.clone();
+                move |_| {
+                    let latest = block_number.fetch_add(1, Ordering::SeqCst) + 1;
+                    Ok((latest >= 10).then(|| receipt(10)))
+                }
+            })
+            .on_call("eth_blockNumber", move |_| {
+                Ok(U64::from(block_number.load(Ordering::SeqCst)))
+            });
+        let client = EthJsonRpcClient::new(mock.clone());
+
+        let receipt = client
+            .send_pending_transaction(transaction())
+            .await
+            .unwrap()
+            .with_confirmations(3)
+            .await
+            .unwrap();
+
+        assert_eq!(receipt.block_number, Some(10.into()));
+        mock.assert_called_times("eth_sendRawTransaction", 1);
+        mock.assert_called_times("eth_getTransactionReceipt", 4);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn should_report_transaction_not_found_by_the_node_as_dropped() {
+        let mock = MockClient::new();
+        mock.on("eth_getTransactionReceipt", None::<TransactionReceipt>)
+            .on("eth_getTransactionByHash", None::<Transaction>)
+            .on("eth_getTransactionCount", U64::from(5));
+        let client = EthJsonRpcClient::new(mock.clone());
+
+        let err = PendingTransaction::new(&client, TX_HASH.into())
+            .with_sender(H160::repeat_byte(2).into(), 5u64.into())
+            .await
+            .unwrap_err();
+
+        assert_eq!(err, PendingTransactionError::Dropped(TX_HASH.into()));
+        mock.assert_called_times(
+            "eth_getTransactionByHash",
+            DEFAULT_DROPPED_AFTER_POLLS as usize,
+        );
+    }
+}
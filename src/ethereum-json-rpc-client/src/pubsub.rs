@@ -0,0 +1,1086 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::future::Future;
+use std::marker::PhantomData;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use ethers_core::types::{Block, Log, H160, H256};
+use futures::future::BoxFuture;
+use futures::Stream;
+use jsonrpc_core::{Call, Id, MethodCall, Output, Params, Request, Response, Version};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::sync::{mpsc, oneshot};
+
+use crate::{Client, EthGetLogsParams, EthJsonRpcClient, EthJsonRpcError, EthJsonRpcResult};
+
+const ETH_SUBSCRIBE_METHOD: &str = "eth_subscribe";
+const ETH_UNSUBSCRIBE_METHOD: &str = "eth_unsubscribe";
+const ETH_SUBSCRIPTION_METHOD: &str = "eth_subscription";
+
+/// A client which supports `eth_subscribe` subscriptions.
+pub trait PubSubClient: Client {
+    /// Subscribes with the given `eth_subscribe` parameters.
+    ///
+    /// The subscription is cancelled when the returned stream is dropped.
+    fn subscribe<T: DeserializeOwned + Send + 'static>(
+        &self,
+        params: Params,
+    ) -> Pin<Box<dyn Future<Output = EthJsonRpcResult<Subscription<T>>> + Send>>;
+}
+
+impl<C: PubSubClient> EthJsonRpcClient<C> {
+    /// Subscribes to the headers of new blocks.
+    ///
+    /// The blocks yielded by the stream contain no transactions.
+    pub async fn subscribe_new_heads(&self) -> EthJsonRpcResult<Subscription<Block<H256>>> {
+        self.client
+            .subscribe(Params::Array(vec!["newHeads".into()]))
+            .await
+    }
+
+    /// Subscribes to the logs matching the given filter.
+    ///
+    /// Only the address and topics of the filter are used, as subscriptions
+    /// always deliver logs from new blocks.
+    pub async fn subscribe_logs(
+        &self,
+        params: EthGetLogsParams,
+    ) -> EthJsonRpcResult<Subscription<Log>> {
+        let filter = LogsSubscriptionFilter {
+            address: params.address,
+            topics: params.topics,
+        };
+
+        self.client
+            .subscribe(Params::Array(vec![
+                "logs".into(),
+                serde_json::to_value(filter)?,
+            ]))
+            .await
+    }
+}
+
+/// Filter of a `logs` subscription.
+#[derive(Serialize)]
+struct LogsSubscriptionFilter {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    address: Option<Vec<H160>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    topics: Option<Vec<Option<Vec<H256>>>>,
+}
+
+/// A stream of subscription notifications.
+///
+/// The stream ends when the subscription is closed by the client transport.
+pub struct Subscription<T> {
+    notifications: mpsc::UnboundedReceiver<EthJsonRpcResult<Value>>,
+    _guard: UnsubscribeGuard,
+    _item: PhantomData<fn() -> T>,
+}
+
+impl<T> Subscription<T> {
+    /// Creates a new subscription.
+    ///
+    /// # Arguments
+    /// * `notifications` - The receiver of the subscription notifications.
+    /// * `unsubscribe` - Called when the subscription is dropped.
+    pub(crate) fn new(
+        notifications: mpsc::UnboundedReceiver<EthJsonRpcResult<Value>>,
+        unsubscribe: impl FnOnce() + Send + 'static,
+    ) -> Self {
+        Self {
+            notifications,
+            _guard: UnsubscribeGuard(Some(Box::new(unsubscribe))),
+            _item: PhantomData,
+        }
+    }
+}
+
+impl<T: DeserializeOwned> Stream for Subscription<T> {
+    type Item = EthJsonRpcResult<T>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.notifications.poll_recv(cx).map(|notification| {
+            notification.map(|value| {
+                value.and_then(|value| {
+                    serde_json::from_value(value)
+                        .map_err(|e| EthJsonRpcError::Deserialization(e.to_string()))
+                })
+            })
+        })
+    }
+}
+
+struct UnsubscribeGuard(Option<Box<dyn FnOnce() + Send>>);
+
+impl Drop for UnsubscribeGuard {
+    fn drop(&mut self) {
+        if let Some(unsubscribe) = self.0.take() {
+            unsubscribe();
+        }
+    }
+}
+
+/// Connection settings of a [`TransportClient`].
+#[derive(Debug, Clone)]
+pub struct ConnectionConfig {
+    /// Delay before trying to reconnect after the connection has been lost.
+    pub reconnect_delay: Duration,
+    /// Maximum number of consecutive failed reconnection attempts.
+    /// `None` means that the client never gives up.
+    pub max_reconnect_attempts: Option<usize>,
+    /// Maximum time to wait for the response to a request.
+    pub request_timeout: Duration,
+}
+
+impl Default for ConnectionConfig {
+    fn default() -> Self {
+        Self {
+            reconnect_delay: Duration::from_secs(1),
+            max_reconnect_attempts: None,
+            request_timeout: Duration::from_secs(30),
+        }
+    }
+}
+
+impl ConnectionConfig {
+    /// Sets the delay before trying to reconnect.
+    pub fn with_reconnect_delay(mut self, reconnect_delay: Duration) -> Self {
+        self.reconnect_delay = reconnect_delay;
+        self
+    }
+
+    /// Sets the maximum number of consecutive failed reconnection attempts.
+    pub fn with_max_reconnect_attempts(mut self, max_reconnect_attempts: Option<usize>) -> Self {
+        self.max_reconnect_attempts = max_reconnect_attempts;
+        self
+    }
+
+    /// Sets the maximum time to wait for the response to a request.
+    pub fn with_request_timeout(mut self, request_timeout: Duration) -> Self {
+        self.request_timeout = request_timeout;
+        self
+    }
+}
+
+/// Opens the connections of a [`TransportClient`].
+///
+/// Displayed as the endpoint in the logs.
+pub trait Transport: fmt::Display + Send + Sync + 'static {
+    /// An open connection.
+    type Connection: Connection;
+
+    /// Name of the client in the logs and in the errors, e.g. `WebSocketClient`.
+    const NAME: &'static str;
+
+    /// Opens a new connection to the endpoint.
+    fn connect(&self) -> BoxFuture<'_, EthJsonRpcResult<Self::Connection>>;
+}
+
+/// A connection exchanging JSON-RPC messages.
+pub trait Connection: Send + 'static {
+    /// Sends a message.
+    fn send(&mut self, message: String) -> BoxFuture<'_, EthJsonRpcResult<()>>;
+
+    /// Receives the next message, or `None` once the connection is closed.
+    ///
+    /// Must be cancel safe, as it is raced against the commands of the client.
+    fn receive(&mut self) -> BoxFuture<'_, Option<EthJsonRpcResult<String>>>;
+
+    /// Closes the connection.
+    fn close(&mut self) -> BoxFuture<'_, ()>;
+}
+
+/// A JSON-RPC client over a persistent connection, supporting subscriptions.
+///
+/// The connection is owned by a background task which reconnects when the
+/// connection is lost and renews the active subscriptions. Requests in flight
+/// when the connection is lost fail with a transport error.
+/// Requests are multiplexed on the connection by rewriting their ids, so
+/// responses can arrive in any order.
+pub struct TransportClient<T: Transport> {
+    commands: mpsc::UnboundedSender<Command>,
+    next_subscription_id: Arc<AtomicU64>,
+    request_timeout: Duration,
+    _transport: PhantomData<fn() -> T>,
+}
+
+impl<T: Transport> Clone for TransportClient<T> {
+    fn clone(&self) -> Self {
+        Self {
+            commands: self.commands.clone(),
+            next_subscription_id: self.next_subscription_id.clone(),
+            request_timeout: self.request_timeout,
+            _transport: PhantomData,
+        }
+    }
+}
+
+impl<T: Transport> TransportClient<T> {
+    /// Connects with the given transport.
+    ///
+    /// Must be called within a tokio runtime, which runs the connection task.
+    ///
+    /// # Arguments
+    /// * `transport` - Opens the connections to the endpoint.
+    /// * `config` - The connection settings.
+    pub async fn connect_with_transport(
+        transport: T,
+        config: ConnectionConfig,
+    ) -> EthJsonRpcResult<Self> {
+        let connection = transport.connect().await?;
+
+        let (commands, receiver) = mpsc::unbounded_channel();
+        let request_timeout = config.request_timeout;
+        tokio::spawn(run(transport, config, connection, receiver));
+
+        Ok(Self {
+            commands,
+            next_subscription_id: Arc::new(AtomicU64::new(0)),
+            request_timeout,
+            _transport: PhantomData,
+        })
+    }
+
+    fn send_command(&self, command: Command) -> EthJsonRpcResult<()> {
+        self.commands
+            .send(command)
+            .map_err(|_| EthJsonRpcError::Transport(format!("{} connection is closed", T::NAME)))
+    }
+}
+
+impl<T: Transport> Client for TransportClient<T> {
+    fn send_rpc_request(
+        &self,
+        request: Request,
+    ) -> Pin<Box<dyn Future<Output = EthJsonRpcResult<Response>> + Send>> {
+        let client = self.clone();
+
+        Box::pin(async move {
+            log::trace!("{} - sending request {request:?}", T::NAME);
+
+            let (response, receiver) = oneshot::channel();
+            client.send_command(Command::Request { request, response })?;
+
+            let response = wait_for(receiver, client.request_timeout).await?;
+            log::trace!("{} - response: {response:?}", T::NAME);
+
+            Ok(response)
+        })
+    }
+}
+
+impl<T: Transport> PubSubClient for TransportClient<T> {
+    fn subscribe<R: DeserializeOwned + Send + 'static>(
+        &self,
+        params: Params,
+    ) -> Pin<Box<dyn Future<Output = EthJsonRpcResult<Subscription<R>>> + Send>> {
+        let client = self.clone();
+
+        Box::pin(async move {
+            let id = client.next_subscription_id.fetch_add(1, Ordering::Relaxed);
+            let (notifications, receiver) = mpsc::unbounded_channel();
+            let (ready, ready_receiver) = oneshot::channel();
+            client.send_command(Command::Subscribe {
+                id,
+                params,
+                notifications,
+                ready,
+            })?;
+
+            // Created before waiting, so that the subscription is cancelled
+            // if this future is dropped.
+            let commands = client.commands.clone();
+            let subscription = Subscription::new(receiver, move || {
+                let _ = commands.send(Command::Unsubscribe { id });
+            });
+
+            wait_for(ready_receiver, client.request_timeout).await?;
+
+            Ok(subscription)
+        })
+    }
+}
+
+/// Runs the connection until all the client handles and subscriptions are dropped
+/// or the reconnection attempts are exhausted.
+async fn run<T: Transport>(
+    transport: T,
+    config: ConnectionConfig,
+    connection: T::Connection,
+    mut commands: mpsc::UnboundedReceiver<Command>,
+) {
+    let mut state = ConnectionState::default();
+    let mut connection = Some(connection);
+    let mut failed_attempts = 0;
+
+    loop {
+        if let Some(connection) = connection.take() {
+            failed_attempts = 0;
+            if serve::<T>(connection, &mut state, &mut commands).await {
+                return;
+            }
+            state.on_disconnected();
+            log::warn!("{} - connection to {transport} lost", T::NAME);
+        }
+
+        if config
+            .max_reconnect_attempts
+            .is_some_and(|max_attempts| failed_attempts >= max_attempts)
+        {
+            log::warn!("{} - giving up reconnecting to {transport}", T::NAME);
+            return;
+        }
+
+        if wait_disconnected(config.reconnect_delay, &mut state, &mut commands).await {
+            return;
+        }
+
+        match transport.connect().await {
+            Ok(new_connection) => {
+                log::trace!("{} - reconnected to {transport}", T::NAME);
+                connection = Some(new_connection);
+            }
+            Err(e) => {
+                failed_attempts += 1;
+                log::warn!("{} - failed to reconnect to {transport}: {e}", T::NAME);
+            }
+        }
+    }
+}
+
+/// Serves the commands on the given connection until it is closed.
+///
+/// Returns true if there are no more client handles.
+async fn serve<T: Transport>(
+    mut connection: T::Connection,
+    state: &mut ConnectionState,
+    commands: &mut mpsc::UnboundedReceiver<Command>,
+) -> bool {
+    for message in state.on_connected() {
+        if let Err(e) = connection.send(message).await {
+            log::warn!("{} - failed to renew subscription: {e}", T::NAME);
+            return false;
+        }
+    }
+
+    loop {
+        let message = tokio::select! {
+            command = commands.recv() => {
+                let Some(command) = command else {
+                    connection.close().await;
+                    return true;
+                };
+                state.handle_command(command, true)
+            }
+            message = connection.receive() => match message {
+                Some(Ok(message)) => state.handle_message(&message),
+                Some(Err(e)) => {
+                    log::warn!("{} - failed to receive message: {e}", T::NAME);
+                    return false;
+                }
+                None => return false,
+            }
+        };
+
+        if let Some(message) = message {
+            if let Err(e) = connection.send(message).await {
+                log::warn!("{} - failed to send message: {e}", T::NAME);
+                return false;
+            }
+        }
+    }
+}
+
+/// Handles the commands received while waiting to reconnect.
+///
+/// Returns true if there are no more client handles.
+async fn wait_disconnected(
+    delay: Duration,
+    state: &mut ConnectionState,
+    commands: &mut mpsc::UnboundedReceiver<Command>,
+) -> bool {
+    let sleep = tokio::time::sleep(delay);
+    tokio::pin!(sleep);
+
+    loop {
+        tokio::select! {
+            _ = &mut sleep => return false,
+            command = commands.recv() => match command {
+                Some(command) => {
+                    state.handle_command(command, false);
+                }
+                None => return true,
+            }
+        }
+    }
+}
+
+/// Commands sent by client handles to the task owning the connection.
+enum Command {
+    Request {
+        request: Request,
+        response: oneshot::Sender<EthJsonRpcResult<Response>>,
+    },
+    Subscribe {
+        id: u64,
+        params: Params,
+        notifications: mpsc::UnboundedSender<EthJsonRpcResult<Value>>,
+        ready: oneshot::Sender<EthJsonRpcResult<()>>,
+    },
+    Unsubscribe {
+        id: u64,
+    },
+}
+
+/// Waits for the response of the task owning the connection.
+async fn wait_for<T>(
+    receiver: oneshot::Receiver<EthJsonRpcResult<T>>,
+    timeout: Duration,
+) -> EthJsonRpcResult<T> {
+    match tokio::time::timeout(timeout, receiver).await {
+        Ok(Ok(result)) => result,
+        Ok(Err(_)) => Err(EthJsonRpcError::Transport(
+            "connection is closed".to_string(),
+        )),
+        Err(_) => Err(EthJsonRpcError::Transport(format!(
+            "no response received in {timeout:?}"
+        ))),
+    }
+}
+
+/// A request waiting for its response.
+enum Pending {
+    Request {
+        /// Ids assigned to the calls, with the ids given by the caller.
+        ids: Vec<(u64, Id)>,
+        batch: bool,
+        response: oneshot::Sender<EthJsonRpcResult<Response>>,
+    },
+    Subscribe {
+        subscription: u64,
+    },
+    Unsubscribe,
+}
+
+struct ActiveSubscription {
+    params: Params,
+    notifications: mpsc::UnboundedSender<EthJsonRpcResult<Value>>,
+    /// Notified when the first `eth_subscribe` call completes.
+    ready: Option<oneshot::Sender<EthJsonRpcResult<()>>>,
+    server_id: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct SubscriptionNotification {
+    params: SubscriptionNotificationParams,
+}
+
+#[derive(Deserialize)]
+struct SubscriptionNotificationParams {
+    subscription: Value,
+    result: Value,
+}
+
+/// Transport-independent state of a JSON-RPC connection supporting subscriptions.
+///
+/// Request ids are rewritten so that concurrent requests never collide, and
+/// subscriptions are kept across connections so that they can be renewed
+/// after a reconnection.
+#[derive(Default)]
+struct ConnectionState {
+    next_request_id: u64,
+    pending: HashMap<u64, Pending>,
+    /// Maps the ids of batched calls to the key of their pending request.
+    batch_owners: HashMap<u64, u64>,
+    subscriptions: HashMap<u64, ActiveSubscription>,
+    /// Maps the subscription ids assigned by the server to the local ones.
+    server_ids: HashMap<String, u64>,
+}
+
+impl ConnectionState {
+    /// Handles a command, returning the message to send if any.
+    ///
+    /// Requests received while disconnected fail immediately, while
+    /// subscriptions are established on the next connection.
+    fn handle_command(&mut self, command: Command, connected: bool) -> Option<String> {
+        match command {
+            Command::Request { request, response } => {
+                if !connected {
+                    let _ =
+                        response.send(Err(EthJsonRpcError::Transport("not connected".to_string())));
+                    return None;
+                }
+                self.prune_abandoned_requests();
+                self.request(request, response)
+            }
+            Command::Subscribe {
+                id,
+                params,
+                notifications,
+                ready,
+            } => {
+                self.subscriptions.insert(
+                    id,
+                    ActiveSubscription {
+                        params,
+                        notifications,
+                        ready: Some(ready),
+                        server_id: None,
+                    },
+                );
+                connected.then(|| self.subscribe(id)).flatten()
+            }
+            Command::Unsubscribe { id } => {
+                // If the subscription has not been acknowledged yet, its pending
+                // `eth_subscribe` call unsubscribes once the server id is known.
+                let server_id = self.subscriptions.remove(&id)?.server_id?;
+                self.server_ids.remove(&server_id);
+                connected.then(|| self.unsubscribe(Value::String(server_id)))
+            }
+        }
+    }
+
+    /// Returns the messages renewing the active subscriptions on a new connection.
+    fn on_connected(&mut self) -> Vec<String> {
+        let ids = self.subscriptions.keys().copied().collect::<Vec<_>>();
+        ids.into_iter()
+            .filter_map(|id| self.subscribe(id))
+            .collect()
+    }
+
+    /// Fails the pending requests after the connection has been lost.
+    fn on_disconnected(&mut self) {
+        for (_, pending) in self.pending.drain() {
+            if let Pending::Request { response, .. } = pending {
+                let _ = response.send(Err(EthJsonRpcError::Transport(
+                    "connection closed".to_string(),
+                )));
+            }
+        }
+        self.batch_owners.clear();
+        self.server_ids.clear();
+        for subscription in self.subscriptions.values_mut() {
+            subscription.server_id = None;
+        }
+    }
+
+    /// Handles a message received from the server, returning the message to send if any.
+    fn handle_message(&mut self, message: &str) -> Option<String> {
+        let value = match serde_json::from_str::<Value>(message) {
+            Ok(value) => value,
+            Err(e) => {
+                log::warn!("ConnectionState - failed to parse message: {e}");
+                return None;
+            }
+        };
+
+        if value.get("method").and_then(Value::as_str) == Some(ETH_SUBSCRIPTION_METHOD) {
+            match serde_json::from_value::<SubscriptionNotification>(value) {
+                Ok(notification) => self.handle_notification(notification.params),
+                Err(e) => log::warn!("ConnectionState - invalid subscription notification: {e}"),
+            }
+            return None;
+        }
+
+        match serde_json::from_value::<Response>(value) {
+            Ok(Response::Single(output)) => self.handle_output(output),
+            Ok(Response::Batch(outputs)) => {
+                self.handle_batch(outputs);
+                None
+            }
+            Err(e) => {
+                log::warn!("ConnectionState - invalid response: {e}");
+                None
+            }
+        }
+    }
+
+    /// Forgets the requests which are no longer awaited, e.g. after a timeout,
+    /// so that their entries do not pile up while the server does not answer.
+    fn prune_abandoned_requests(&mut self) {
+        self.pending.retain(|_, pending| {
+            !matches!(pending, Pending::Request { response, .. } if response.is_closed())
+        });
+        let pending = &self.pending;
+        self.batch_owners.retain(|_, key| pending.contains_key(key));
+    }
+
+    fn next_request_id(&mut self) -> u64 {
+        self.next_request_id += 1;
+        self.next_request_id
+    }
+
+    fn request(
+        &mut self,
+        request: Request,
+        response: oneshot::Sender<EthJsonRpcResult<Response>>,
+    ) -> Option<String> {
+        let (mut calls, batch) = match request {
+            Request::Single(call) => (vec![call], false),
+            Request::Batch(calls) => (calls, true),
+        };
+
+        let mut ids = Vec::new();
+        for call in &mut calls {
+            if let Call::MethodCall(call) = call {
+                let id = self.next_request_id();
+                ids.push((id, std::mem::replace(&mut call.id, Id::Num(id))));
+            }
+        }
+
+        let Some(&(key, _)) = ids.first() else {
+            let _ = response.send(Err(EthJsonRpcError::UnexpectedResponse(
+                "requests without method calls have no response".to_string(),
+            )));
+            return None;
+        };
+
+        let request = if batch {
+            Request::Batch(calls)
+        } else {
+            Request::Single(calls.remove(0))
+        };
+        let message = match serde_json::to_string(&request) {
+            Ok(message) => message,
+            Err(e) => {
+                let _ = response.send(Err(e.into()));
+                return None;
+            }
+        };
+
+        if batch {
+            self.batch_owners
+                .extend(ids.iter().map(|(id, _)| (*id, key)));
+        }
+        self.pending.insert(
+            key,
+            Pending::Request {
+                ids,
+                batch,
+                response,
+            },
+        );
+
+        Some(message)
+    }
+
+    fn subscribe(&mut self, subscription: u64) -> Option<String> {
+        let params = self.subscriptions.get(&subscription)?.params.clone();
+        let request_id = self.next_request_id();
+        self.pending
+            .insert(request_id, Pending::Subscribe { subscription });
+
+        Some(method_call(ETH_SUBSCRIBE_METHOD, params, request_id))
+    }
+
+    fn unsubscribe(&mut self, server_id: Value) -> String {
+        let request_id = self.next_request_id();
+        self.pending.insert(request_id, Pending::Unsubscribe);
+
+        method_call(
+            ETH_UNSUBSCRIBE_METHOD,
+            Params::Array(vec![server_id]),
+            request_id,
+        )
+    }
+
+    fn handle_output(&mut self, output: Output) -> Option<String> {
+        let Some(id) = numeric_id(output_id(&output)) else {
+            log::warn!(
+                "ConnectionState - response with unknown id: {:?}",
+                output_id(&output)
+            );
+            return None;
+        };
+
+        if let Some(key) = self.batch_owners.get(&id).copied() {
+            self.handle_batch_response(key, vec![output]);
+            return None;
+        }
+
+        match self.pending.remove(&id) {
+            Some(Pending::Request { ids, response, .. }) => {
+                let mut output = output;
+                if let Some((_, original)) = ids.into_iter().next() {
+                    set_output_id(&mut output, original);
+                }
+                let _ = response.send(Ok(Response::Single(output)));
+            }
+            Some(Pending::Subscribe { subscription }) => {
+                return self.handle_subscribe_response(subscription, output);
+            }
+            Some(Pending::Unsubscribe) => {}
+            None => log::warn!("ConnectionState - response to unknown request {id}"),
+        }
+        None
+    }
+
+    fn handle_batch(&mut self, outputs: Vec<Output>) {
+        let key = outputs
+            .iter()
+            .find_map(|output| numeric_id(output_id(output)))
+            .and_then(|id| self.batch_owners.get(&id).copied());
+
+        match key {
+            Some(key) => self.handle_batch_response(key, outputs),
+            None => log::warn!("ConnectionState - batch response to unknown request"),
+        }
+    }
+
+    fn handle_batch_response(&mut self, key: u64, mut outputs: Vec<Output>) {
+        let Some(Pending::Request {
+            ids,
+            batch,
+            response,
+        }) = self.pending.remove(&key)
+        else {
+            return;
+        };
+
+        for (id, _) in &ids {
+            self.batch_owners.remove(id);
+        }
+
+        let originals = ids.into_iter().collect::<HashMap<_, _>>();
+        for output in &mut outputs {
+            if let Some(original) = numeric_id(output_id(output)).and_then(|id| originals.get(&id))
+            {
+                set_output_id(output, original.clone());
+            }
+        }
+
+        let response_value = if batch || outputs.len() != 1 {
+            Response::Batch(outputs)
+        } else {
+            Response::Single(outputs.remove(0))
+        };
+        let _ = response.send(Ok(response_value));
+    }
+
+    /// Returns the `eth_unsubscribe` message to send if the subscription
+    /// has been cancelled before being acknowledged.
+    fn handle_subscribe_response(&mut self, id: u64, output: Output) -> Option<String> {
+        let Some(subscription) = self.subscriptions.get_mut(&id) else {
+            return match output {
+                Output::Success(success) => {
+                    log::trace!("ConnectionState - subscription {id} cancelled before ack");
+                    Some(self.unsubscribe(success.result))
+                }
+                Output::Failure(_) => None,
+            };
+        };
+
+        match output {
+            Output::Success(success) => {
+                let server_id = subscription_key(&success.result);
+                subscription.server_id = Some(server_id.clone());
+                if let Some(ready) = subscription.ready.take() {
+                    let _ = ready.send(Ok(()));
+                }
+                self.server_ids.insert(server_id, id);
+            }
+            Output::Failure(failure) => {
+                let subscription = self.subscriptions.remove(&id)?;
+                match subscription.ready {
+                    Some(ready) => {
+                        let _ = ready.send(Err(failure.error.into()));
+                    }
+                    None => {
+                        log::warn!("ConnectionState - failed to renew subscription {id}");
+                        let _ = subscription.notifications.send(Err(failure.error.into()));
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    fn handle_notification(&mut self, params: SubscriptionNotificationParams) {
+        let server_id = subscription_key(&params.subscription);
+        let Some(id) = self.server_ids.get(&server_id).copied() else {
+            log::trace!("ConnectionState - notification for unknown subscription {server_id}");
+            return;
+        };
+
+        let Some(subscription) = self.subscriptions.get(&id) else {
+            return;
+        };
+        if subscription.notifications.send(Ok(params.result)).is_err() {
+            // The stream has been dropped, the unsubscribe command is on its way.
+            log::trace!("ConnectionState - subscription {id} has been dropped");
+        }
+    }
+}
+
+fn method_call(method: &str, params: Params, id: u64) -> String {
+    let request = Request::Single(Call::MethodCall(MethodCall {
+        jsonrpc: Some(Version::V2),
+        method: method.to_string(),
+        params,
+        id: Id::Num(id),
+    }));
+
+    serde_json::to_string(&request).expect("method calls are always serializable")
+}
+
+fn output_id(output: &Output) -> &Id {
+    match output {
+        Output::Success(success) => &success.id,
+        Output::Failure(failure) => &failure.id,
+    }
+}
+
+fn set_output_id(output: &mut Output, id: Id) {
+    match output {
+        Output::Success(success) => success.id = id,
+        Output::Failure(failure) => failure.id = id,
+    }
+}
+
+fn numeric_id(id: &Id) -> Option<u64> {
+    match id {
+        Id::Num(id) => Some(*id),
+        _ => None,
+    }
+}
+
+/// Servers may use either strings or numbers as subscription ids.
+fn subscription_key(id: &Value) -> String {
+    match id {
+        Value::String(id) => id.clone(),
+        id => id.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::StreamExt;
+
+    use super::*;
+
+    fn request(method: &str, id: Id) -> Request {
+        Request::Single(Call::MethodCall(MethodCall {
+            jsonrpc: Some(Version::V2),
+            method: method.to_string(),
+            params: Params::Array(vec![]),
+            id,
+        }))
+    }
+
+    fn sent_id(message: &str) -> u64 {
+        let value: Value = serde_json::from_str(message).unwrap();
+        value["id"].as_u64().unwrap()
+    }
+
+    #[tokio::test]
+    async fn should_restore_request_ids() {
+        let mut state = ConnectionState::default();
+        let (first_tx, first_rx) = oneshot::channel();
+        let (second_tx, second_rx) = oneshot::channel();
+
+        let id = Id::Str("eth_blockNumber".to_string());
+        let first = state
+            .handle_command(
+                Command::Request {
+                    request: request("eth_blockNumber", id.clone()),
+                    response: first_tx,
+                },
+                true,
+            )
+            .unwrap();
+        let second = state
+            .handle_command(
+                Command::Request {
+                    request: request("eth_blockNumber", id.clone()),
+                    response: second_tx,
+                },
+                true,
+            )
+            .unwrap();
+        assert_ne!(sent_id(&first), sent_id(&second));
+
+        state.handle_message(&format!(
+            r#"{{"jsonrpc":"2.0","result":"0x2","id":{}}}"#,
+            sent_id(&second)
+        ));
+        state.handle_message(&format!(
+            r#"{{"jsonrpc":"2.0","result":"0x1","id":{}}}"#,
+            sent_id(&first)
+        ));
+
+        let Response::Single(Output::Success(first)) = first_rx.await.unwrap().unwrap() else {
+            panic!("unexpected response");
+        };
+        assert_eq!(first.id, id);
+        assert_eq!(first.result, Value::from("0x1"));
+
+        let Response::Single(Output::Success(second)) = second_rx.await.unwrap().unwrap() else {
+            panic!("unexpected response");
+        };
+        assert_eq!(second.id, id);
+        assert_eq!(second.result, Value::from("0x2"));
+    }
+
+    #[tokio::test]
+    async fn should_fail_pending_requests_on_disconnection() {
+        let mut state = ConnectionState::default();
+        let (tx, rx) = oneshot::channel();
+        state.handle_command(
+            Command::Request {
+                request: request("eth_chainId", Id::Num(1)),
+                response: tx,
+            },
+            true,
+        );
+
+        state.on_disconnected();
+
+        assert!(matches!(
+            rx.await.unwrap(),
+            Err(EthJsonRpcError::Transport(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn should_forget_requests_no_longer_awaited() {
+        let mut state = ConnectionState::default();
+        let (abandoned_tx, abandoned_rx) = oneshot::channel();
+        let batch = Request::Batch(vec![
+            Call::MethodCall(MethodCall {
+                jsonrpc: Some(Version::V2),
+                method: "eth_chainId".to_string(),
+                params: Params::Array(vec![]),
+                id: Id::Num(1),
+            }),
+            Call::MethodCall(MethodCall {
+                jsonrpc: Some(Version::V2),
+                method: "eth_blockNumber".to_string(),
+                params: Params::Array(vec![]),
+                id: Id::Num(2),
+            }),
+        ]);
+        state.handle_command(
+            Command::Request {
+                request: batch,
+                response: abandoned_tx,
+            },
+            true,
+        );
+        assert_eq!(state.batch_owners.len(), 2);
+
+        // The caller timed out
+        drop(abandoned_rx);
+        let (tx, rx) = oneshot::channel();
+        let message = state
+            .handle_command(
+                Command::Request {
+                    request: request("eth_chainId", Id::Num(3)),
+                    response: tx,
+                },
+                true,
+            )
+            .unwrap();
+
+        assert_eq!(state.pending.len(), 1);
+        assert!(state.batch_owners.is_empty());
+
+        state.handle_message(&format!(
+            r#"{{"jsonrpc":"2.0","result":"0x1","id":{}}}"#,
+            sent_id(&message)
+        ));
+        assert!(rx.await.unwrap().is_ok());
+    }
+
+    #[tokio::test]
+    async fn should_renew_subscriptions_on_reconnection() {
+        let mut state = ConnectionState::default();
+        let (notifications_tx, notifications_rx) = mpsc::unbounded_channel();
+        let (ready_tx, ready_rx) = oneshot::channel();
+        let mut subscription = Subscription::<u64>::new(notifications_rx, || {});
+
+        let message = state
+            .handle_command(
+                Command::Subscribe {
+                    id: 1,
+                    params: Params::Array(vec!["newHeads".into()]),
+                    notifications: notifications_tx,
+                    ready: ready_tx,
+                },
+                true,
+            )
+            .unwrap();
+        state.handle_message(&format!(
+            r#"{{"jsonrpc":"2.0","result":"0xa","id":{}}}"#,
+            sent_id(&message)
+        ));
+        ready_rx.await.unwrap().unwrap();
+
+        state.handle_message(
+            r#"{"jsonrpc":"2.0","method":"eth_subscription","params":{"subscription":"0xa","result":1}}"#,
+        );
+        assert_eq!(subscription.next().await.unwrap().unwrap(), 1);
+
+        state.on_disconnected();
+        let messages = state.on_connected();
+        assert_eq!(messages.len(), 1);
+        state.handle_message(&format!(
+            r#"{{"jsonrpc":"2.0","result":7,"id":{}}}"#,
+            sent_id(&messages[0])
+        ));
+
+        // notifications for the old subscription id are ignored
+        state.handle_message(
+            r#"{"jsonrpc":"2.0","method":"eth_subscription","params":{"subscription":"0xa","result":2}}"#,
+        );
+        state.handle_message(
+            r#"{"jsonrpc":"2.0","method":"eth_subscription","params":{"subscription":7,"result":3}}"#,
+        );
+        assert_eq!(subscription.next().await.unwrap().unwrap(), 3);
+    }
+
+    #[tokio::test]
+    async fn should_unsubscribe_when_cancelled_before_ack() {
+        let mut state = ConnectionState::default();
+        let (notifications_tx, _notifications_rx) = mpsc::unbounded_channel();
+        let (ready_tx, _ready_rx) = oneshot::channel();
+
+        let message = state
+            .handle_command(
+                Command::Subscribe {
+                    id: 1,
+                    params: Params::Array(vec!["newHeads".into()]),
+                    notifications: notifications_tx,
+                    ready: ready_tx,
+                },
+                true,
+            )
+            .unwrap();
+        assert!(state
+            .handle_command(Command::Unsubscribe { id: 1 }, true)
+            .is_none());
+
+        let unsubscribe = state
+            .handle_message(&format!(
+                r#"{{"jsonrpc":"2.0","result":"0xa","id":{}}}"#,
+                sent_id(&message)
+            ))
+            .unwrap();
+        let unsubscribe: Value = serde_json::from_str(&unsubscribe).unwrap();
+        assert_eq!(unsubscribe["method"], ETH_UNSUBSCRIBE_METHOD);
+        assert_eq!(unsubscribe["params"], serde_json::json!(["0xa"]));
+
+        assert!(state
+            .handle_message(&format!(
+                r#"{{"jsonrpc":"2.0","result":true,"id":{}}}"#,
+                unsubscribe["id"]
+            ))
+            .is_none());
+    }
+}
@@ -0,0 +1,279 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::Duration;
+
+use jsonrpc_core::{Request, Response};
+use tokio::sync::{Mutex, Semaphore};
+use tokio::time::Instant;
+
+use crate::{Client, EthJsonRpcResult};
+
+/// Limits of the [`RateLimitedClient`]. All the limits are disabled by default.
+#[derive(Debug, Clone, Default)]
+pub struct RateLimits {
+    /// Maximum number of requests sent per second, a batch counting as one request.
+    pub requests_per_second: Option<u32>,
+    /// Maximum number of calls sent per second, counting every call of a batch.
+    pub batch_items_per_second: Option<u32>,
+    /// Maximum number of requests waiting for a response at the same time.
+    pub max_concurrent_requests: Option<usize>,
+}
+
+impl RateLimits {
+    /// Sets the maximum number of requests sent per second.
+    pub fn with_requests_per_second(mut self, requests_per_second: u32) -> Self {
+        self.requests_per_second = Some(requests_per_second);
+        self
+    }
+
+    /// Sets the maximum number of calls sent per second, counting every call of a batch.
+    pub fn with_batch_items_per_second(mut self, batch_items_per_second: u32) -> Self {
+        self.batch_items_per_second = Some(batch_items_per_second);
+        self
+    }
+
+    /// Sets the maximum number of requests waiting for a response at the same time.
+    pub fn with_max_concurrent_requests(mut self, max_concurrent_requests: usize) -> Self {
+        self.max_concurrent_requests = Some(max_concurrent_requests);
+        self
+    }
+}
+
+/// A token bucket which allows bursts of up to one second of traffic.
+#[derive(Debug)]
+struct TokenBucket {
+    rate: f64,
+    tokens: f64,
+    updated_at: Instant,
+}
+
+impl TokenBucket {
+    fn new(rate: u32) -> Self {
+        let rate = f64::from(rate.max(1));
+        Self {
+            rate,
+            tokens: rate,
+            updated_at: Instant::now(),
+        }
+    }
+
+    /// Waits until the tokens are available and takes them.
+    ///
+    /// Requests larger than the bucket capacity are let through when the bucket
+    /// is full, leaving it in debt, so that they do not wait forever.
+    async fn acquire(&mut self, tokens: usize) {
+        let tokens = tokens as f64;
+        let required = tokens.min(self.rate);
+
+        self.refill();
+        if self.tokens < required {
+            let delay = Duration::from_secs_f64((required - self.tokens) / self.rate);
+            tokio::time::sleep(delay).await;
+            self.refill();
+        }
+
+        self.tokens -= tokens;
+    }
+
+    fn refill(&mut self) {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.updated_at).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.rate).min(self.rate);
+        self.updated_at = now;
+    }
+}
+
+/// A client which limits the rate and the concurrency of the requests of the inner client.
+///
+/// The limits are shared by all the clones of the client, so that a single instance
+/// can be used by all the jobs talking to the same endpoint.
+/// Requests waiting for the rate limits are served in order.
+#[derive(Clone)]
+pub struct RateLimitedClient<C: Client> {
+    inner: C,
+    limits: Arc<RateLimits>,
+    requests: Option<Arc<Mutex<TokenBucket>>>,
+    batch_items: Option<Arc<Mutex<TokenBucket>>>,
+    concurrency: Option<Arc<Semaphore>>,
+}
+
+impl<C: Client> RateLimitedClient<C> {
+    /// Creates a new client.
+    ///
+    /// # Arguments
+    /// * `inner` - The client used to send the requests.
+    /// * `limits` - The limits applied to the requests.
+    pub fn new(inner: C, limits: RateLimits) -> Self {
+        let bucket =
+            |rate: Option<u32>| rate.map(|rate| Arc::new(Mutex::new(TokenBucket::new(rate))));
+
+        Self {
+            inner,
+            requests: bucket(limits.requests_per_second),
+            batch_items: bucket(limits.batch_items_per_second),
+            concurrency: limits
+                .max_concurrent_requests
+                .map(|permits| Arc::new(Semaphore::new(permits.max(1)))),
+            limits: Arc::new(limits),
+        }
+    }
+
+    /// Returns the limits applied to the requests.
+    pub fn limits(&self) -> &RateLimits {
+        &self.limits
+    }
+
+    /// Returns the inner client.
+    pub fn inner(&self) -> &C {
+        &self.inner
+    }
+}
+
+impl<C: Client + 'static> Client for RateLimitedClient<C> {
+    fn send_rpc_request(
+        &self,
+        request: Request,
+    ) -> Pin<Box<dyn Future<Output = EthJsonRpcResult<Response>> + Send>> {
+        let client = self.clone();
+
+        Box::pin(async move {
+            let _permit = match &client.concurrency {
+                Some(semaphore) => Some(
+                    semaphore
+                        .clone()
+                        .acquire_owned()
+                        .await
+                        .expect("the semaphore is never closed"),
+                ),
+                None => None,
+            };
+
+            if let Some(requests) = &client.requests {
+                requests.lock().await.acquire(1).await;
+            }
+
+            if let Some(batch_items) = &client.batch_items {
+                let items = match &request {
+                    Request::Single(_) => 1,
+                    Request::Batch(calls) => calls.len(),
+                };
+                batch_items.lock().await.acquire(items).await;
+            }
+
+            log::trace!("RateLimitedClient - sending request");
+            client.inner.send_rpc_request(request).await
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use jsonrpc_core::{Call, Id, MethodCall, Output, Params, Success, Version};
+
+    use super::*;
+
+    /// Takes 100 ms to answer and keeps track of the concurrent requests.
+    #[derive(Clone, Default)]
+    struct SlowClient {
+        requests: Arc<AtomicUsize>,
+        in_flight: Arc<AtomicUsize>,
+        max_in_flight: Arc<AtomicUsize>,
+    }
+
+    impl Client for SlowClient {
+        fn send_rpc_request(
+            &self,
+            _request: Request,
+        ) -> Pin<Box<dyn Future<Output = EthJsonRpcResult<Response>> + Send>> {
+            let client = self.clone();
+            Box::pin(async move {
+                client.requests.fetch_add(1, Ordering::SeqCst);
+                let in_flight = client.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                client.max_in_flight.fetch_max(in_flight, Ordering::SeqCst);
+                tokio::time::sleep(Duration::from_millis(100)).await;
+                client.in_flight.fetch_sub(1, Ordering::SeqCst);
+
+                Ok(Response::Single(Output::Success(Success {
+                    jsonrpc: Some(Version::V2),
+                    result: serde_json::Value::Null,
+                    id: Id::Num(1),
+                })))
+            })
+        }
+    }
+
+    fn call() -> Call {
+        Call::MethodCall(MethodCall {
+            jsonrpc: Some(Version::V2),
+            method: "eth_blockNumber".to_string(),
+            params: Params::Array(vec![]),
+            id: Id::Num(1),
+        })
+    }
+
+    async fn send_all(client: &RateLimitedClient<SlowClient>, requests: Vec<Request>) {
+        let results = futures::future::join_all(
+            requests
+                .into_iter()
+                .map(|request| client.send_rpc_request(request)),
+        )
+        .await;
+        assert!(results.iter().all(Result::is_ok));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn should_limit_requests_per_second() {
+        let client = RateLimitedClient::new(
+            SlowClient::default(),
+            RateLimits::default().with_requests_per_second(10),
+        );
+        let started_at = Instant::now();
+
+        send_all(&client, vec![Request::Single(call()); 30]).await;
+
+        // 10 requests are sent immediately, the other 20 in the next two seconds
+        let elapsed = started_at.elapsed();
+        assert!(elapsed >= Duration::from_secs(2), "{elapsed:?}");
+        assert!(elapsed < Duration::from_millis(2200), "{elapsed:?}");
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn should_limit_batch_items_per_second() {
+        let client = RateLimitedClient::new(
+            SlowClient::default(),
+            RateLimits::default().with_batch_items_per_second(20),
+        );
+        let started_at = Instant::now();
+
+        // 50 items, the last batch is larger than the bucket
+        let requests = vec![
+            Request::Batch(vec![call(); 10]),
+            Request::Batch(vec![call(); 10]),
+            Request::Batch(vec![call(); 30]),
+        ];
+        send_all(&client, requests).await;
+
+        // The first 20 items are sent immediately, the bucket is full again after one second
+        // and the large batch leaves it in debt.
+        let elapsed = started_at.elapsed();
+        assert!(elapsed >= Duration::from_secs(1), "{elapsed:?}");
+        assert!(elapsed < Duration::from_millis(1200), "{elapsed:?}");
+        assert_eq!(client.inner().requests.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn should_limit_concurrent_requests() {
+        let client = RateLimitedClient::new(
+            SlowClient::default(),
+            RateLimits::default().with_max_concurrent_requests(3),
+        );
+
+        send_all(&client, vec![Request::Single(call()); 10]).await;
+
+        assert_eq!(client.inner().max_in_flight.load(Ordering::SeqCst), 3);
+        assert_eq!(client.inner().requests.load(Ordering::SeqCst), 10);
+    }
+}
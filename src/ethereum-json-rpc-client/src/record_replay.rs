@@ -0,0 +1,556 @@
+use std::fs::File;
+use std::future::Future;
+use std::io::{BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+
+use jsonrpc_core::{Call, Id, Output, Params, Request, Response};
+use serde::{Deserialize, Serialize};
+
+use crate::{Client, EthJsonRpcError, EthJsonRpcResult};
+
+/// A request sent to the endpoint and the response or the error it returned.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Interaction {
+    pub request: Request,
+    #[serde(flatten)]
+    pub outcome: Outcome,
+}
+
+/// The result of a recorded request.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Outcome {
+    Response(Response),
+    Error(EthJsonRpcError),
+}
+
+impl From<EthJsonRpcResult<Response>> for Outcome {
+    fn from(result: EthJsonRpcResult<Response>) -> Self {
+        match result {
+            Ok(response) => Self::Response(response),
+            Err(err) => Self::Error(err),
+        }
+    }
+}
+
+/// A sequence of interactions recorded by the [`RecordingClient`]
+/// and served by the [`ReplayClient`].
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Cassette {
+    pub interactions: Vec<Interaction>,
+}
+
+impl Cassette {
+    /// Reads a cassette from a JSON file.
+    pub fn load(path: impl AsRef<Path>) -> std::io::Result<Self> {
+        let reader = BufReader::new(File::open(path)?);
+        Ok(serde_json::from_reader(reader)?)
+    }
+
+    /// Writes the cassette to a JSON file, replacing its content.
+    ///
+    /// The cassette is written to a temporary file next to it first, so that a failed
+    /// write leaves the previous content in place.
+    pub fn save(&self, path: impl AsRef<Path>) -> std::io::Result<()> {
+        let path = path.as_ref();
+        let mut temp_path = path.as_os_str().to_owned();
+        temp_path.push(".tmp");
+
+        let result = self
+            .write(&temp_path)
+            .and_then(|_| std::fs::rename(&temp_path, path));
+        if result.is_err() {
+            let _ = std::fs::remove_file(&temp_path);
+        }
+        result
+    }
+
+    fn write(&self, path: impl AsRef<Path>) -> std::io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        serde_json::to_writer_pretty(&mut writer, self)?;
+        writer.flush()
+    }
+}
+
+/// A client which records the requests sent through the inner client
+/// and the responses or errors it returns to a cassette file.
+///
+/// The interactions are kept in memory and written to the file by
+/// [`RecordingClient::save`], and when the last clone of the client is dropped.
+/// The file is not written on drop if nothing has been recorded or if the thread
+/// is panicking, so that a failed test keeps the previous cassette.
+#[derive(Clone)]
+pub struct RecordingClient<C: Client> {
+    inner: C,
+    recorder: Arc<Recorder>,
+}
+
+struct Recorder {
+    path: PathBuf,
+    cassette: Mutex<Cassette>,
+}
+
+impl Recorder {
+    fn save(&self) -> std::io::Result<()> {
+        let cassette = self.cassette.lock().expect("poisoned mutex");
+        cassette.save(&self.path)
+    }
+}
+
+impl Drop for Recorder {
+    fn drop(&mut self) {
+        if std::thread::panicking() {
+            return;
+        }
+        // A poisoned cassette was left by a panic while recording
+        let Ok(cassette) = self.cassette.get_mut() else {
+            return;
+        };
+        if cassette.interactions.is_empty() {
+            return;
+        }
+
+        if let Err(err) = cassette.save(&self.path) {
+            log::error!(
+                "RecordingClient - failed to write cassette {}: {err}",
+                self.path.display()
+            );
+        }
+    }
+}
+
+impl<C: Client> RecordingClient<C> {
+    /// Creates a new client which starts recording to an empty cassette.
+    ///
+    /// # Arguments
+    /// * `inner` - The client used to send the requests.
+    /// * `path` - The path of the cassette file, replaced if it already exists.
+    pub fn new(inner: C, path: impl Into<PathBuf>) -> Self {
+        Self {
+            inner,
+            recorder: Arc::new(Recorder {
+                path: path.into(),
+                cassette: Default::default(),
+            }),
+        }
+    }
+
+    /// Returns a copy of the interactions recorded so far.
+    pub fn cassette(&self) -> Cassette {
+        self.recorder
+            .cassette
+            .lock()
+            .expect("poisoned mutex")
+            .clone()
+    }
+
+    /// Writes the interactions recorded so far to the cassette file.
+    pub fn save(&self) -> std::io::Result<()> {
+        self.recorder.save()
+    }
+
+    /// Returns the path of the cassette file.
+    pub fn path(&self) -> &Path {
+        &self.recorder.path
+    }
+
+    /// Returns the inner client.
+    pub fn inner(&self) -> &C {
+        &self.inner
+    }
+
+    fn record(&self, interaction: Interaction) {
+        self.recorder
+            .cassette
+            .lock()
+            .expect("poisoned mutex")
+            .interactions
+            .push(interaction);
+    }
+}
+
+impl<C: Client + 'static> Client for RecordingClient<C> {
+    fn send_rpc_request(
+        &self,
+        request: Request,
+    ) -> Pin<Box<dyn Future<Output = EthJsonRpcResult<Response>> + Send>> {
+        let client = self.clone();
+
+        Box::pin(async move {
+            let result = client.inner.send_rpc_request(request.clone()).await;
+            client.record(Interaction {
+                request,
+                outcome: result.clone().into(),
+            });
+
+            result
+        })
+    }
+}
+
+/// A client which serves the responses of a cassette without sending any request.
+///
+/// Requests are matched by method and parameters, ignoring their ids, and the
+/// ids of the returned responses are replaced with the ids of the request.
+/// The interactions matching a request are served in the recorded order; once they
+/// are all used, the last one is served again, so that polling the same request
+/// keeps working. Recorded errors are returned as they are.
+/// Requests without a matching interaction fail with
+/// [`EthJsonRpcError::UnmatchedRequest`].
+#[derive(Clone)]
+pub struct ReplayClient {
+    cassette: Arc<Cassette>,
+    used: Arc<Mutex<Vec<bool>>>,
+}
+
+impl ReplayClient {
+    /// Creates a new client serving the interactions of the cassette.
+    pub fn new(cassette: Cassette) -> Self {
+        Self {
+            used: Arc::new(Mutex::new(vec![false; cassette.interactions.len()])),
+            cassette: Arc::new(cassette),
+        }
+    }
+
+    /// Creates a new client serving the interactions of a cassette file.
+    pub fn from_file(path: impl AsRef<Path>) -> std::io::Result<Self> {
+        Cassette::load(path).map(Self::new)
+    }
+
+    /// Returns the cassette served by the client.
+    pub fn cassette(&self) -> &Cassette {
+        &self.cassette
+    }
+
+    /// Returns the number of interactions which have not been served yet.
+    pub fn remaining(&self) -> usize {
+        let used = self.used.lock().expect("poisoned mutex");
+        used.iter().filter(|used| !**used).count()
+    }
+
+    fn replay(&self, request: &Request) -> EthJsonRpcResult<Response> {
+        let key = request_key(request);
+        let mut used = self.used.lock().expect("poisoned mutex");
+
+        let matching: Vec<usize> = self
+            .cassette
+            .interactions
+            .iter()
+            .enumerate()
+            .filter(|(_, interaction)| request_key(&interaction.request) == key)
+            .map(|(index, _)| index)
+            .collect();
+
+        let Some(&last) = matching.last() else {
+            return Err(EthJsonRpcError::UnmatchedRequest(describe_request(request)));
+        };
+
+        let index = matching
+            .into_iter()
+            .find(|index| !used[*index])
+            .unwrap_or(last);
+        used[index] = true;
+
+        let interaction = &self.cassette.interactions[index];
+        match &interaction.outcome {
+            Outcome::Response(response) => Ok(replace_ids(
+                response.clone(),
+                &request_ids(&interaction.request),
+                &request_ids(request),
+            )),
+            Outcome::Error(err) => Err(err.clone()),
+        }
+    }
+}
+
+impl Client for ReplayClient {
+    fn send_rpc_request(
+        &self,
+        request: Request,
+    ) -> Pin<Box<dyn Future<Output = EthJsonRpcResult<Response>> + Send>> {
+        let result = self.replay(&request);
+        Box::pin(async move { result })
+    }
+}
+
+/// Returns the methods and parameters of the calls of the request.
+fn request_key(request: &Request) -> Vec<(&str, &Params)> {
+    match request {
+        Request::Single(call) => vec![call_key(call)],
+        Request::Batch(calls) => calls.iter().map(call_key).collect(),
+    }
+}
+
+fn call_key(call: &Call) -> (&str, &Params) {
+    const NO_PARAMS: &Params = &Params::None;
+
+    match call {
+        Call::MethodCall(call) => (call.method.as_str(), &call.params),
+        Call::Notification(notification) => (notification.method.as_str(), &notification.params),
+        Call::Invalid { .. } => ("", NO_PARAMS),
+    }
+}
+
+/// Returns the ids of the calls of the request.
+fn request_ids(request: &Request) -> Vec<Option<&Id>> {
+    match request {
+        Request::Single(call) => vec![call_id(call)],
+        Request::Batch(calls) => calls.iter().map(call_id).collect(),
+    }
+}
+
+fn call_id(call: &Call) -> Option<&Id> {
+    match call {
+        Call::MethodCall(call) => Some(&call.id),
+        Call::Notification(_) => None,
+        Call::Invalid { id } => Some(id),
+    }
+}
+
+fn describe_request(request: &Request) -> String {
+    request_key(request)
+        .into_iter()
+        .map(|(method, params)| {
+            let params = serde_json::to_string(params).unwrap_or_default();
+            format!("{method}({params})")
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Replaces the recorded ids of the response outputs with the ids of the calls
+/// at the same position in the live request.
+fn replace_ids(response: Response, recorded: &[Option<&Id>], live: &[Option<&Id>]) -> Response {
+    let replace = |mut output: Output| {
+        let id = match &mut output {
+            Output::Success(success) => &mut success.id,
+            Output::Failure(failure) => &mut failure.id,
+        };
+        let live_id = recorded
+            .iter()
+            .position(|recorded| *recorded == Some(&*id))
+            .and_then(|position| live.get(position).copied().flatten());
+        if let Some(live_id) = live_id {
+            *id = live_id.clone();
+        }
+        output
+    };
+
+    match response {
+        Response::Single(output) => Response::Single(replace(output)),
+        Response::Batch(outputs) => Response::Batch(outputs.into_iter().map(replace).collect()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ethers_core::types::U64;
+    use jsonrpc_core::{MethodCall, Success, Version};
+    use serde_json::json;
+
+    use super::*;
+    use crate::EthJsonRpcClient;
+
+    /// Answers `eth_blockNumber` with an increasing number and echoes the first param of other calls.
+    #[derive(Clone, Default)]
+    struct CountingClient {
+        block_number: Arc<Mutex<u64>>,
+    }
+
+    impl Client for CountingClient {
+        fn send_rpc_request(
+            &self,
+            request: Request,
+        ) -> Pin<Box<dyn Future<Output = EthJsonRpcResult<Response>> + Send>> {
+            let mut block_number = self.block_number.lock().unwrap();
+            let mut output = |call: Call| {
+                let Call::MethodCall(call) = call else {
+                    panic!("unexpected call");
+                };
+                let result = if call.method == "eth_blockNumber" {
+                    *block_number += 1;
+                    json!(U64::from(*block_number))
+                } else {
+                    let Params::Array(params) = call.params else {
+                        panic!("unexpected params");
+                    };
+                    params[0].clone()
+                };
+                Output::Success(Success {
+                    jsonrpc: Some(Version::V2),
+                    result,
+                    id: call.id,
+                })
+            };
+
+            let response = match request {
+                Request::Single(call) => Response::Single(output(call)),
+                Request::Batch(calls) => Response::Batch(calls.into_iter().map(output).collect()),
+            };
+            Box::pin(async move { Ok(response) })
+        }
+    }
+
+    #[tokio::test]
+    async fn should_record_and_replay_requests() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("cassette.json");
+
+        let recording = RecordingClient::new(CountingClient::default(), &path);
+        let client = EthJsonRpcClient::new(recording.clone());
+        assert_eq!(client.get_block_number().await.unwrap(), 1);
+        assert_eq!(client.get_block_number().await.unwrap(), 2);
+        let code = client
+            .get_code(Default::default(), Default::default())
+            .await
+            .unwrap();
+        let cassette = recording.cassette();
+        assert_eq!(cassette.interactions.len(), 3);
+        assert!(!path.exists());
+
+        // The cassette is written when the last clone is dropped
+        drop(client);
+        drop(recording);
+        let replay = ReplayClient::from_file(&path).unwrap();
+        assert_eq!(replay.cassette(), &cassette);
+
+        let client = EthJsonRpcClient::new(replay.clone());
+        assert_eq!(client.get_block_number().await.unwrap(), 1);
+        assert_eq!(client.get_block_number().await.unwrap(), 2);
+        // The last matching interaction is served again
+        assert_eq!(client.get_block_number().await.unwrap(), 2);
+        assert_eq!(replay.remaining(), 1);
+        assert_eq!(
+            client
+                .get_code(Default::default(), Default::default())
+                .await
+                .unwrap(),
+            code
+        );
+        assert_eq!(replay.remaining(), 0);
+    }
+
+    #[tokio::test]
+    async fn should_replace_response_ids() {
+        let call = |id: u64, param: u64| {
+            Call::MethodCall(MethodCall {
+                jsonrpc: Some(Version::V2),
+                method: "eth_getBlockByNumber".to_string(),
+                params: Params::Array(vec![json!(param)]),
+                id: Id::Num(id),
+            })
+        };
+
+        let recording = CountingClient::default();
+        let recorded_request = Request::Batch(vec![call(1, 10), call(2, 20)]);
+        let cassette = Cassette {
+            interactions: vec![Interaction {
+                outcome: recording
+                    .send_rpc_request(recorded_request.clone())
+                    .await
+                    .into(),
+                request: recorded_request,
+            }],
+        };
+
+        let replay = ReplayClient::new(cassette);
+        let response = replay
+            .send_rpc_request(Request::Batch(vec![call(7, 10), call(8, 20)]))
+            .await
+            .unwrap();
+
+        let Response::Batch(outputs) = response else {
+            panic!("expected a batch response");
+        };
+        let outputs: Vec<_> = outputs
+            .into_iter()
+            .map(|output| match output {
+                Output::Success(success) => (success.id, success.result),
+                Output::Failure(_) => panic!("unexpected failure"),
+            })
+            .collect();
+        assert_eq!(
+            outputs,
+            vec![(Id::Num(7), json!(10)), (Id::Num(8), json!(20))]
+        );
+    }
+
+    #[test]
+    fn should_keep_previous_cassette_on_drop_without_interactions() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("cassette.json");
+        std::fs::write(&path, "previous").unwrap();
+
+        drop(RecordingClient::new(CountingClient::default(), &path));
+
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "previous");
+    }
+
+    #[test]
+    fn should_keep_previous_cassette_on_drop_while_panicking() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("cassette.json");
+        std::fs::write(&path, "previous").unwrap();
+
+        let recording = RecordingClient::new(CountingClient::default(), &path);
+        recording.record(Interaction {
+            request: Request::Single(Call::MethodCall(MethodCall {
+                jsonrpc: Some(Version::V2),
+                method: "eth_blockNumber".to_string(),
+                params: Params::None,
+                id: Id::Num(1),
+            })),
+            outcome: Outcome::Error(EthJsonRpcError::Transport("unreachable".to_string())),
+        });
+        let result = std::thread::spawn(move || {
+            let _recording = recording;
+            panic!("test failed while recording");
+        })
+        .join();
+
+        assert!(result.is_err());
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "previous");
+    }
+
+    #[tokio::test]
+    async fn should_record_and_replay_errors() {
+        #[derive(Clone)]
+        struct FailingClient;
+
+        impl Client for FailingClient {
+            fn send_rpc_request(
+                &self,
+                _request: Request,
+            ) -> Pin<Box<dyn Future<Output = EthJsonRpcResult<Response>> + Send>> {
+                Box::pin(async { Err(EthJsonRpcError::Transport("connection reset".to_string())) })
+            }
+        }
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("cassette.json");
+
+        let recording = RecordingClient::new(FailingClient, &path);
+        let err = EthJsonRpcClient::new(recording.clone())
+            .get_chain_id()
+            .await
+            .unwrap_err();
+        recording.save().unwrap();
+
+        let client = EthJsonRpcClient::new(ReplayClient::from_file(&path).unwrap());
+        assert_eq!(client.get_chain_id().await.unwrap_err(), err);
+    }
+
+    #[tokio::test]
+    async fn should_fail_on_unmatched_request() {
+        let client = EthJsonRpcClient::new(ReplayClient::new(Cassette::default()));
+
+        let err = client.get_chain_id().await.unwrap_err();
+
+        assert!(
+            matches!(&err, EthJsonRpcError::UnmatchedRequest(request) if request.starts_with("eth_chainId")),
+            "{err:?}"
+        );
+    }
+}
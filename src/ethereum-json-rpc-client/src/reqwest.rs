@@ -1,17 +1,37 @@
 use std::future::Future;
 use std::pin::Pin;
+use std::time::Duration;
 
-use anyhow::Context;
 use jsonrpc_core::{Request, Response};
 pub use reqwest;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
 
-use crate::Client;
+use crate::{Client, EthJsonRpcError, EthJsonRpcResult};
+
+/// Authentication of the requests sent by a [`ReqwestClient`].
+#[derive(Clone)]
+pub enum Auth {
+    /// `Authorization: Bearer <token>`
+    Bearer(String),
+    /// `Authorization: Basic <credentials>`
+    Basic {
+        username: String,
+        password: Option<String>,
+    },
+}
 
 /// Reqwest client implementation.
+///
+/// Gzip and brotli encoded responses are decoded, unless the client is created
+/// with a custom reqwest client which disables it.
 #[derive(Clone)]
 pub struct ReqwestClient {
     client: reqwest::Client,
     endpoint_url: String,
+    auth: Option<Auth>,
+    headers: HeaderMap,
+    timeout: Option<Duration>,
+    max_response_size: Option<usize>,
 }
 
 impl ReqwestClient {
@@ -25,35 +45,152 @@ impl ReqwestClient {
         Self {
             endpoint_url,
             client,
+            auth: None,
+            headers: HeaderMap::new(),
+            timeout: None,
+            max_response_size: None,
         }
     }
+
+    /// Authenticates the requests with a bearer token.
+    pub fn with_bearer_auth(mut self, token: impl Into<String>) -> Self {
+        self.auth = Some(Auth::Bearer(token.into()));
+        self
+    }
+
+    /// Authenticates the requests with basic authentication.
+    pub fn with_basic_auth(
+        mut self,
+        username: impl Into<String>,
+        password: Option<impl Into<String>>,
+    ) -> Self {
+        self.auth = Some(Auth::Basic {
+            username: username.into(),
+            password: password.map(Into::into),
+        });
+        self
+    }
+
+    /// Adds a header to all the requests.
+    pub fn with_header(mut self, name: HeaderName, value: HeaderValue) -> Self {
+        self.headers.insert(name, value);
+        self
+    }
+
+    /// Adds headers to all the requests.
+    pub fn with_headers(mut self, headers: HeaderMap) -> Self {
+        self.headers.extend(headers);
+        self
+    }
+
+    /// Sets the timeout of every request, from the start of the connection
+    /// until the response body has been read.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Sets the maximum size in bytes of the response bodies.
+    ///
+    /// Larger responses fail with [`EthJsonRpcError::ResponseTooLarge`].
+    pub fn with_max_response_size(mut self, max_response_size: usize) -> Self {
+        self.max_response_size = Some(max_response_size);
+        self
+    }
+
+    fn request_builder(&self, request: &Request) -> reqwest::RequestBuilder {
+        let mut request_builder = self
+            .client
+            .post(&self.endpoint_url)
+            .headers(self.headers.clone())
+            .json(request);
+
+        request_builder = match &self.auth {
+            Some(Auth::Bearer(token)) => request_builder.bearer_auth(token),
+            Some(Auth::Basic { username, password }) => {
+                request_builder.basic_auth(username, password.as_ref())
+            }
+            None => request_builder,
+        };
+        if let Some(timeout) = self.timeout {
+            request_builder = request_builder.timeout(timeout);
+        }
+
+        request_builder
+    }
+}
+
+/// Reads the response body, failing as soon as it is larger than `limit`.
+async fn read_body(
+    mut response: reqwest::Response,
+    limit: Option<usize>,
+) -> EthJsonRpcResult<Vec<u8>> {
+    let Some(limit) = limit else {
+        return response
+            .bytes()
+            .await
+            .map(Into::into)
+            .map_err(|e| EthJsonRpcError::Transport(format!("failed to read RPC response: {e}")));
+    };
+
+    if response
+        .content_length()
+        .is_some_and(|length| length > limit as u64)
+    {
+        return Err(EthJsonRpcError::ResponseTooLarge { limit });
+    }
+
+    let mut body = Vec::new();
+    while let Some(chunk) = response
+        .chunk()
+        .await
+        .map_err(|e| EthJsonRpcError::Transport(format!("failed to read RPC response: {e}")))?
+    {
+        if body.len() + chunk.len() > limit {
+            return Err(EthJsonRpcError::ResponseTooLarge { limit });
+        }
+        body.extend_from_slice(&chunk);
+    }
+
+    Ok(body)
 }
 
 impl Client for ReqwestClient {
     fn send_rpc_request(
         &self,
         request: Request,
-    ) -> Pin<Box<dyn Future<Output = anyhow::Result<Response>> + Send>> {
+    ) -> Pin<Box<dyn Future<Output = EthJsonRpcResult<Response>> + Send>> {
         log::trace!("ReqwestClient - sending request {request:?}");
 
-        let request_builder = self.client.post(&self.endpoint_url).json(&request);
+        let request_builder = self.request_builder(&request);
+        let max_response_size = self.max_response_size;
 
         Box::pin(async move {
-            let response = request_builder
-                .send()
-                .await
-                .context("failed to send RPC request")?;
+            let response = request_builder.send().await.map_err(|e| {
+                // The request cannot be built, e.g. because of an invalid url
+                if e.is_builder() {
+                    EthJsonRpcError::InvalidConfiguration(format!("invalid RPC request: {e}"))
+                } else {
+                    EthJsonRpcError::Transport(format!("failed to send RPC request: {e}"))
+                }
+            })?;
 
             if !response.status().is_success() {
                 let status = response.status();
-                let text = response.text().await.unwrap_or_default();
-                anyhow::bail!("RPC request failed: {status} - {text}");
+                let body = read_body(response, max_response_size)
+                    .await
+                    .map(|body| String::from_utf8_lossy(&body).into_owned())
+                    .unwrap_or_default();
+                return Err(EthJsonRpcError::HttpStatus {
+                    status: status.as_u16(),
+                    body,
+                });
             }
 
-            let json_response = response
-                .json::<Response>()
-                .await
-                .context("failed to decode RPC response")?;
+            let body = read_body(response, max_response_size).await?;
+            let json_response = serde_json::from_slice::<Response>(&body).map_err(|e| {
+                EthJsonRpcError::Deserialization(format!("failed to decode RPC response: {e}"))
+            })?;
 
             log::trace!("response: {:?}", json_response);
             Ok(json_response)
@@ -0,0 +1,483 @@
+use std::collections::HashSet;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::Duration;
+
+use jsonrpc_core::{Call, Output, Request, Response};
+use rand::Rng;
+
+use crate::{Client, EthJsonRpcError, EthJsonRpcResult, ETH_SEND_RAW_TRANSACTION_METHOD};
+
+const ETH_SEND_TRANSACTION_METHOD: &str = "eth_sendTransaction";
+
+/// JSON-RPC error codes which report a temporary condition of the server.
+const RETRYABLE_JSON_RPC_CODES: [i64; 2] = [
+    -32005, // LIMIT_EXCEEDED
+    -32603, // INTERNAL_ERROR
+];
+
+/// HTTP status codes which report a temporary condition of the server.
+const RETRYABLE_HTTP_STATUSES: [u16; 6] = [408, 429, 500, 502, 503, 504];
+
+/// HTTP status code returned when a request is throttled before being processed.
+const TOO_MANY_REQUESTS_STATUS: u16 = 429;
+
+/// Configuration of the [`RetryClient`].
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// Maximum number of attempts, including the first one.
+    pub max_attempts: u32,
+    /// Delay before the first retry.
+    pub initial_backoff: Duration,
+    /// Upper bound of the delay between two attempts.
+    pub max_backoff: Duration,
+    /// Factor applied to the delay after every failed attempt.
+    pub backoff_multiplier: u32,
+    /// Fraction of the delay, in the `[0, 1]` range, which is randomized
+    /// to avoid many clients retrying at the same time.
+    /// Values out of the range are clamped, and NaN disables the jitter.
+    pub jitter: f64,
+    /// Methods which are not safe to send twice.
+    /// A request containing any of them is retried only if the server
+    /// rejected it before processing.
+    pub non_idempotent_methods: HashSet<String>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 5,
+            initial_backoff: Duration::from_millis(200),
+            max_backoff: Duration::from_secs(10),
+            backoff_multiplier: 2,
+            jitter: 0.2,
+            non_idempotent_methods: [ETH_SEND_RAW_TRANSACTION_METHOD, ETH_SEND_TRANSACTION_METHOD]
+                .into_iter()
+                .map(ToString::to_string)
+                .collect(),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Sets the maximum number of attempts, including the first one.
+    pub fn with_max_attempts(mut self, max_attempts: u32) -> Self {
+        self.max_attempts = max_attempts;
+        self
+    }
+
+    /// Sets the initial and the maximum delay between two attempts.
+    pub fn with_backoff(mut self, initial_backoff: Duration, max_backoff: Duration) -> Self {
+        self.initial_backoff = initial_backoff;
+        self.max_backoff = max_backoff;
+        self
+    }
+
+    /// Sets the factor applied to the delay after every failed attempt.
+    pub fn with_backoff_multiplier(mut self, backoff_multiplier: u32) -> Self {
+        self.backoff_multiplier = backoff_multiplier;
+        self
+    }
+
+    /// Sets the randomized fraction of the delay.
+    pub fn with_jitter(mut self, jitter: f64) -> Self {
+        self.jitter = jitter.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Marks the method as safe to be sent more than once.
+    pub fn with_idempotent_method(mut self, method: &str) -> Self {
+        self.non_idempotent_methods.remove(method);
+        self
+    }
+
+    /// Marks the method as not safe to be sent more than once.
+    pub fn with_non_idempotent_method(mut self, method: &str) -> Self {
+        self.non_idempotent_methods.insert(method.to_string());
+        self
+    }
+
+    /// Returns true if all the calls of the request can be safely sent more than once.
+    pub fn is_idempotent(&self, request: &Request) -> bool {
+        let is_idempotent_call = |call: &Call| match call {
+            Call::MethodCall(call) => !self.non_idempotent_methods.contains(&call.method),
+            Call::Notification(notification) => {
+                !self.non_idempotent_methods.contains(&notification.method)
+            }
+            Call::Invalid { .. } => true,
+        };
+
+        match request {
+            Request::Single(call) => is_idempotent_call(call),
+            Request::Batch(calls) => calls.iter().all(is_idempotent_call),
+        }
+    }
+
+    /// Returns the delay to wait after the given failed attempt, starting from 1.
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let factor = self
+            .backoff_multiplier
+            .checked_pow(attempt.saturating_sub(1))
+            .unwrap_or(u32::MAX);
+        let delay = self
+            .initial_backoff
+            .checked_mul(factor)
+            .unwrap_or(self.max_backoff)
+            .min(self.max_backoff);
+
+        if self.jitter.is_nan() || self.jitter <= 0.0 {
+            return delay;
+        }
+
+        let max_jitter = self.jitter.min(1.0);
+        let jitter = rand::thread_rng().gen_range(-max_jitter..=max_jitter);
+        delay.mul_f64(1.0 + jitter).min(self.max_backoff)
+    }
+}
+
+/// Returns true if the error reports a temporary failure which may succeed if retried.
+///
+/// Transport errors are retried, as the permanent failures of the clients, like an
+/// invalid url or too few cycles, are reported with their own variants.
+pub fn is_retryable_error(err: &EthJsonRpcError) -> bool {
+    match err {
+        EthJsonRpcError::Transport(_) => true,
+        EthJsonRpcError::HttpStatus { status, .. } => RETRYABLE_HTTP_STATUSES.contains(status),
+        EthJsonRpcError::JsonRpc { code, .. } => RETRYABLE_JSON_RPC_CODES.contains(code),
+        // Endpoints may temporarily disagree while they are syncing
+        EthJsonRpcError::NoQuorum { .. } | EthJsonRpcError::InconsistentResponses { .. } => true,
+        EthJsonRpcError::Serialization(_)
+        | EthJsonRpcError::Deserialization(_)
+        | EthJsonRpcError::UnexpectedResponse(_)
+        | EthJsonRpcError::NotFound(_)
+        | EthJsonRpcError::UnmatchedRequest(_)
+        | EthJsonRpcError::Certification(_)
+        | EthJsonRpcError::ResponseTooLarge { .. }
+        | EthJsonRpcError::InvalidConfiguration(_)
+        | EthJsonRpcError::InsufficientCycles { .. }
+        | EthJsonRpcError::Contract(_) => false,
+    }
+}
+
+/// Returns true if the error guarantees that the request was not processed by the server.
+fn is_rejected_before_processing(err: &EthJsonRpcError) -> bool {
+    matches!(
+        err,
+        EthJsonRpcError::HttpStatus {
+            status: TOO_MANY_REQUESTS_STATUS,
+            ..
+        }
+    )
+}
+
+/// Returns true if the response contains an error which may disappear if the request is retried.
+fn is_retryable_response(response: &Response) -> bool {
+    let is_retryable_output = |output: &Output| match output {
+        Output::Success(_) => false,
+        Output::Failure(failure) => RETRYABLE_JSON_RPC_CODES.contains(&failure.error.code.code()),
+    };
+
+    match response {
+        Response::Single(output) => is_retryable_output(output),
+        Response::Batch(outputs) => outputs.iter().any(is_retryable_output),
+    }
+}
+
+/// A client which retries failed requests of the inner client with exponential backoff.
+#[derive(Clone)]
+pub struct RetryClient<C: Client> {
+    inner: C,
+    policy: Arc<RetryPolicy>,
+}
+
+impl<C: Client> RetryClient<C> {
+    /// Creates a new client with the default retry policy.
+    ///
+    /// # Arguments
+    /// * `inner` - The client used to send the requests.
+    pub fn new(inner: C) -> Self {
+        Self::with_policy(inner, RetryPolicy::default())
+    }
+
+    /// Creates a new client with a custom retry policy.
+    ///
+    /// # Arguments
+    /// * `inner` - The client used to send the requests.
+    /// * `policy` - The retry policy.
+    pub fn with_policy(inner: C, policy: RetryPolicy) -> Self {
+        Self {
+            inner,
+            policy: Arc::new(policy),
+        }
+    }
+
+    /// Returns the retry policy.
+    pub fn policy(&self) -> &RetryPolicy {
+        &self.policy
+    }
+
+    /// Returns the inner client.
+    pub fn inner(&self) -> &C {
+        &self.inner
+    }
+}
+
+impl<C: Client + 'static> Client for RetryClient<C> {
+    fn send_rpc_request(
+        &self,
+        request: Request,
+    ) -> Pin<Box<dyn Future<Output = EthJsonRpcResult<Response>> + Send>> {
+        let client = self.inner.clone();
+        let policy = self.policy.clone();
+
+        Box::pin(async move {
+            let is_idempotent = policy.is_idempotent(&request);
+            let mut attempt = 1;
+
+            loop {
+                let result = client.send_rpc_request(request.clone()).await;
+
+                let should_retry = match &result {
+                    Ok(response) => is_idempotent && is_retryable_response(response),
+                    Err(err) if is_idempotent => is_retryable_error(err),
+                    Err(err) => is_rejected_before_processing(err),
+                };
+
+                if !should_retry || attempt >= policy.max_attempts {
+                    return result;
+                }
+
+                let delay = policy.backoff(attempt);
+                log::warn!(
+                    "RetryClient - attempt {attempt} of {} failed, retrying in {delay:?}",
+                    policy.max_attempts
+                );
+                tokio::time::sleep(delay).await;
+
+                attempt += 1;
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    use jsonrpc_core::{Failure, Id, MethodCall, Params, Success, Version};
+
+    use super::*;
+    use crate::ETH_CHAIN_ID_METHOD;
+
+    /// A client which fails with the given error a fixed number of times.
+    #[derive(Clone)]
+    struct FlakyClient {
+        failures: u32,
+        error: EthJsonRpcError,
+        calls: Arc<AtomicU32>,
+    }
+
+    impl FlakyClient {
+        fn new(failures: u32, error: EthJsonRpcError) -> Self {
+            Self {
+                failures,
+                error,
+                calls: Arc::default(),
+            }
+        }
+
+        fn calls(&self) -> u32 {
+            self.calls.load(Ordering::SeqCst)
+        }
+    }
+
+    impl Client for FlakyClient {
+        fn send_rpc_request(
+            &self,
+            _request: Request,
+        ) -> Pin<Box<dyn Future<Output = EthJsonRpcResult<Response>> + Send>> {
+            let call = self.calls.fetch_add(1, Ordering::SeqCst);
+            let result = if call < self.failures {
+                Err(self.error.clone())
+            } else {
+                Ok(Response::Single(Output::Success(Success {
+                    jsonrpc: Some(Version::V2),
+                    result: "0x1".into(),
+                    id: Id::Num(0),
+                })))
+            };
+
+            Box::pin(async move { result })
+        }
+    }
+
+    fn request(method: &str) -> Request {
+        Request::Single(Call::MethodCall(MethodCall {
+            jsonrpc: Some(Version::V2),
+            method: method.to_string(),
+            params: Params::Array(vec![]),
+            id: Id::Num(0),
+        }))
+    }
+
+    fn policy() -> RetryPolicy {
+        RetryPolicy::default()
+            .with_max_attempts(3)
+            .with_backoff(Duration::from_millis(1), Duration::from_millis(5))
+    }
+
+    #[tokio::test]
+    async fn should_retry_transport_errors() {
+        let flaky = FlakyClient::new(2, EthJsonRpcError::Transport("timeout".to_string()));
+        let client = RetryClient::with_policy(flaky.clone(), policy());
+
+        let response = client.send_rpc_request(request(ETH_CHAIN_ID_METHOD)).await;
+
+        assert!(response.is_ok());
+        assert_eq!(flaky.calls(), 3);
+    }
+
+    #[tokio::test]
+    async fn should_give_up_after_max_attempts() {
+        let flaky = FlakyClient::new(10, EthJsonRpcError::Transport("timeout".to_string()));
+        let client = RetryClient::with_policy(flaky.clone(), policy());
+
+        let response = client.send_rpc_request(request(ETH_CHAIN_ID_METHOD)).await;
+
+        assert_eq!(
+            response.unwrap_err(),
+            EthJsonRpcError::Transport("timeout".to_string())
+        );
+        assert_eq!(flaky.calls(), 3);
+    }
+
+    #[tokio::test]
+    async fn should_not_retry_permanent_errors() {
+        let error = EthJsonRpcError::JsonRpc {
+            code: -32602,
+            message: "invalid params".to_string(),
+            data: None,
+        };
+        let flaky = FlakyClient::new(1, error);
+        let client = RetryClient::with_policy(flaky.clone(), policy());
+
+        let response = client.send_rpc_request(request(ETH_CHAIN_ID_METHOD)).await;
+
+        assert!(response.is_err());
+        assert_eq!(flaky.calls(), 1);
+    }
+
+    #[tokio::test]
+    async fn should_not_retry_client_errors() {
+        for error in [
+            EthJsonRpcError::InvalidConfiguration("failed to parse url `localhost`".to_string()),
+            EthJsonRpcError::InsufficientCycles {
+                required: 2_000_000_000,
+                available: 1_000,
+            },
+            EthJsonRpcError::ResponseTooLarge { limit: 2_000_000 },
+        ] {
+            let flaky = FlakyClient::new(1, error.clone());
+            let client = RetryClient::with_policy(flaky.clone(), policy());
+
+            let response = client.send_rpc_request(request(ETH_CHAIN_ID_METHOD)).await;
+
+            assert_eq!(response.unwrap_err(), error);
+            assert_eq!(flaky.calls(), 1);
+        }
+    }
+
+    #[tokio::test]
+    async fn should_not_resend_raw_transaction_after_transport_error() {
+        let flaky = FlakyClient::new(1, EthJsonRpcError::Transport("timeout".to_string()));
+        let client = RetryClient::with_policy(flaky.clone(), policy());
+
+        let response = client
+            .send_rpc_request(request(ETH_SEND_RAW_TRANSACTION_METHOD))
+            .await;
+
+        assert!(response.is_err());
+        assert_eq!(flaky.calls(), 1);
+    }
+
+    #[tokio::test]
+    async fn should_resend_raw_transaction_after_throttling() {
+        let error = EthJsonRpcError::HttpStatus {
+            status: 429,
+            body: "Too Many Requests".to_string(),
+        };
+        let flaky = FlakyClient::new(1, error);
+        let client = RetryClient::with_policy(flaky.clone(), policy());
+
+        let response = client
+            .send_rpc_request(request(ETH_SEND_RAW_TRANSACTION_METHOD))
+            .await;
+
+        assert!(response.is_ok());
+        assert_eq!(flaky.calls(), 2);
+    }
+
+    #[test]
+    fn should_detect_retryable_responses() {
+        let failure = |code: i64| {
+            Response::Single(Output::Failure(Failure {
+                jsonrpc: Some(Version::V2),
+                error: jsonrpc_core::Error {
+                    code: code.into(),
+                    message: String::new(),
+                    data: None,
+                },
+                id: Id::Num(0),
+            }))
+        };
+
+        assert!(is_retryable_response(&failure(-32005)));
+        assert!(!is_retryable_response(&failure(-32602)));
+    }
+
+    #[test]
+    fn should_compute_backoff() {
+        let policy = RetryPolicy::default()
+            .with_backoff(Duration::from_millis(100), Duration::from_millis(1000))
+            .with_jitter(0.0);
+
+        assert_eq!(policy.backoff(1), Duration::from_millis(100));
+        assert_eq!(policy.backoff(2), Duration::from_millis(200));
+        assert_eq!(policy.backoff(3), Duration::from_millis(400));
+        assert_eq!(policy.backoff(10), Duration::from_millis(1000));
+        assert_eq!(policy.backoff(100), Duration::from_millis(1000));
+
+        let policy = policy.with_jitter(0.5);
+        for attempt in 1..10 {
+            assert!(policy.backoff(attempt) <= Duration::from_millis(1000));
+        }
+    }
+
+    #[test]
+    fn should_clamp_jitter_set_on_the_field() {
+        let mut policy = RetryPolicy::default()
+            .with_backoff(Duration::from_millis(100), Duration::from_millis(1000));
+
+        policy.jitter = 1.5;
+        for attempt in 1..10 {
+            assert!(policy.backoff(attempt) <= Duration::from_millis(1000));
+        }
+
+        policy.jitter = f64::NAN;
+        assert_eq!(policy.backoff(1), Duration::from_millis(100));
+
+        policy.jitter = f64::INFINITY;
+        assert!(policy.backoff(1) <= Duration::from_millis(200));
+    }
+
+    #[test]
+    fn should_detect_idempotent_requests() {
+        let policy = RetryPolicy::default();
+        assert!(policy.is_idempotent(&request(ETH_CHAIN_ID_METHOD)));
+        assert!(!policy.is_idempotent(&request(ETH_SEND_RAW_TRANSACTION_METHOD)));
+
+        let policy = policy.with_idempotent_method(ETH_SEND_RAW_TRANSACTION_METHOD);
+        assert!(policy.is_idempotent(&request(ETH_SEND_RAW_TRANSACTION_METHOD)));
+    }
+}
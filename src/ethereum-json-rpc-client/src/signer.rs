@@ -0,0 +1,340 @@
+use std::sync::Arc;
+
+use did::error::EvmError;
+use eth_signer::sign_strategy::{TransactionSigner, TransactionSignerError};
+use eth_signer::transaction::{SigningMethod, TransactionBuilder};
+use ethers_core::types::transaction::eip2718::TypedTransaction;
+use ethers_core::types::{BlockNumber, Transaction, TransactionRequest, H160, H256, U256};
+use futures::lock::Mutex;
+use thiserror::Error;
+
+use crate::{Client, EthJsonRpcClient, EthJsonRpcError};
+
+/// Errors returned by the [`SignerClient`].
+#[derive(Debug, Error)]
+pub enum SignerClientError {
+    /// A request to the node failed.
+    #[error(transparent)]
+    Rpc(#[from] EthJsonRpcError),
+
+    /// The transaction could not be signed.
+    #[error("signer error: {0}")]
+    Signer(#[from] TransactionSignerError),
+
+    /// The signed transaction could not be built.
+    #[error("failed to build transaction: {0}")]
+    Build(#[from] EvmError),
+
+    /// The transaction request cannot be signed as it is.
+    #[error("invalid transaction request: {0}")]
+    InvalidRequest(String),
+}
+
+/// Result of the [`SignerClient`] operations.
+pub type SignerClientResult<T> = Result<T, SignerClientError>;
+
+/// A client which fills, signs and sends transactions on behalf of a [`TransactionSigner`].
+///
+/// The fields missing from the transaction requests are filled as follows:
+/// - the nonce is assigned by a local nonce manager, initialized with the pending
+///   transaction count of the signer, so that concurrent sends do not use the same nonce;
+/// - the gas limit is estimated by the node;
+/// - the gas price is the one returned by the node;
+/// - the chain id is the one returned by the node, unless set with [`SignerClient::with_chain_id`].
+///
+/// If signing or sending a transaction fails, the nonce manager is reset and the nonce
+/// is fetched again from the node on the next send.
+/// Clones share the nonce manager.
+#[derive(Clone)]
+pub struct SignerClient<C: Client, S: TransactionSigner> {
+    client: EthJsonRpcClient<C>,
+    signer: S,
+    chain_id: Arc<Mutex<Option<u64>>>,
+    next_nonce: Arc<Mutex<Option<U256>>>,
+}
+
+impl<C: Client, S: TransactionSigner> SignerClient<C, S> {
+    /// Creates a new client.
+    ///
+    /// # Arguments
+    /// * `client` - The client used to talk to the node.
+    /// * `signer` - The signer of the transactions.
+    pub fn new(client: EthJsonRpcClient<C>, signer: S) -> Self {
+        Self {
+            client,
+            signer,
+            chain_id: Arc::default(),
+            next_nonce: Arc::default(),
+        }
+    }
+
+    /// Sets the chain id of the transactions instead of fetching it from the node.
+    pub fn with_chain_id(mut self, chain_id: u64) -> Self {
+        self.chain_id = Arc::new(Mutex::new(Some(chain_id)));
+        self
+    }
+
+    /// Returns the client used to talk to the node.
+    pub fn client(&self) -> &EthJsonRpcClient<C> {
+        &self.client
+    }
+
+    /// Returns the signer of the transactions.
+    pub fn signer(&self) -> &S {
+        &self.signer
+    }
+
+    /// Returns the address of the signer.
+    pub async fn address(&self) -> SignerClientResult<H160> {
+        Ok(self.signer.get_address().await?.into())
+    }
+
+    /// Forgets the next nonce, so that it is fetched again from the node on the next send.
+    pub async fn reset_nonce(&self) {
+        *self.next_nonce.lock().await = None;
+    }
+
+    /// Fills the missing fields of the transaction request.
+    ///
+    /// The nonce, if missing, is taken from the nonce manager: the request should be sent,
+    /// or [`SignerClient::reset_nonce`] called, to avoid leaving a gap in the nonces.
+    pub async fn fill_transaction(
+        &self,
+        request: &mut TransactionRequest,
+    ) -> SignerClientResult<()> {
+        let from = self.address().await?;
+        request.from = Some(from);
+
+        if request.chain_id.is_none() {
+            request.chain_id = Some(self.chain_id().await?.into());
+        }
+        if request.gas_price.is_none() {
+            request.gas_price = Some(self.client.gas_price().await?);
+        }
+        if request.gas.is_none() {
+            request.gas = Some(self.client.estimate_gas(request.clone()).await?);
+        }
+        if request.nonce.is_none() {
+            request.nonce = Some(self.next_nonce(from).await?);
+        }
+
+        Ok(())
+    }
+
+    /// Fills the missing fields of the transaction request and signs it.
+    pub async fn sign_transaction(
+        &self,
+        mut request: TransactionRequest,
+    ) -> SignerClientResult<Transaction> {
+        self.fill_transaction(&mut request).await?;
+
+        let result = self.sign_filled_transaction(request).await;
+        if result.is_err() {
+            self.reset_nonce().await;
+        }
+        result
+    }
+
+    /// Fills the missing fields of the transaction request, signs it and sends it.
+    ///
+    /// Returns the hash of the transaction.
+    pub async fn send_transaction(&self, request: TransactionRequest) -> SignerClientResult<H256> {
+        let transaction = self.sign_transaction(request).await?;
+
+        let result = self.client.send_raw_transaction(transaction).await;
+        if result.is_err() {
+            self.reset_nonce().await;
+        }
+        Ok(result?)
+    }
+
+    async fn sign_filled_transaction(
+        &self,
+        request: TransactionRequest,
+    ) -> SignerClientResult<Transaction> {
+        let to = match request.to {
+            Some(to) => Some(*to.as_address().ok_or_else(|| {
+                SignerClientError::InvalidRequest("ENS names are not supported".to_string())
+            })?),
+            None => None,
+        };
+        let from = request.from.unwrap_or_default().into();
+        let builder = TransactionBuilder {
+            from: &from,
+            to: to.map(Into::into),
+            nonce: request.nonce.unwrap_or_default().into(),
+            value: request.value.unwrap_or_default().into(),
+            gas: request.gas.unwrap_or_default().into(),
+            gas_price: request.gas_price.map(Into::into),
+            input: request.data.unwrap_or_default().to_vec(),
+            signature: SigningMethod::None,
+            chain_id: request.chain_id.unwrap_or_default().as_u64(),
+        };
+
+        let unsigned: Transaction = builder.clone().calculate_hash_and_build()?.into();
+        let mut typed_transaction: TypedTransaction = (&unsigned).into();
+        typed_transaction.set_chain_id(builder.chain_id);
+        let signature = self.signer.sign_transaction(&typed_transaction).await?;
+
+        Ok(TransactionBuilder {
+            signature: SigningMethod::Signature(signature.into()),
+            ..builder
+        }
+        .calculate_hash_and_build()?
+        .into())
+    }
+
+    async fn chain_id(&self) -> SignerClientResult<u64> {
+        let mut chain_id = self.chain_id.lock().await;
+        match *chain_id {
+            Some(chain_id) => Ok(chain_id),
+            None => {
+                let fetched = self.client.get_chain_id().await?;
+                *chain_id = Some(fetched);
+                Ok(fetched)
+            }
+        }
+    }
+
+    /// Returns the next nonce of the signer and increments it.
+    async fn next_nonce(&self, from: H160) -> SignerClientResult<U256> {
+        let mut next_nonce = self.next_nonce.lock().await;
+        let nonce = match *next_nonce {
+            Some(nonce) => nonce,
+            None => self
+                .client
+                .get_transaction_count(from, BlockNumber::Pending)
+                .await?
+                .into(),
+        };
+        *next_nonce = Some(nonce + 1);
+
+        Ok(nonce)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use eth_signer::sign_strategy::{SigningStrategy, TxSigner};
+    use ethers_core::types::{Bytes, U64};
+    use ethers_core::utils::rlp;
+    use jsonrpc_core::Params;
+
+    use super::*;
+    use crate::mock::MockClient;
+
+    const CHAIN_ID: u64 = 355113;
+
+    fn signer() -> TxSigner {
+        SigningStrategy::Local {
+            private_key: [7; 32],
+        }
+        .make_signer(CHAIN_ID)
+        .unwrap()
+    }
+
+    /// Decodes the raw transactions sent to the node.
+    fn sent_transactions(mock: &MockClient) -> Vec<Transaction> {
+        mock.calls_to("eth_sendRawTransaction")
+            .into_iter()
+            .map(|params| {
+                let Params::Array(params) = params else {
+                    panic!("unexpected params");
+                };
+                let bytes: Bytes = serde_json::from_value(params[0].clone()).unwrap();
+                rlp::decode(&bytes).unwrap()
+            })
+            .collect()
+    }
+
+    fn node() -> MockClient {
+        let mock = MockClient::new();
+        mock.on("eth_chainId", U64::from(CHAIN_ID))
+            .on("eth_getTransactionCount", U64::from(7))
+            .on("eth_gasPrice", U256::from(10))
+            .on("eth_estimateGas", U256::from(21_000))
+            .on("eth_sendRawTransaction", H256::zero());
+        mock
+    }
+
+    #[tokio::test]
+    async fn should_fill_sign_and_send_transaction() {
+        let mock = node();
+        let client = SignerClient::new(EthJsonRpcClient::new(mock.clone()), signer());
+        let to = H160::repeat_byte(1);
+
+        client
+            .send_transaction(TransactionRequest::new().to(to).value(100))
+            .await
+            .unwrap();
+
+        let transaction = &sent_transactions(&mock)[0];
+        assert_eq!(transaction.to, Some(to));
+        assert_eq!(transaction.value, 100.into());
+        assert_eq!(transaction.nonce, 7.into());
+        assert_eq!(transaction.gas, 21_000.into());
+        assert_eq!(transaction.gas_price, Some(10.into()));
+        assert_eq!(
+            transaction.recover_from().unwrap(),
+            client.address().await.unwrap()
+        );
+        // EIP-155 signature
+        assert!(matches!(transaction.v.as_u64() - CHAIN_ID * 2, 35 | 36));
+        mock.assert_called_with(
+            "eth_getTransactionCount",
+            Params::Array(vec![
+                serde_json::to_value(client.address().await.unwrap()).unwrap(),
+                "pending".into(),
+            ]),
+        );
+    }
+
+    #[tokio::test]
+    async fn should_assign_consecutive_nonces_to_concurrent_sends() {
+        let mock = node();
+        let client = SignerClient::new(EthJsonRpcClient::new(mock.clone()), signer())
+            .with_chain_id(CHAIN_ID);
+
+        let results = futures::future::join_all(
+            (0..3).map(|_| client.send_transaction(TransactionRequest::new().to(H160::zero()))),
+        )
+        .await;
+
+        assert!(results.iter().all(Result::is_ok));
+        let mut nonces: Vec<_> = sent_transactions(&mock)
+            .iter()
+            .map(|transaction| transaction.nonce.as_u64())
+            .collect();
+        nonces.sort();
+        assert_eq!(nonces, vec![7, 8, 9]);
+        mock.assert_called_times("eth_getTransactionCount", 1);
+        mock.assert_not_called("eth_chainId");
+    }
+
+    #[tokio::test]
+    async fn should_reset_nonce_after_failed_send() {
+        let mock = node();
+        let client = SignerClient::new(EthJsonRpcClient::new(mock.clone()), signer());
+        let request = TransactionRequest::new().to(H160::zero());
+
+        client.send_transaction(request.clone()).await.unwrap();
+        mock.on_error(
+            "eth_sendRawTransaction",
+            EthJsonRpcError::JsonRpc {
+                code: -32000,
+                message: "nonce too low".to_string(),
+                data: None,
+            },
+        );
+        client.send_transaction(request.clone()).await.unwrap_err();
+        mock.on("eth_sendRawTransaction", H256::zero());
+        client.send_transaction(request).await.unwrap();
+
+        mock.assert_called_times("eth_getTransactionCount", 2);
+        let nonces: Vec<_> = sent_transactions(&mock)
+            .iter()
+            .map(|transaction| transaction.nonce.as_u64())
+            .collect();
+        assert_eq!(nonces, vec![7, 8, 7]);
+    }
+}
@@ -0,0 +1,106 @@
+use std::fmt;
+
+use futures::future::BoxFuture;
+use futures::{SinkExt, StreamExt};
+use tokio::net::TcpStream;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+
+use crate::pubsub::{Connection, ConnectionConfig, Transport, TransportClient};
+use crate::{EthJsonRpcError, EthJsonRpcResult};
+
+/// Connection settings of the [`WebSocketClient`].
+pub type WebSocketConfig = ConnectionConfig;
+
+/// A JSON-RPC client over a WebSocket connection.
+///
+/// See [`TransportClient`] for the reconnection and multiplexing behaviour.
+pub type WebSocketClient = TransportClient<WebSocketTransport>;
+
+impl WebSocketClient {
+    /// Connects to the given WebSocket endpoint with the default configuration.
+    ///
+    /// # Arguments
+    /// * `url` - The WebSocket endpoint, e.g. `ws://127.0.0.1:8546`.
+    pub async fn connect(url: impl Into<String>) -> EthJsonRpcResult<Self> {
+        Self::connect_with_config(url, WebSocketConfig::default()).await
+    }
+
+    /// Connects to the given WebSocket endpoint.
+    ///
+    /// Must be called within a tokio runtime, which runs the connection task.
+    ///
+    /// # Arguments
+    /// * `url` - The WebSocket endpoint, e.g. `ws://127.0.0.1:8546`.
+    /// * `config` - The connection settings.
+    pub async fn connect_with_config(
+        url: impl Into<String>,
+        config: WebSocketConfig,
+    ) -> EthJsonRpcResult<Self> {
+        Self::connect_with_transport(WebSocketTransport { url: url.into() }, config).await
+    }
+}
+
+/// Opens the connections of the [`WebSocketClient`].
+pub struct WebSocketTransport {
+    url: String,
+}
+
+impl fmt::Display for WebSocketTransport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.url)
+    }
+}
+
+impl Transport for WebSocketTransport {
+    type Connection = WebSocketConnection;
+
+    const NAME: &'static str = "WebSocketClient";
+
+    fn connect(&self) -> BoxFuture<'_, EthJsonRpcResult<Self::Connection>> {
+        Box::pin(async move {
+            let (stream, _) = tokio_tungstenite::connect_async(&self.url)
+                .await
+                .map_err(|e| EthJsonRpcError::Transport(e.to_string()))?;
+
+            Ok(WebSocketConnection(stream))
+        })
+    }
+}
+
+/// An open WebSocket connection, exchanging a message per text frame.
+pub struct WebSocketConnection(WebSocketStream<MaybeTlsStream<TcpStream>>);
+
+impl Connection for WebSocketConnection {
+    fn send(&mut self, message: String) -> BoxFuture<'_, EthJsonRpcResult<()>> {
+        Box::pin(async move {
+            self.0
+                .send(Message::Text(message))
+                .await
+                .map_err(|e| EthJsonRpcError::Transport(e.to_string()))
+        })
+    }
+
+    fn receive(&mut self) -> BoxFuture<'_, Option<EthJsonRpcResult<String>>> {
+        Box::pin(async move {
+            loop {
+                match self.0.next().await? {
+                    Ok(Message::Text(text)) => return Some(Ok(text)),
+                    Ok(Message::Binary(bytes)) => match String::from_utf8(bytes) {
+                        Ok(text) => return Some(Ok(text)),
+                        Err(e) => log::warn!("WebSocketClient - invalid binary message: {e}"),
+                    },
+                    Ok(Message::Close(_)) => return None,
+                    Ok(_) => {}
+                    Err(e) => return Some(Err(EthJsonRpcError::Transport(e.to_string()))),
+                }
+            }
+        })
+    }
+
+    fn close(&mut self) -> BoxFuture<'_, ()> {
+        Box::pin(async move {
+            let _ = self.0.close(None).await;
+        })
+    }
+}
@@ -1,2 +1,8 @@
+#[cfg(all(unix, feature = "ipc"))]
+mod ipc;
+#[cfg(feature = "reqwest")]
+mod mock_server;
 #[cfg(feature = "reqwest")]
 mod reqwest;
+#[cfg(feature = "websocket")]
+mod websocket;
@@ -0,0 +1,135 @@
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+use ethereum_json_rpc_client::ipc::{IpcClient, IpcConfig};
+use ethereum_json_rpc_client::{EthJsonRpcClient, EthJsonRpcError};
+use jsonrpc_core::{Id, MethodCall, Params};
+use tempfile::TempDir;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::unix::OwnedWriteHalf;
+use tokio::net::{UnixListener, UnixStream};
+use tokio::sync::Mutex;
+
+/// Delay of the responses to the `slow` method.
+const SLOW_RESPONSE_DELAY: Duration = Duration::from_millis(100);
+
+/// Starts a server which answers every call with the name of its method.
+///
+/// The `slow` method is answered after [`SLOW_RESPONSE_DELAY`], and the
+/// `disconnect` method closes the connection without answering.
+fn start_server(path: &Path) {
+    let listener = UnixListener::bind(path).unwrap();
+    tokio::spawn(async move {
+        loop {
+            let (stream, _) = listener.accept().await.unwrap();
+            tokio::spawn(serve(stream));
+        }
+    });
+}
+
+async fn serve(stream: UnixStream) {
+    let (reader, writer) = stream.into_split();
+    let writer = Arc::new(Mutex::new(writer));
+    let mut lines = BufReader::new(reader).lines();
+
+    while let Ok(Some(line)) = lines.next_line().await {
+        let call: MethodCall = serde_json::from_str(&line).unwrap();
+        if call.method == "disconnect" {
+            return;
+        }
+
+        let writer = writer.clone();
+        tokio::spawn(async move {
+            if call.method == "slow" {
+                tokio::time::sleep(SLOW_RESPONSE_DELAY).await;
+            }
+            respond(&writer, call).await;
+        });
+    }
+}
+
+async fn respond(writer: &Mutex<OwnedWriteHalf>, call: MethodCall) {
+    let response = serde_json::json!({
+        "jsonrpc": "2.0",
+        "result": call.method,
+        "id": call.id,
+    });
+    let message = format!("{response}\n");
+    writer
+        .lock()
+        .await
+        .write_all(message.as_bytes())
+        .await
+        .unwrap();
+}
+
+async fn client(dir: &TempDir) -> EthJsonRpcClient<IpcClient> {
+    let path = dir.path().join("evmc.ipc");
+    start_server(&path);
+    let config = IpcConfig::default()
+        .with_reconnect_delay(Duration::from_millis(10))
+        .with_request_timeout(Duration::from_secs(5));
+
+    EthJsonRpcClient::new(IpcClient::connect_with_config(path, config).await.unwrap())
+}
+
+async fn call(
+    client: &EthJsonRpcClient<IpcClient>,
+    method: &str,
+) -> Result<String, EthJsonRpcError> {
+    client
+        .single_request::<String>(method.to_string(), Params::Array(vec![]), Id::Num(1))
+        .await
+}
+
+#[tokio::test]
+async fn should_send_requests_over_unix_socket() {
+    let dir = TempDir::new().unwrap();
+    let client = client(&dir).await;
+
+    assert_eq!(call(&client, "eth_chainId").await.unwrap(), "eth_chainId");
+}
+
+#[tokio::test]
+async fn should_multiplex_concurrent_requests() {
+    let dir = TempDir::new().unwrap();
+    let client = client(&dir).await;
+
+    // Both calls use the same id, and the first response arrives last
+    let (slow, fast) = tokio::join!(call(&client, "slow"), async {
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        call(&client, "fast").await
+    });
+
+    assert_eq!(slow.unwrap(), "slow");
+    assert_eq!(fast.unwrap(), "fast");
+}
+
+#[tokio::test]
+async fn should_reconnect_after_connection_is_lost() {
+    let dir = TempDir::new().unwrap();
+    let client = client(&dir).await;
+
+    let result = call(&client, "disconnect").await;
+    assert!(matches!(result, Err(EthJsonRpcError::Transport(_))));
+
+    let mut result = call(&client, "eth_chainId").await;
+    for _ in 0..50 {
+        if result.is_ok() {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        result = call(&client, "eth_chainId").await;
+    }
+    assert_eq!(result.unwrap(), "eth_chainId");
+}
+
+#[tokio::test]
+async fn should_fail_to_connect_to_missing_socket() {
+    let dir = TempDir::new().unwrap();
+
+    let result = IpcClient::connect(dir.path().join("missing.ipc")).await;
+
+    assert!(matches!(result, Err(EthJsonRpcError::Transport(_))));
+}
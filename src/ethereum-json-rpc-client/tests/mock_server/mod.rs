@@ -0,0 +1,560 @@
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use did::trace::{BuiltinTracer, CallFrame, StructLoggerResult, TraceOptions};
+use ethereum_json_rpc_client::reqwest::reqwest::header::{HeaderName, HeaderValue};
+use ethereum_json_rpc_client::reqwest::ReqwestClient;
+use ethereum_json_rpc_client::{EthJsonRpcClient, EthJsonRpcError};
+use ethers_core::types::{
+    Block, BlockNumber, FeeHistory, Transaction, TransactionRequest, H160, H256, U256, U64,
+};
+use jsonrpsee::core::RpcResult;
+use jsonrpsee::server::{Server, ServerHandle};
+use jsonrpsee::types::Params;
+use jsonrpsee::RpcModule;
+use serde_json::Value;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+use tokio::task::JoinHandle;
+
+const MAX_BATCH_SIZE: usize = 2;
+const CLIENT_VERSION: &str = "EVMC/v0.20.0";
+const NETWORK_ID: &str = "355113";
+
+/// Hash of the only transaction known by the mock server.
+fn known_transaction_hash() -> H256 {
+    H256::from_low_u64_be(1)
+}
+
+/// Hash of the only block known by the mock server.
+fn known_block_hash() -> H256 {
+    H256::from_low_u64_be(2)
+}
+
+fn transaction(hash: H256) -> Transaction {
+    Transaction {
+        hash,
+        block_hash: Some(known_block_hash()),
+        gas: 21000.into(),
+        ..Default::default()
+    }
+}
+
+fn block(full: bool) -> Value {
+    let transactions = vec![transaction(known_transaction_hash())];
+    if full {
+        serde_json::to_value(Block {
+            hash: Some(known_block_hash()),
+            number: Some(7.into()),
+            transactions,
+            ..Default::default()
+        })
+    } else {
+        serde_json::to_value(Block {
+            hash: Some(known_block_hash()),
+            number: Some(7.into()),
+            transactions: transactions.iter().map(|tx| tx.hash).collect(),
+            ..Default::default()
+        })
+    }
+    .unwrap()
+}
+
+fn get_transaction_by_hash(params: Params) -> RpcResult<Option<Transaction>> {
+    let (hash,): (H256,) = params.parse()?;
+    Ok((hash == known_transaction_hash()).then(|| transaction(hash)))
+}
+
+fn get_block_by_hash(params: Params) -> RpcResult<Option<Value>> {
+    let (hash, full): (H256, bool) = params.parse()?;
+    Ok((hash == known_block_hash()).then(|| block(full)))
+}
+
+fn get_block_transaction_count_by_number(params: Params) -> RpcResult<Option<U64>> {
+    let (block,): (BlockNumber,) = params.parse()?;
+    Ok((block == BlockNumber::Number(7.into())).then(U64::one))
+}
+
+fn get_block_transaction_count_by_hash(params: Params) -> RpcResult<Option<U64>> {
+    let (hash,): (H256,) = params.parse()?;
+    Ok((hash == known_block_hash()).then(U64::one))
+}
+
+fn get_storage_at(params: Params) -> RpcResult<H256> {
+    let (_address, slot, _block): (H160, H256, BlockNumber) = params.parse()?;
+    // Every slot stores its own index plus one
+    Ok(H256::from_low_u64_be(slot.to_low_u64_be() + 1))
+}
+
+fn estimate_gas(params: Params) -> RpcResult<U256> {
+    let (request,): (TransactionRequest,) = params.parse()?;
+    let data_gas = request.data.map(|data| data.len() as u64 * 16).unwrap_or(0);
+    Ok((21000 + data_gas).into())
+}
+
+fn fee_history(params: Params) -> RpcResult<FeeHistory> {
+    let (block_count, newest_block, percentiles): (U64, BlockNumber, Vec<f64>) = params.parse()?;
+    let newest_block = newest_block.as_number().unwrap_or(U64::from(100));
+    let block_count = block_count.as_u64();
+    Ok(FeeHistory {
+        base_fee_per_gas: (0..=block_count).map(|_| 7.into()).collect(),
+        gas_used_ratio: (0..block_count).map(|_| 0.5).collect(),
+        oldest_block: (newest_block.as_u64() + 1 - block_count).into(),
+        reward: (0..block_count)
+            .map(|_| percentiles.iter().map(|p| (*p as u64).into()).collect())
+            .collect(),
+    })
+}
+
+fn call_frame() -> CallFrame {
+    CallFrame {
+        call_type: "CALL".to_string(),
+        from: H160::from_low_u64_be(1).into(),
+        to: Some(H160::from_low_u64_be(2).into()),
+        value: None,
+        gas: 30000u64.into(),
+        gas_used: 25000u64.into(),
+        input: vec![0xa9, 0x05, 0x9c, 0xbb].into(),
+        output: None,
+        error: Some("execution reverted".to_string()),
+        revert_reason: Some("insufficient balance".to_string()),
+        calls: vec![],
+        logs: vec![],
+    }
+}
+
+fn struct_logger_result() -> StructLoggerResult {
+    StructLoggerResult {
+        failed: false,
+        gas: 21000,
+        return_value: String::new(),
+        struct_logs: vec![],
+    }
+}
+
+fn trace(options: &TraceOptions) -> Value {
+    match options.tracer {
+        Some(BuiltinTracer::CallTracer) => serde_json::to_value(call_frame()),
+        None => serde_json::to_value(struct_logger_result()),
+    }
+    .unwrap()
+}
+
+fn debug_trace_transaction(params: Params) -> RpcResult<Value> {
+    let (_hash, options): (H256, TraceOptions) = params.parse()?;
+    Ok(trace(&options))
+}
+
+fn debug_trace_block_by_number(params: Params) -> RpcResult<Vec<Value>> {
+    let (_block, options): (BlockNumber, TraceOptions) = params.parse()?;
+    Ok(vec![serde_json::json!({
+        "txHash": known_transaction_hash(),
+        "result": trace(&options),
+    })])
+}
+
+async fn start_server() -> (SocketAddr, ServerHandle) {
+    let mut module = RpcModule::new(());
+    module
+        .register_method("eth_getTransactionByHash", |params, _| {
+            get_transaction_by_hash(params)
+        })
+        .unwrap();
+    module
+        .register_method("eth_getBlockByHash", |params, _| get_block_by_hash(params))
+        .unwrap();
+    module
+        .register_method("eth_getBlockTransactionCountByNumber", |params, _| {
+            get_block_transaction_count_by_number(params)
+        })
+        .unwrap();
+    module
+        .register_method("eth_getBlockTransactionCountByHash", |params, _| {
+            get_block_transaction_count_by_hash(params)
+        })
+        .unwrap();
+    module
+        .register_method("eth_getStorageAt", |params, _| get_storage_at(params))
+        .unwrap();
+    module
+        .register_method("eth_estimateGas", |params, _| estimate_gas(params))
+        .unwrap();
+    module
+        .register_method("eth_feeHistory", |params, _| fee_history(params))
+        .unwrap();
+    module
+        .register_method("eth_maxPriorityFeePerGas", |_, _| "0x3b9aca00")
+        .unwrap();
+    module
+        .register_method("debug_traceTransaction", |params, _| {
+            debug_trace_transaction(params)
+        })
+        .unwrap();
+    module
+        .register_method("debug_traceBlockByNumber", |params, _| {
+            debug_trace_block_by_number(params)
+        })
+        .unwrap();
+    module
+        .register_method("net_version", |_, _| NETWORK_ID)
+        .unwrap();
+    module
+        .register_method("web3_clientVersion", |_, _| CLIENT_VERSION)
+        .unwrap();
+
+    let server = Server::builder().build("127.0.0.1:0").await.unwrap();
+    let addr = server.local_addr().unwrap();
+
+    (addr, server.start(module))
+}
+
+async fn client() -> (EthJsonRpcClient<ReqwestClient>, ServerHandle) {
+    let (addr, server) = start_server().await;
+    let client = EthJsonRpcClient::new(ReqwestClient::new(format!("http://{addr}")));
+
+    (client, server)
+}
+
+#[tokio::test]
+async fn should_get_transaction_by_hash() {
+    let (client, _server) = client().await;
+
+    let transaction = client
+        .get_transaction_by_hash(known_transaction_hash())
+        .await
+        .unwrap();
+    assert_eq!(transaction.hash, known_transaction_hash());
+    assert_eq!(transaction.gas, 21000.into());
+
+    let missing = client
+        .get_transaction_by_hash(H256::from_low_u64_be(42))
+        .await;
+    assert_eq!(
+        missing,
+        Err(EthJsonRpcError::NotFound("transaction".to_string()))
+    );
+}
+
+#[tokio::test]
+async fn should_get_transactions_by_hash() {
+    let (client, _server) = client().await;
+
+    let transactions = client
+        .get_transactions_by_hash(
+            [
+                known_transaction_hash(),
+                H256::from_low_u64_be(42),
+                known_transaction_hash(),
+            ],
+            MAX_BATCH_SIZE,
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(transactions.len(), 3);
+    assert_eq!(
+        transactions[0].as_ref().unwrap().hash,
+        known_transaction_hash()
+    );
+    assert_eq!(
+        transactions[1],
+        Err(EthJsonRpcError::NotFound("transaction".to_string()))
+    );
+    assert_eq!(
+        transactions[2].as_ref().unwrap().hash,
+        known_transaction_hash()
+    );
+}
+
+#[tokio::test]
+async fn should_get_block_by_hash() {
+    let (client, _server) = client().await;
+
+    let block = client.get_block_by_hash(known_block_hash()).await.unwrap();
+    assert_eq!(block.number, Some(7.into()));
+    assert_eq!(block.transactions, vec![known_transaction_hash()]);
+
+    let full_block = client
+        .get_full_block_by_hash(known_block_hash())
+        .await
+        .unwrap();
+    assert_eq!(full_block.transactions[0].gas, 21000.into());
+
+    let missing = client.get_block_by_hash(H256::from_low_u64_be(42)).await;
+    assert_eq!(missing, Err(EthJsonRpcError::NotFound("block".to_string())));
+}
+
+#[tokio::test]
+async fn should_get_full_blocks_by_hash() {
+    let (client, _server) = client().await;
+
+    let blocks = client
+        .get_full_blocks_by_hash(vec![known_block_hash(); 3], MAX_BATCH_SIZE)
+        .await
+        .unwrap();
+    assert_eq!(blocks.len(), 3);
+    assert!(blocks
+        .iter()
+        .all(|block| block.hash == Some(known_block_hash())));
+
+    let missing = client
+        .get_full_blocks_by_hash(
+            [known_block_hash(), H256::from_low_u64_be(42)],
+            MAX_BATCH_SIZE,
+        )
+        .await;
+    assert_eq!(missing, Err(EthJsonRpcError::NotFound("block".to_string())));
+}
+
+#[tokio::test]
+async fn should_get_block_transaction_count() {
+    let (client, _server) = client().await;
+
+    let by_number = client
+        .get_block_transaction_count_by_number(BlockNumber::Number(7.into()))
+        .await
+        .unwrap();
+    assert_eq!(by_number, 1);
+
+    let by_hash = client
+        .get_block_transaction_count_by_hash(known_block_hash())
+        .await
+        .unwrap();
+    assert_eq!(by_hash, 1);
+
+    let missing = client
+        .get_block_transaction_count_by_hash(H256::from_low_u64_be(42))
+        .await;
+    assert_eq!(missing, Err(EthJsonRpcError::NotFound("block".to_string())));
+}
+
+#[tokio::test]
+async fn should_get_storage_at() {
+    let (client, _server) = client().await;
+
+    let value = client
+        .get_storage_at(
+            H160::from_low_u64_be(1),
+            H256::from_low_u64_be(4),
+            BlockNumber::Latest,
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(value, H256::from_low_u64_be(5));
+}
+
+#[tokio::test]
+async fn should_estimate_gas() {
+    let (client, _server) = client().await;
+
+    let gas = client
+        .estimate_gas(TransactionRequest::new().data(vec![1, 2, 3]))
+        .await
+        .unwrap();
+
+    assert_eq!(gas, (21000 + 3 * 16).into());
+}
+
+#[tokio::test]
+async fn should_get_fee_history() {
+    let (client, _server) = client().await;
+
+    let history = client
+        .fee_history(4, BlockNumber::Number(10.into()), &[25.0, 75.0])
+        .await
+        .unwrap();
+
+    assert_eq!(history.oldest_block, 7.into());
+    assert_eq!(history.base_fee_per_gas.len(), 5);
+    assert_eq!(history.gas_used_ratio.len(), 4);
+    assert_eq!(history.reward[0], vec![25.into(), 75.into()]);
+}
+
+#[tokio::test]
+async fn should_get_max_priority_fee_per_gas() {
+    let (client, _server) = client().await;
+
+    let fee = client.max_priority_fee_per_gas().await.unwrap();
+
+    assert_eq!(fee, 1_000_000_000u64.into());
+}
+
+#[tokio::test]
+async fn should_get_node_info() {
+    let (client, _server) = client().await;
+
+    assert_eq!(client.net_version().await.unwrap(), NETWORK_ID);
+    assert_eq!(client.web3_client_version().await.unwrap(), CLIENT_VERSION);
+}
+
+#[tokio::test]
+async fn should_batch_new_methods() {
+    let (client, _server) = client().await;
+    let mut batch = client.batch();
+
+    let transaction = batch
+        .get_transaction_by_hash(known_transaction_hash())
+        .unwrap();
+    let block = batch.get_block_by_hash(H256::from_low_u64_be(42)).unwrap();
+    let storage = batch
+        .get_storage_at(H160::from_low_u64_be(1), H256::zero(), BlockNumber::Latest)
+        .unwrap();
+    let gas = batch.estimate_gas(TransactionRequest::new()).unwrap();
+    let fee = batch.max_priority_fee_per_gas().unwrap();
+
+    let response = batch.send(MAX_BATCH_SIZE).await.unwrap();
+
+    assert_eq!(
+        response.get(&transaction).unwrap().unwrap().hash,
+        known_transaction_hash()
+    );
+    assert_eq!(response.get(&block).unwrap(), None);
+    assert_eq!(response.get(&storage).unwrap(), H256::from_low_u64_be(1));
+    assert_eq!(response.get(&gas).unwrap(), 21000.into());
+    assert_eq!(response.get(&fee).unwrap(), 1_000_000_000u64.into());
+}
+
+#[tokio::test]
+async fn should_trace_transaction() {
+    let (client, _server) = client().await;
+
+    let calls = client
+        .trace_transaction_calls(known_transaction_hash())
+        .await
+        .unwrap();
+    assert_eq!(calls, call_frame());
+
+    let struct_logs: StructLoggerResult = client
+        .debug_trace_transaction(known_transaction_hash(), TraceOptions::struct_logger())
+        .await
+        .unwrap();
+    assert_eq!(struct_logs, struct_logger_result());
+}
+
+#[tokio::test]
+async fn should_trace_block() {
+    let (client, _server) = client().await;
+
+    let traces = client.trace_block_calls(BlockNumber::Latest).await.unwrap();
+
+    assert_eq!(traces.len(), 1);
+    assert_eq!(traces[0].tx_hash, Some(known_transaction_hash().into()));
+    assert_eq!(traces[0].result, Some(call_frame()));
+}
+
+/// Accepts a single connection, answers `eth_blockNumber` and returns the head
+/// of the received HTTP request, lowercased.
+async fn serve_once() -> (SocketAddr, JoinHandle<String>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let handle = tokio::spawn(async move {
+        let (mut stream, _) = listener.accept().await.unwrap();
+        let mut request = Vec::new();
+        let (head, body) = loop {
+            let mut buf = [0; 1024];
+            let read = stream.read(&mut buf).await.unwrap();
+            request.extend_from_slice(&buf[..read]);
+            let text = String::from_utf8_lossy(&request).to_lowercase();
+            if let Some((head, body)) = text.split_once("\r\n\r\n") {
+                let length = head
+                    .lines()
+                    .find_map(|line| line.strip_prefix("content-length: "))
+                    .and_then(|length| length.parse::<usize>().ok())
+                    .unwrap_or_default();
+                if body.len() >= length {
+                    break (head.to_string(), body.to_string());
+                }
+            }
+        };
+
+        let request: Value = serde_json::from_str(&body).unwrap();
+        let response =
+            serde_json::json!({ "jsonrpc": "2.0", "result": "0x7", "id": request["id"] })
+                .to_string();
+        let response = format!(
+            "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{response}",
+            response.len()
+        );
+        stream.write_all(response.as_bytes()).await.unwrap();
+        head
+    });
+
+    (addr, handle)
+}
+
+#[tokio::test]
+async fn should_send_bearer_auth_and_custom_headers() {
+    let (addr, server) = serve_once().await;
+    let client = EthJsonRpcClient::new(
+        ReqwestClient::new(format!("http://{addr}"))
+            .with_bearer_auth("secret")
+            .with_header(
+                HeaderName::from_static("x-api-key"),
+                HeaderValue::from_static("key"),
+            ),
+    );
+
+    assert_eq!(client.get_block_number().await.unwrap(), 7);
+
+    let head = server.await.unwrap();
+    assert!(head.contains("authorization: bearer secret"));
+    assert!(head.contains("x-api-key: key"));
+    let accept_encoding = head
+        .lines()
+        .find_map(|line| line.strip_prefix("accept-encoding: "))
+        .unwrap();
+    assert!(accept_encoding.contains("gzip") && accept_encoding.contains("br"));
+}
+
+#[tokio::test]
+async fn should_send_basic_auth() {
+    let (addr, server) = serve_once().await;
+    let client = EthJsonRpcClient::new(
+        ReqwestClient::new(format!("http://{addr}")).with_basic_auth("user", Some("pass")),
+    );
+
+    assert_eq!(client.get_block_number().await.unwrap(), 7);
+
+    let head = server.await.unwrap();
+    // base64 of "user:pass"
+    assert!(head.contains("authorization: basic dxnlcjpwyxnz"));
+}
+
+#[tokio::test]
+async fn should_reject_responses_larger_than_limit() {
+    let (addr, _server) = start_server().await;
+    let client = |limit| {
+        EthJsonRpcClient::new(
+            ReqwestClient::new(format!("http://{addr}")).with_max_response_size(limit),
+        )
+    };
+
+    let result = client(64).get_full_block_by_hash(known_block_hash()).await;
+    assert_eq!(result, Err(EthJsonRpcError::ResponseTooLarge { limit: 64 }));
+
+    client(64 * 1024)
+        .get_full_block_by_hash(known_block_hash())
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn should_time_out_requests() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let _server = tokio::spawn(async move {
+        // Accept the connection and never answer
+        let connection = listener.accept().await.unwrap();
+        tokio::time::sleep(Duration::from_secs(60)).await;
+        drop(connection);
+    });
+    let client = EthJsonRpcClient::new(
+        ReqwestClient::new(format!("http://{addr}")).with_timeout(Duration::from_millis(100)),
+    );
+
+    let result = client.get_block_number().await;
+
+    assert!(matches!(result, Err(EthJsonRpcError::Transport(_))));
+}
@@ -1,5 +1,5 @@
 use ethereum_json_rpc_client::reqwest::ReqwestClient;
-use ethereum_json_rpc_client::{EthGetLogsParams, EthJsonRpcClient};
+use ethereum_json_rpc_client::{EthGetLogsParams, EthJsonRpcClient, LogsBlockFilter};
 use ethers_core::abi::{Function, Param, ParamType, StateMutability, Token};
 use ethers_core::types::{BlockNumber, Log, TransactionRequest, H160, H256, U256};
 
@@ -210,24 +210,26 @@ async fn should_get_logs() {
         address: Some(vec!["0xb59f67a8bff5d8cd03f6ac17265c550ed8f33907"
             .parse()
             .unwrap()]),
-        from_block: "0x429d3b".parse().unwrap(),
-        to_block: BlockNumber::Latest,
+        block_filter: LogsBlockFilter::Range {
+            from_block: Some("0x429d3b".parse().unwrap()),
+            to_block: Some(BlockNumber::Latest),
+        },
         topics: Some(vec![
-            vec![
+            Some(vec![
                 "0xddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef"
                     .parse()
                     .unwrap(),
-            ],
-            vec![
+            ]),
+            Some(vec![
                 "0x00000000000000000000000000b46c2526e227482e2ebb8f4c69e4674d262e75"
                     .parse()
                     .unwrap(),
-            ],
-            vec![
+            ]),
+            Some(vec![
                 "0x00000000000000000000000054a2d42a40f51259dedd1978f6c118a0f0eff078"
                     .parse()
                     .unwrap(),
-            ],
+            ]),
         ]),
     };
 
@@ -270,8 +272,8 @@ async fn should_get_transaction_receipts() {
         )
         .await
         .unwrap();
-    assert_eq!(receipts[0].gas_used, Some(21000.into()));
-    assert_eq!(receipts[1].gas_used, Some(52358.into()));
+    assert_eq!(receipts[0].as_ref().unwrap().gas_used, Some(21000.into()));
+    assert_eq!(receipts[1].as_ref().unwrap().gas_used, Some(52358.into()));
 }
 
 const ERC_1820_EXPECTED_CODE: &str = "0x608060405234801561001057600080fd5b50600436106100a557600035\
@@ -0,0 +1,176 @@
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use ethereum_json_rpc_client::websocket::{WebSocketClient, WebSocketConfig};
+use ethereum_json_rpc_client::{EthGetLogsParams, EthJsonRpcClient, LogsBlockFilter};
+use ethers_core::types::{Block, BlockNumber, Log, Transaction, H160, H256};
+use futures::StreamExt;
+use jsonrpsee::core::{RpcResult, SubscriptionResult};
+use jsonrpsee::server::{Server, ServerHandle};
+use jsonrpsee::{PendingSubscriptionSink, RpcModule, SubscriptionMessage};
+use serde_json::Value;
+
+const BLOCK_NUMBER: u64 = 42;
+
+fn block(number: u64) -> Block<H256> {
+    Block {
+        number: Some(number.into()),
+        hash: Some(H256::from_low_u64_be(number)),
+        ..Default::default()
+    }
+}
+
+/// Emits a new head every few milliseconds, or a log from the first filtered address.
+async fn eth_subscribe(
+    params: jsonrpsee::types::Params<'static>,
+    pending: PendingSubscriptionSink,
+) -> SubscriptionResult {
+    let mut params = params.sequence();
+    let kind: String = params.next()?;
+    let filter: Option<Value> = params.optional_next()?;
+    let sink = pending.accept().await?;
+
+    for number in 1.. {
+        let message = match kind.as_str() {
+            "newHeads" => SubscriptionMessage::from_json(&block(number))?,
+            "logs" => {
+                let filter = filter.clone().unwrap_or_default();
+                assert!(filter.get("fromBlock").is_none());
+                let address = serde_json::from_value::<Vec<H160>>(filter["address"].clone())?;
+                SubscriptionMessage::from_json(&Log {
+                    address: address[0],
+                    block_number: Some(number.into()),
+                    ..Default::default()
+                })?
+            }
+            _ => return Err("unsupported subscription".into()),
+        };
+
+        sink.send(message).await?;
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+
+    Ok(())
+}
+
+async fn start_server(addr: SocketAddr) -> (SocketAddr, ServerHandle) {
+    let mut module = RpcModule::new(());
+    module
+        .register_method("eth_blockNumber", |_, _| format!("{BLOCK_NUMBER:#x}"))
+        .unwrap();
+    module
+        .register_method("eth_getBlockByNumber", |params, _| {
+            let (number, _full): (BlockNumber, bool) = params.parse().unwrap();
+            RpcResult::Ok(Block::<Transaction> {
+                number: number.as_number(),
+                ..Default::default()
+            })
+        })
+        .unwrap();
+    module
+        .register_subscription(
+            "eth_subscribe",
+            "eth_subscription",
+            "eth_unsubscribe",
+            |params, pending, _| eth_subscribe(params, pending),
+        )
+        .unwrap();
+
+    let server = Server::builder().build(addr).await.unwrap();
+    let addr = server.local_addr().unwrap();
+
+    (addr, server.start(module))
+}
+
+async fn client(addr: SocketAddr) -> EthJsonRpcClient<WebSocketClient> {
+    let config = WebSocketConfig::default().with_reconnect_delay(Duration::from_millis(50));
+    EthJsonRpcClient::new(
+        WebSocketClient::connect_with_config(format!("ws://{addr}"), config)
+            .await
+            .unwrap(),
+    )
+}
+
+#[tokio::test]
+async fn should_send_requests() {
+    let (addr, _server) = start_server(([127, 0, 0, 1], 0).into()).await;
+    let client = client(addr).await;
+
+    let (block_number, blocks) = tokio::join!(
+        client.get_block_number(),
+        client.get_full_blocks_by_number((1..=5).map(|n| BlockNumber::Number(n.into())), 2)
+    );
+
+    assert_eq!(block_number.unwrap(), BLOCK_NUMBER);
+    let numbers = blocks
+        .unwrap()
+        .into_iter()
+        .map(|block| block.number.unwrap().as_u64())
+        .collect::<Vec<_>>();
+    assert_eq!(numbers, vec![1, 2, 3, 4, 5]);
+}
+
+#[tokio::test]
+async fn should_subscribe_to_new_heads() {
+    let (addr, _server) = start_server(([127, 0, 0, 1], 0).into()).await;
+    let client = client(addr).await;
+
+    let heads = client.subscribe_new_heads().await.unwrap();
+    let numbers = heads
+        .take(3)
+        .map(|block| block.unwrap().number.unwrap().as_u64())
+        .collect::<Vec<_>>()
+        .await;
+
+    assert_eq!(numbers, vec![1, 2, 3]);
+}
+
+#[tokio::test]
+async fn should_subscribe_to_logs() {
+    let (addr, _server) = start_server(([127, 0, 0, 1], 0).into()).await;
+    let client = client(addr).await;
+    let address = H160::from_low_u64_be(7);
+
+    let mut logs = client
+        .subscribe_logs(EthGetLogsParams {
+            address: Some(vec![address]),
+            block_filter: LogsBlockFilter::Range {
+                from_block: Some(BlockNumber::Latest),
+                to_block: Some(BlockNumber::Latest),
+            },
+            topics: None,
+        })
+        .await
+        .unwrap();
+
+    let log = logs.next().await.unwrap().unwrap();
+    assert_eq!(log.address, address);
+}
+
+#[tokio::test]
+async fn should_resubscribe_after_reconnection() {
+    let (addr, server) = start_server(([127, 0, 0, 1], 0).into()).await;
+    let client = client(addr).await;
+
+    let mut heads = client.subscribe_new_heads().await.unwrap();
+    assert_eq!(heads.next().await.unwrap().unwrap().number, Some(1.into()));
+
+    server.stop().unwrap();
+    server.stopped().await;
+    let (_, _server) = start_server(addr).await;
+
+    // The new server starts counting from the first block again
+    let block = tokio::time::timeout(Duration::from_secs(5), async {
+        loop {
+            let block = heads.next().await.unwrap().unwrap();
+            if block.number == Some(1.into()) {
+                return block;
+            }
+        }
+    })
+    .await
+    .unwrap();
+    assert_eq!(block.hash, Some(H256::from_low_u64_be(1)));
+
+    assert_eq!(client.get_block_number().await.unwrap(), BLOCK_NUMBER);
+}
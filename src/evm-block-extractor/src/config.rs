@@ -1,6 +1,9 @@
 use std::sync::Arc;
+use std::time::Duration;
 
 use clap::{Parser, Subcommand};
+use ethereum_json_rpc_client::reqwest::reqwest::header::{HeaderName, HeaderValue};
+use ethereum_json_rpc_client::reqwest::ReqwestClient;
 use sqlx::postgres::{PgConnectOptions, PgSslMode};
 use sqlx::PgPool;
 
@@ -25,6 +28,36 @@ pub struct ExtractorArgs {
     #[arg(long = "rpc-url", short('u'), default_value = None)]
     pub remote_rpc_url: Option<String>,
 
+    /// Comma separated JSON-RPC URLs of other EVMC instances to use
+    /// when the main one is not available.
+    #[arg(long = "fallback-rpc-urls", value_delimiter = ',')]
+    pub fallback_rpc_urls: Vec<String>,
+
+    /// Bearer token to authenticate the requests to the main EVMC.
+    /// It is not sent to the fallback EVMCs.
+    #[arg(long, env = "RPC_BEARER_TOKEN", hide_env_values = true)]
+    pub rpc_bearer_token: Option<String>,
+
+    /// Credentials to authenticate the requests to the main EVMC with basic authentication,
+    /// in the `username:password` format. They are not sent to the fallback EVMCs.
+    #[arg(
+        long,
+        env = "RPC_BASIC_AUTH",
+        hide_env_values = true,
+        conflicts_with = "rpc_bearer_token"
+    )]
+    pub rpc_basic_auth: Option<String>,
+
+    /// Header added to the requests to the main EVMC, in the `name: value` format.
+    /// It is not sent to the fallback EVMCs. Can be repeated.
+    #[arg(long = "rpc-header", value_parser = parse_header)]
+    pub rpc_headers: Vec<(HeaderName, HeaderValue)>,
+
+    /// The maximum size in bytes of the responses of the EVMC.
+    /// If missing, the size of the responses is not limited.
+    #[arg(long)]
+    pub max_response_size: Option<usize>,
+
     /// Time in seconds to wait for a response from the EVMC
     #[arg(long, default_value = "60")]
     pub request_time_out_secs: u64,
@@ -32,6 +65,20 @@ pub struct ExtractorArgs {
     #[arg(long, default_value = "10")]
     pub rpc_batch_size: usize,
 
+    /// The maximum number of batch requests sent in parallel to the EVMC
+    #[arg(long, default_value = "1")]
+    pub max_number_of_requests: usize,
+
+    /// The maximum number of requests per second sent to the EVMC.
+    /// If missing, the requests are not rate limited.
+    #[arg(long)]
+    pub max_requests_per_second: Option<u32>,
+
+    /// The maximum number of batch items per second sent to the EVMC.
+    /// If missing, the batch items are not rate limited.
+    #[arg(long)]
+    pub max_batch_items_per_second: Option<u32>,
+
     /// Sets the logger [`EnvFilter`].
     /// Valid values: trace, debug, info, warn, error
     /// Example of a valid filter: "warn,my_crate=info,my_crate::my_mod=debug,[my_span]=trace".
@@ -51,6 +98,53 @@ pub struct ExtractorArgs {
     pub block_extractor_job_interval_seconds: u64,
 }
 
+impl ExtractorArgs {
+    /// Build the HTTP client of the main EVMC JSON-RPC endpoint,
+    /// with the credentials and the custom headers
+    pub fn rpc_client(&self, endpoint_url: String) -> ReqwestClient {
+        let mut client = self.fallback_rpc_client(endpoint_url);
+
+        if let Some(token) = &self.rpc_bearer_token {
+            client = client.with_bearer_auth(token);
+        }
+        if let Some(credentials) = &self.rpc_basic_auth {
+            client = match credentials.split_once(':') {
+                Some((username, password)) => client.with_basic_auth(username, Some(password)),
+                None => client.with_basic_auth(credentials, None::<String>),
+            };
+        }
+        for (name, value) in &self.rpc_headers {
+            client = client.with_header(name.clone(), value.clone());
+        }
+
+        client
+    }
+
+    /// Build the HTTP client of a fallback EVMC JSON-RPC endpoint,
+    /// without the credentials and the custom headers of the main one
+    pub fn fallback_rpc_client(&self, endpoint_url: String) -> ReqwestClient {
+        let mut client = ReqwestClient::new(endpoint_url)
+            .with_timeout(Duration::from_secs(self.request_time_out_secs));
+
+        if let Some(max_response_size) = self.max_response_size {
+            client = client.with_max_response_size(max_response_size);
+        }
+
+        client
+    }
+}
+
+/// Parses a header in the `name: value` format
+fn parse_header(header: &str) -> anyhow::Result<(HeaderName, HeaderValue)> {
+    let (name, value) = header
+        .split_once(':')
+        .ok_or_else(|| anyhow::anyhow!("header must be in the `name: value` format"))?;
+    let mut value = HeaderValue::from_str(value.trim())?;
+    value.set_sensitive(true);
+
+    Ok((HeaderName::from_bytes(name.trim().as_bytes())?, value))
+}
+
 #[derive(Subcommand, Debug, Clone)]
 pub enum Database {
     #[command(name = "--postgres")]
@@ -4,7 +4,8 @@ use std::time::Duration;
 
 use clap::Parser;
 use env_logger::Builder;
-use ethereum_json_rpc_client::reqwest::ReqwestClient;
+use ethereum_json_rpc_client::multi_endpoint::MultiEndpointClient;
+use ethereum_json_rpc_client::rate_limit::{RateLimitedClient, RateLimits};
 use ethereum_json_rpc_client::EthJsonRpcClient;
 use evm_block_extractor::config::ExtractorArgs;
 use evm_block_extractor::server::{server_start, server_stop};
@@ -25,7 +26,37 @@ async fn main() -> Result<(), Box<dyn Error>> {
     info!("----------------------");
     info!("- server_address: {}", config.server_address);
     info!("- remote_rpc_url: {:?}", config.remote_rpc_url);
+    info!("- fallback_rpc_urls: {:?}", config.fallback_rpc_urls);
     info!("- rpc_batch_size: {}", config.rpc_batch_size);
+    info!(
+        "- max_number_of_requests: {}",
+        config.max_number_of_requests
+    );
+    info!(
+        "- max_requests_per_second: {:?}",
+        config.max_requests_per_second
+    );
+    info!(
+        "- max_batch_items_per_second: {:?}",
+        config.max_batch_items_per_second
+    );
+    info!(
+        "- rpc_auth: {}",
+        match (&config.rpc_bearer_token, &config.rpc_basic_auth) {
+            (Some(_), _) => "bearer",
+            (_, Some(_)) => "basic",
+            _ => "none",
+        }
+    );
+    info!(
+        "- rpc_headers: {:?}",
+        config
+            .rpc_headers
+            .iter()
+            .map(|(name, _)| name)
+            .collect::<Vec<_>>()
+    );
+    info!("- max_response_size: {:?}", config.max_response_size);
     info!("- request_time_out_secs: {}", config.request_time_out_secs);
     info!(
         "- reset_db_on_state_change: {}",
@@ -39,7 +70,25 @@ async fn main() -> Result<(), Box<dyn Error>> {
 
     // Configure and start the block extractor task
     if let Some(rpc_url) = config.remote_rpc_url.clone() {
-        let evm_client = Arc::new(EthJsonRpcClient::new(ReqwestClient::new(rpc_url)));
+        let endpoints = std::iter::once(config.rpc_client(rpc_url))
+            .chain(
+                config
+                    .fallback_rpc_urls
+                    .iter()
+                    .map(|url| config.fallback_rpc_client(url.clone())),
+            )
+            .collect();
+        let rate_limits = RateLimits {
+            requests_per_second: config.max_requests_per_second,
+            batch_items_per_second: config.max_batch_items_per_second,
+            max_concurrent_requests: None,
+        };
+        let rpc_client =
+            RateLimitedClient::new(MultiEndpointClient::failover(endpoints), rate_limits);
+        let evm_client = Arc::new(
+            EthJsonRpcClient::new(rpc_client)
+                .with_max_concurrent_batches(config.max_number_of_requests),
+        );
         let config = config.clone();
         let evm_client = evm_client.clone();
         let db_client = db_client.clone();
@@ -1,7 +1,6 @@
 use std::sync::Arc;
 
-use ethereum_json_rpc_client::reqwest::ReqwestClient;
-use ethereum_json_rpc_client::EthJsonRpcClient;
+use ethereum_json_rpc_client::{Client, EthJsonRpcClient};
 use ethers_core::types::BlockNumber;
 use log::*;
 use tokio::time::Duration;
@@ -10,10 +9,10 @@ use crate::config::ExtractorArgs;
 use crate::database::{AccountBalance, CertifiedBlock, DatabaseClient};
 
 /// Starts the block extractor process
-pub async fn start_extractor(
+pub async fn start_extractor<C: Client>(
     config: ExtractorArgs,
     db_client: Arc<dyn DatabaseClient>,
-    evm_client: Arc<EthJsonRpcClient<ReqwestClient>>,
+    evm_client: Arc<EthJsonRpcClient<C>>,
 ) -> anyhow::Result<()> {
     let earliest_block = evm_client
         .get_block_by_number(BlockNumber::Earliest)
@@ -44,16 +43,16 @@ pub async fn start_extractor(
 }
 
 /// Extracts blocks from an EVMC and stores them in a database
-pub struct BlockExtractor {
-    client: Arc<EthJsonRpcClient<ReqwestClient>>,
+pub struct BlockExtractor<C: Client> {
+    client: Arc<EthJsonRpcClient<C>>,
     request_time_out_secs: u64,
     rpc_batch_size: usize,
     blockchain: Arc<dyn DatabaseClient>,
 }
 
-impl BlockExtractor {
+impl<C: Client> BlockExtractor<C> {
     pub fn new(
-        client: Arc<EthJsonRpcClient<ReqwestClient>>,
+        client: Arc<EthJsonRpcClient<C>>,
         request_time_out_secs: u64,
         rpc_batch_size: usize,
         blockchain: Arc<dyn DatabaseClient>,
@@ -86,11 +85,13 @@ impl BlockExtractor {
 
         let request_time_out_secs = self.request_time_out_secs;
         let batch_size = self.rpc_batch_size;
+        // Fetch enough blocks to keep all the concurrent batch requests busy
+        let blocks_per_request = batch_size * client.max_concurrent_batches();
 
         let mut next_from = from_block_inclusive;
 
         while next_from <= to_block_inclusive {
-            let to = (to_block_inclusive + 1).min(next_from + batch_size as u64);
+            let to = (to_block_inclusive + 1).min(next_from + blocks_per_request as u64);
             let blocks_batch = next_from..to;
             next_from = to;
 
@@ -198,3 +199,244 @@ impl BlockExtractor {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use std::sync::Mutex;
+
+    use did::{Block, Transaction, H256};
+    use ethereum_json_rpc_client::mock::MockClient;
+    use ethereum_json_rpc_client::EthJsonRpcError;
+    use ethers_core::types::{
+        Block as EthersBlock, Transaction as EthersTransaction, H160, H256 as EthersH256, U256, U64,
+    };
+    use jsonrpc_core::Params;
+
+    use super::*;
+
+    #[derive(Default)]
+    struct InMemoryState {
+        blocks: BTreeMap<u64, Block<H256>>,
+        transactions: Vec<Transaction>,
+        certified_block: Option<CertifiedBlock>,
+        genesis_balances: Option<Vec<AccountBalance>>,
+        chain_id: Option<u64>,
+    }
+
+    /// A database client keeping the data in memory.
+    #[derive(Default)]
+    struct InMemoryDatabaseClient {
+        state: Mutex<InMemoryState>,
+    }
+
+    #[async_trait::async_trait]
+    impl DatabaseClient for InMemoryDatabaseClient {
+        async fn init(&self, _block: Option<Block<H256>>, _reset: bool) -> anyhow::Result<()> {
+            Ok(())
+        }
+
+        async fn clear(&self) -> anyhow::Result<()> {
+            *self.state.lock().unwrap() = InMemoryState::default();
+            Ok(())
+        }
+
+        async fn get_block_by_number(&self, block_number: u64) -> anyhow::Result<Block<H256>> {
+            let state = self.state.lock().unwrap();
+            state
+                .blocks
+                .get(&block_number)
+                .cloned()
+                .ok_or_else(|| anyhow::anyhow!("block {block_number} not found"))
+        }
+
+        async fn get_full_block_by_number(
+            &self,
+            block_number: u64,
+        ) -> anyhow::Result<Block<Transaction>> {
+            Err(anyhow::anyhow!(
+                "full block {block_number} not available in memory"
+            ))
+        }
+
+        async fn insert_block_data(
+            &self,
+            blocks: &[Block<H256>],
+            transactions: &[Transaction],
+        ) -> anyhow::Result<()> {
+            let mut state = self.state.lock().unwrap();
+            for block in blocks {
+                state.blocks.insert(block.number.0.as_u64(), block.clone());
+            }
+            state.transactions.extend_from_slice(transactions);
+            Ok(())
+        }
+
+        async fn insert_certified_block_data(
+            &self,
+            response: CertifiedBlock,
+        ) -> anyhow::Result<()> {
+            self.state.lock().unwrap().certified_block = Some(response);
+            Ok(())
+        }
+
+        async fn get_last_certified_block_data(&self) -> anyhow::Result<CertifiedBlock> {
+            let state = self.state.lock().unwrap();
+            state
+                .certified_block
+                .clone()
+                .ok_or_else(|| anyhow::anyhow!("certified block not found"))
+        }
+
+        async fn get_genesis_balances(&self) -> anyhow::Result<Option<Vec<AccountBalance>>> {
+            Ok(self.state.lock().unwrap().genesis_balances.clone())
+        }
+
+        async fn insert_genesis_balances(
+            &self,
+            genesis_balances: &[AccountBalance],
+        ) -> anyhow::Result<()> {
+            self.state.lock().unwrap().genesis_balances = Some(genesis_balances.to_vec());
+            Ok(())
+        }
+
+        async fn get_chain_id(&self) -> anyhow::Result<Option<u64>> {
+            Ok(self.state.lock().unwrap().chain_id)
+        }
+
+        async fn insert_chain_id(&self, chain_id: u64) -> anyhow::Result<()> {
+            self.state.lock().unwrap().chain_id = Some(chain_id);
+            Ok(())
+        }
+
+        async fn get_transaction(&self, tx_hash: H256) -> anyhow::Result<Transaction> {
+            let state = self.state.lock().unwrap();
+            state
+                .transactions
+                .iter()
+                .find(|transaction| transaction.hash == tx_hash)
+                .cloned()
+                .ok_or_else(|| anyhow::anyhow!("transaction {tx_hash} not found"))
+        }
+
+        async fn get_latest_block_number(&self) -> anyhow::Result<Option<u64>> {
+            Ok(self.state.lock().unwrap().blocks.keys().last().copied())
+        }
+
+        async fn get_earliest_block_number(&self) -> anyhow::Result<u64> {
+            Ok(self
+                .state
+                .lock()
+                .unwrap()
+                .blocks
+                .keys()
+                .next()
+                .copied()
+                .unwrap_or_default())
+        }
+    }
+
+    /// Returns a mock EVMC answering with a block containing one transaction for each block number.
+    fn mock_evm() -> MockClient {
+        let mock = MockClient::new();
+        mock.on("eth_chainId", U64::from(355113))
+            .on(
+                "ic_getGenesisBalances",
+                vec![(H160::from_low_u64_be(1), U256::from(100))],
+            )
+            .on(
+                "ic_getLastCertifiedBlock",
+                did::certified::CertifiedResult {
+                    data: EthersBlock::<EthersH256>::default(),
+                    witness: vec![1],
+                    certificate: vec![2],
+                },
+            )
+            .on_call("eth_getBlockByNumber", |params| {
+                let Params::Array(params) = params else {
+                    panic!("unexpected params");
+                };
+                let number: U64 = serde_json::from_value(params[0].clone())?;
+                let hash = EthersH256::from_low_u64_be(number.as_u64() + 1);
+
+                Ok(EthersBlock {
+                    number: Some(number),
+                    hash: Some(hash),
+                    transactions: vec![EthersTransaction {
+                        hash: EthersH256::from_low_u64_be(number.as_u64() + 1000),
+                        block_hash: Some(hash),
+                        block_number: Some(number),
+                        ..Default::default()
+                    }],
+                    ..Default::default()
+                })
+            });
+        mock
+    }
+
+    #[tokio::test]
+    async fn should_collect_blocks() {
+        let mock = mock_evm();
+        let db_client = Arc::new(InMemoryDatabaseClient::default());
+        let evm_client = Arc::new(EthJsonRpcClient::new(mock.clone()));
+        let mut extractor = BlockExtractor::new(evm_client, 10, 3, db_client.clone());
+
+        let result = extractor.collect_all(5, 14).await.unwrap();
+
+        assert_eq!(result, (5, 14));
+        mock.assert_called_times("eth_getBlockByNumber", 10);
+        // 4 batches plus the chain id, genesis balances and certified block requests
+        assert_eq!(mock.request_count(), 7);
+
+        let state = db_client.state.lock().unwrap();
+        assert_eq!(
+            state.blocks.keys().copied().collect::<Vec<_>>(),
+            (5..=14).collect::<Vec<_>>()
+        );
+        assert_eq!(state.transactions.len(), 10);
+        assert_eq!(state.chain_id, Some(355113));
+        assert_eq!(state.genesis_balances.as_ref().unwrap().len(), 1);
+        assert_eq!(state.certified_block.as_ref().unwrap().certificate, vec![2]);
+    }
+
+    #[tokio::test]
+    async fn should_skip_stored_chain_id_and_missing_genesis_balances() {
+        let mock = mock_evm();
+        mock.on_error(
+            "ic_getGenesisBalances",
+            EthJsonRpcError::JsonRpc {
+                code: -32601,
+                message: "Method not found".to_string(),
+                data: None,
+            },
+        );
+        let db_client = Arc::new(InMemoryDatabaseClient::default());
+        db_client.insert_chain_id(1).await.unwrap();
+        let evm_client = Arc::new(EthJsonRpcClient::new(mock.clone()));
+        let mut extractor = BlockExtractor::new(evm_client, 10, 3, db_client.clone());
+
+        extractor.collect_all(0, 0).await.unwrap();
+
+        mock.assert_not_called("eth_chainId");
+        mock.assert_called_times("ic_getGenesisBalances", 1);
+        let state = db_client.state.lock().unwrap();
+        assert_eq!(state.chain_id, Some(1));
+        assert_eq!(state.genesis_balances, None);
+        assert_eq!(state.blocks.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn should_fail_on_block_request_errors() {
+        let mock = mock_evm();
+        mock.on_error(
+            "eth_getBlockByNumber",
+            EthJsonRpcError::Transport("connection reset".to_string()),
+        );
+        let db_client = Arc::new(InMemoryDatabaseClient::default());
+        let evm_client = Arc::new(EthJsonRpcClient::new(mock.clone()));
+        let mut extractor = BlockExtractor::new(evm_client, 10, 3, db_client.clone());
+
+        assert!(extractor.collect_all(0, 5).await.is_err());
+        assert!(db_client.state.lock().unwrap().blocks.is_empty());
+    }
+}
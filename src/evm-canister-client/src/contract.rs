@@ -0,0 +1,228 @@
+pub use did::contract::{Contract, ContractError, ContractResult, DecodedEvent};
+use did::error::EvmError;
+use did::{Bytes, TransactionReceipt, H160};
+use ethers_core::abi::Token;
+use ic_canister_client::{CanisterClient, CanisterClientResult};
+
+use crate::{EvmCanisterClient, EvmResult};
+
+/// Default gas limit of the calls.
+pub const DEFAULT_GAS_LIMIT: u64 = 30_000_000;
+
+/// A client to call a [`Contract`] deployed on the EVM canister.
+#[derive(Debug, Clone)]
+pub struct ContractClient<C: CanisterClient> {
+    client: EvmCanisterClient<C>,
+    contract: Contract,
+    from: Option<H160>,
+    gas_limit: u64,
+}
+
+impl<C: CanisterClient> ContractClient<C> {
+    /// Creates a new client.
+    ///
+    /// # Arguments
+    /// * `client` - The client of the EVM canister.
+    /// * `contract` - The contract to call.
+    pub fn new(client: EvmCanisterClient<C>, contract: Contract) -> Self {
+        Self {
+            client,
+            contract,
+            from: None,
+            gas_limit: DEFAULT_GAS_LIMIT,
+        }
+    }
+
+    /// Sets the sender of the calls.
+    pub fn with_from(mut self, from: H160) -> Self {
+        self.from = Some(from);
+        self
+    }
+
+    /// Sets the gas limit of the calls.
+    pub fn with_gas_limit(mut self, gas_limit: u64) -> Self {
+        self.gas_limit = gas_limit;
+        self
+    }
+
+    /// Returns the client of the EVM canister.
+    pub fn client(&self) -> &EvmCanisterClient<C> {
+        &self.client
+    }
+
+    /// Returns the contract.
+    pub fn contract(&self) -> &Contract {
+        &self.contract
+    }
+
+    /// Calls `function` with `args` using `eth_call` and returns the decoded output.
+    pub async fn call(
+        &self,
+        function: &str,
+        args: &[Token],
+    ) -> CanisterClientResult<EvmResult<Vec<Token>>> {
+        let data = match self.input(function, args) {
+            Ok(data) => data,
+            Err(err) => return Ok(Err(err)),
+        };
+        let output = match self
+            .client
+            .eth_call(
+                self.from.clone(),
+                Some(self.contract.address().clone()),
+                None,
+                self.gas_limit,
+                None,
+                Some(data.into()),
+            )
+            .await?
+        {
+            Ok(output) => output,
+            Err(err) => return Ok(Err(err)),
+        };
+
+        Ok(Bytes::from_hex_str(&output)
+            .map_err(|e| EvmError::Internal(format!("invalid call output: {e}")))
+            .and_then(|output| Ok(self.contract.decode_output(function, &output.0)?)))
+    }
+
+    /// Returns the input of a transaction calling `function` with `args`,
+    /// e.g. for the `TransactionBuilder` of `eth-signer`.
+    pub fn input(&self, function: &str, args: &[Token]) -> EvmResult<Vec<u8>> {
+        Ok(self.contract.encode_call(function, args)?)
+    }
+
+    /// Decodes the events emitted by the contract in the transaction of the receipt.
+    ///
+    /// The logs of other contracts and the events missing from the ABI are skipped.
+    pub fn events(&self, receipt: &TransactionReceipt) -> EvmResult<Vec<DecodedEvent>> {
+        let mut events = Vec::new();
+        for log in receipt
+            .logs
+            .iter()
+            .filter(|log| &log.address == self.contract.address())
+        {
+            match self.contract.decode_receipt_log(log) {
+                Ok(event) => events.push(event),
+                Err(ContractError::UnknownEvent) => {}
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        Ok(events)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use did::transaction::TransactionReceiptLog;
+    use ethers_core::abi::ethabi;
+    use ethers_core::types::U256;
+
+    use super::*;
+    use crate::mock::MockCanisterClient;
+
+    const ABI: &str = r#"[
+        {
+            "type": "function",
+            "name": "balanceOf",
+            "stateMutability": "view",
+            "inputs": [{ "name": "owner", "type": "address" }],
+            "outputs": [{ "name": "", "type": "uint256" }]
+        },
+        {
+            "type": "event",
+            "name": "Approval",
+            "anonymous": false,
+            "inputs": [{ "name": "value", "type": "uint256", "indexed": false }]
+        }
+    ]"#;
+
+    fn contract_client(mock: &MockCanisterClient) -> ContractClient<MockCanisterClient> {
+        let contract = Contract::from_json(H160::from_slice(&[1; 20]), ABI).unwrap();
+        ContractClient::new(EvmCanisterClient::new(mock.clone()), contract)
+    }
+
+    fn call_output(value: u64) -> EvmResult<String> {
+        Ok(Bytes::from(ethabi::encode(&[Token::Uint(U256::from(value))])).to_hex_str())
+    }
+
+    #[tokio::test]
+    async fn should_call_contract_function() {
+        let mock = MockCanisterClient::new();
+        mock.on("eth_call", call_output(42));
+        let client = contract_client(&mock);
+        let owner = Token::Address(H160::from_slice(&[2; 20]).into());
+
+        let result = client.call("balanceOf", &[owner]).await.unwrap();
+
+        assert_eq!(result, Ok(vec![Token::Uint(42.into())]));
+        assert_eq!(mock.calls_to("eth_call"), 1);
+    }
+
+    #[tokio::test]
+    async fn should_not_call_unknown_function() {
+        let mock = MockCanisterClient::new();
+        let client = contract_client(&mock);
+
+        let result = client.call("transfer", &[]).await.unwrap();
+
+        assert_eq!(
+            result,
+            Err(ContractError::UnknownFunction("transfer".to_string()).into())
+        );
+        assert_eq!(mock.calls_to("eth_call"), 0);
+    }
+
+    #[tokio::test]
+    async fn should_return_call_errors() {
+        let mock = MockCanisterClient::new();
+        let error: EvmResult<String> = Err(EvmError::TransactionReverted("denied".to_string()));
+        mock.on("eth_call", error.clone());
+        let owner = Token::Address(H160::from_slice(&[2; 20]).into());
+
+        let result = contract_client(&mock)
+            .call("balanceOf", &[owner])
+            .await
+            .unwrap();
+
+        assert_eq!(result, error.map(|_| vec![]));
+    }
+
+    #[test]
+    fn should_decode_receipt_events() {
+        let client = contract_client(&MockCanisterClient::new());
+        let approval = client
+            .contract()
+            .abi()
+            .event("Approval")
+            .unwrap()
+            .signature();
+        let log = TransactionReceiptLog {
+            address: client.contract().address().clone(),
+            topics: vec![approval.into()],
+            data: Bytes::from(ethabi::encode(&[Token::Uint(U256::from(5))])),
+            ..Default::default()
+        };
+        let receipt = TransactionReceipt {
+            logs: vec![
+                log.clone(),
+                TransactionReceiptLog {
+                    address: H160::from_slice(&[3; 20]),
+                    ..log.clone()
+                },
+                TransactionReceiptLog {
+                    topics: vec![Default::default()],
+                    ..log
+                },
+            ],
+            ..Default::default()
+        };
+
+        let events = client.events(&receipt).unwrap();
+
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].name, "Approval");
+        assert_eq!(events[0].param("value"), Some(&Token::Uint(5.into())));
+    }
+}
@@ -1,5 +1,10 @@
 pub mod client;
+pub mod contract;
 pub mod error;
+#[cfg(test)]
+mod mock;
+#[cfg(feature = "pending-transaction")]
+pub mod pending_transaction;
 
 pub use client::EvmCanisterClient;
 pub use error::EvmResult;
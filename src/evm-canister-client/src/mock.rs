@@ -0,0 +1,92 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use candid::utils::ArgumentEncoder;
+use candid::CandidType;
+use ic_canister_client::{CanisterClient, CanisterClientResult};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+
+type Handler = Arc<dyn Fn() -> Value + Send + Sync>;
+
+/// A [`CanisterClient`] returning the responses configured for each method.
+///
+/// The responses are stored as JSON and deserialized into the type expected by the
+/// caller, so the arguments of the calls are ignored.
+/// Calling a method without a configured response panics.
+#[derive(Clone, Default)]
+pub struct MockCanisterClient {
+    handlers: Arc<Mutex<HashMap<String, Handler>>>,
+    calls: Arc<Mutex<Vec<String>>>,
+}
+
+impl MockCanisterClient {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns `response` to every call of `method`.
+    pub fn on(&self, method: &str, response: impl Serialize) -> &Self {
+        let response = serde_json::to_value(response).expect("failed to serialize response");
+        self.on_call(method, move || response.clone())
+    }
+
+    /// Returns the result of `handler` to every call of `method`.
+    pub fn on_call<R: Serialize>(
+        &self,
+        method: &str,
+        handler: impl Fn() -> R + Send + Sync + 'static,
+    ) -> &Self {
+        let handler =
+            move || serde_json::to_value(handler()).expect("failed to serialize response");
+        self.handlers
+            .lock()
+            .unwrap()
+            .insert(method.to_string(), Arc::new(handler));
+        self
+    }
+
+    /// Returns the number of calls to `method`.
+    pub fn calls_to(&self, method: &str) -> usize {
+        self.calls
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|call| *call == method)
+            .count()
+    }
+
+    fn call<R: DeserializeOwned>(&self, method: &str) -> CanisterClientResult<R> {
+        self.calls.lock().unwrap().push(method.to_string());
+        let handler = self
+            .handlers
+            .lock()
+            .unwrap()
+            .get(method)
+            .cloned()
+            .unwrap_or_else(|| panic!("unexpected call to {method}"));
+
+        Ok(serde_json::from_value(handler())
+            .unwrap_or_else(|e| panic!("invalid response to {method}: {e}")))
+    }
+}
+
+#[async_trait::async_trait]
+impl CanisterClient for MockCanisterClient {
+    async fn update<T, R>(&self, method: &str, _args: T) -> CanisterClientResult<R>
+    where
+        T: ArgumentEncoder + Send + Sync,
+        R: DeserializeOwned + CandidType,
+    {
+        self.call(method)
+    }
+
+    async fn query<T, R>(&self, method: &str, _args: T) -> CanisterClientResult<R>
+    where
+        T: ArgumentEncoder + Send + Sync,
+        R: DeserializeOwned + CandidType,
+    {
+        self.call(method)
+    }
+}
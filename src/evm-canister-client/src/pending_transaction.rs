@@ -0,0 +1,190 @@
+//! Waiting for the transactions sent to the EVM canister.
+//!
+//! The transaction status is polled with canister queries by the [`PendingTransaction`]
+//! of the `did` crate, shared with the JSON-RPC client.
+
+use did::error::EvmError;
+use did::pending_transaction::TransactionStatusProvider;
+pub use did::pending_transaction::{
+    DEFAULT_DROPPED_AFTER_POLLS, DEFAULT_POLL_INTERVAL, DEFAULT_TIMEOUT,
+};
+use did::{BlockNumber, Transaction, TransactionReceipt, H160, H256, U256};
+use ic_canister_client::{CanisterClient, CanisterClientError, CanisterClientResult};
+use thiserror::Error;
+
+use crate::{EvmCanisterClient, EvmResult};
+
+/// A transaction sent to the EVM canister, which can be awaited for its receipt.
+pub type PendingTransaction<'a, C> =
+    did::pending_transaction::PendingTransaction<'a, EvmCanisterClient<C>>;
+
+/// Errors returned while waiting for a [`PendingTransaction`].
+pub type PendingTransactionError = did::pending_transaction::PendingTransactionError<EvmCallError>;
+
+/// Result of waiting for a [`PendingTransaction`].
+pub type PendingTransactionResult<T> = Result<T, PendingTransactionError>;
+
+/// Error of a query polling the transaction status.
+#[derive(Debug, Error)]
+pub enum EvmCallError {
+    /// The call to the EVM canister failed.
+    #[error(transparent)]
+    Canister(#[from] CanisterClientError),
+
+    /// The EVM canister returned an error.
+    #[error(transparent)]
+    Evm(#[from] EvmError),
+}
+
+impl<C: CanisterClient> TransactionStatusProvider for EvmCanisterClient<C> {
+    type Receipt = TransactionReceipt;
+    type Error = EvmCallError;
+
+    async fn transaction_receipt(
+        &self,
+        tx_hash: &H256,
+    ) -> Result<Option<TransactionReceipt>, EvmCallError> {
+        Ok(self.eth_get_transaction_receipt(tx_hash.clone()).await??)
+    }
+
+    fn receipt_block_number(receipt: &TransactionReceipt) -> Option<u64> {
+        Some(receipt.block_number.into())
+    }
+
+    async fn transaction_sender(
+        &self,
+        tx_hash: &H256,
+    ) -> Result<Option<(H160, U256)>, EvmCallError> {
+        Ok(self
+            .eth_get_transaction_by_hash(tx_hash.clone())
+            .await?
+            .map(|transaction| (transaction.from, transaction.nonce)))
+    }
+
+    async fn transaction_count(&self, address: &H160) -> Result<U256, EvmCallError> {
+        Ok(self
+            .eth_get_transaction_count(address.clone(), BlockNumber::Latest)
+            .await??)
+    }
+
+    async fn block_number(&self) -> Result<u64, EvmCallError> {
+        Ok(self.eth_block_number().await? as u64)
+    }
+}
+
+impl<C: CanisterClient> EvmCanisterClient<C> {
+    /// Sends a raw transaction to the EVM canister and returns a [`PendingTransaction`]
+    /// to wait for its receipt.
+    ///
+    /// `send_raw_transaction` keeps returning the bare hash, since the pending
+    /// transaction borrows the client and is only built with the `pending-transaction`
+    /// feature.
+    ///
+    /// # Arguments
+    /// * `transaction` - The transaction to send
+    pub async fn send_pending_transaction(
+        &self,
+        transaction: Transaction,
+    ) -> CanisterClientResult<EvmResult<PendingTransaction<'_, C>>> {
+        let sender = (transaction.from.clone(), transaction.nonce.clone());
+        let tx_hash = match self.send_raw_transaction(transaction).await? {
+            Ok(tx_hash) => tx_hash,
+            Err(err) => return Ok(Err(err)),
+        };
+
+        Ok(Ok(
+            PendingTransaction::new(self, tx_hash).with_sender(sender.0, sender.1)
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::mock::MockCanisterClient;
+
+    fn tx_hash() -> H256 {
+        H256::from([1; 32])
+    }
+
+    fn transaction() -> Transaction {
+        Transaction {
+            hash: tx_hash(),
+            from: H160::from_slice(&[2; 20]),
+            nonce: 5u64.into(),
+            ..Default::default()
+        }
+    }
+
+    fn receipt(block_number: u64) -> TransactionReceipt {
+        TransactionReceipt {
+            transaction_hash: tx_hash(),
+            block_number: block_number.into(),
+            status: Some(1u64.into()),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn should_confirm_transaction_sent_to_the_evm_canister() {
+        // A new block is produced at each poll, the transaction is mined in block 10
+        let block_number = Arc::new(AtomicU64::new(8));
+        let mock = MockCanisterClient::new();
+        mock.on("send_raw_transaction", EvmResult::Ok(tx_hash()))
+            .on("eth_get_transaction_by_hash", Some(transaction()))
+            .on("eth_get_transaction_count", EvmResult::Ok(U256::from(5u64)))
+            .on_call("eth_get_transaction_receipt", {
+                let block_number = block_number.clone();
+                move || {
+                    let latest = block_number.fetch_add(1, Ordering::SeqCst) + 1;
+                    EvmResult::Ok((latest >= 10).then(|| receipt(10)))
+                }
+            })
+            .on_call("eth_block_number", move || {
+                block_number.load(Ordering::SeqCst) as usize
+            });
+        let client = EvmCanisterClient::new(mock.clone());
+
+        let receipt = client
+            .send_pending_transaction(transaction())
+            .await
+            .unwrap()
+            .unwrap()
+            .with_confirmations(3)
+            .await
+            .unwrap();
+
+        assert_eq!(receipt.block_number, 10u64.into());
+        assert_eq!(mock.calls_to("send_raw_transaction"), 1);
+        assert_eq!(mock.calls_to("eth_get_transaction_receipt"), 4);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn should_detect_replacement_from_the_evm_nonce() {
+        let mock = MockCanisterClient::new();
+        mock.on("send_raw_transaction", EvmResult::Ok(tx_hash()))
+            .on(
+                "eth_get_transaction_receipt",
+                EvmResult::<Option<TransactionReceipt>>::Ok(None),
+            )
+            .on("eth_get_transaction_by_hash", None::<Transaction>)
+            .on("eth_get_transaction_count", EvmResult::Ok(U256::from(6u64)));
+        let client = EvmCanisterClient::new(mock);
+
+        let err = client
+            .send_pending_transaction(transaction())
+            .await
+            .unwrap()
+            .unwrap()
+            .await
+            .unwrap_err();
+
+        assert!(matches!(
+            err,
+            PendingTransactionError::Replaced { tx_hash: hash, nonce } if hash == tx_hash() && nonce == 5u64.into()
+        ));
+    }
+}
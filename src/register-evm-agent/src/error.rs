@@ -3,6 +3,7 @@ use did::error::EvmError;
 use did::H256;
 use eth_signer::WalletError;
 use evm_canister_client::ic_agent::AgentError;
+use evm_canister_client::pending_transaction::{EvmCallError, PendingTransactionError};
 use evm_canister_client::CanisterClientError;
 use thiserror::Error;
 
@@ -29,6 +30,8 @@ pub enum Error {
     TransactionNotFinalized(H256),
     #[error("transaction failed")]
     TransactionFailed,
+    #[error("transaction not mined: {0}")]
+    TransactionNotMined(PendingTransactionError),
 }
 
 impl From<AgentError> for Error {
@@ -60,3 +63,18 @@ impl From<CanisterClientError> for Error {
         Self::CanisterClient(err)
     }
 }
+
+impl From<PendingTransactionError> for Error {
+    fn from(err: PendingTransactionError) -> Self {
+        match err {
+            PendingTransactionError::Client(EvmCallError::Canister(err)) => {
+                Self::CanisterClient(err)
+            }
+            PendingTransactionError::Client(EvmCallError::Evm(err)) => Self::Evm(err),
+            PendingTransactionError::Timeout { tx_hash, .. } => {
+                Self::TransactionNotFinalized(tx_hash)
+            }
+            err => Self::TransactionNotMined(err),
+        }
+    }
+}
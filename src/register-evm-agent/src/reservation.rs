@@ -1,5 +1,7 @@
+use std::time::Duration;
+
 use candid::Principal;
-use did::{H160, H256, U256};
+use did::{H160, U256};
 use eth_signer::transaction::{SigningMethod, TransactionBuilder};
 use eth_signer::{Signer, Wallet};
 use ethers_core::k256::ecdsa::SigningKey;
@@ -12,6 +14,11 @@ use crate::error::{Error, Result};
 
 type EvmCanisterAgentClient = EvmCanisterClient<IcAgentClient>;
 
+/// Interval between two checks of the reservation transaction status.
+const TX_POLL_INTERVAL: Duration = Duration::from_secs(1);
+/// Time after which the reservation transaction is considered not finalized.
+const TX_TIMEOUT: Duration = Duration::from_secs(10);
+
 pub struct ReservationService<'a> {
     client: EvmCanisterAgentClient,
     amount_to_mint: Option<u64>,
@@ -91,9 +98,20 @@ impl<'a> ReservationService<'a> {
         .calculate_hash_and_build()?;
 
         info!("sending transaction to reserve address...");
-        let tx_hash = self.client.send_raw_transaction(tx).await??;
-
-        self.wait_for_transaction(tx_hash.clone()).await?;
+        let pending_tx = self.client.send_pending_transaction(tx).await??;
+        let tx_hash = pending_tx.tx_hash().clone();
+
+        info!("waiting for transaction to be finalized...");
+        // The transaction may not be returned by hash while it waits in the pool,
+        // so it is awaited until the timeout rather than reported as dropped
+        let receipt = pending_tx
+            .with_poll_interval(TX_POLL_INTERVAL)
+            .with_timeout(TX_TIMEOUT)
+            .with_dropped_after_polls(None)
+            .await?;
+        if receipt.status != Some(1_u64.into()) {
+            return Err(Error::TransactionFailed);
+        }
 
         self.client
             .reserve_address(self.reserve_canister_id, tx_hash)
@@ -138,29 +156,4 @@ impl<'a> ReservationService<'a> {
 
         Ok(())
     }
-
-    /// Wait for the transaction to be finalized
-    async fn wait_for_transaction(&self, tx_hash: H256) -> Result<()> {
-        const MAX_RETRIES: u32 = 10;
-
-        for _ in 0..MAX_RETRIES {
-            info!("waiting for transaction to be finalized...");
-            tokio::time::sleep(std::time::Duration::from_secs(1)).await;
-
-            let tx_receipt = self
-                .client
-                .eth_get_transaction_receipt(tx_hash.clone())
-                .await??;
-
-            if let Some(receipt) = tx_receipt {
-                if receipt.status == Some(1_u64.into()) {
-                    return Ok(());
-                } else {
-                    return Err(Error::TransactionFailed);
-                }
-            }
-        }
-
-        Err(Error::TransactionNotFinalized(tx_hash))
-    }
 }